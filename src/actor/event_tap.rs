@@ -64,6 +64,9 @@ struct State {
     disable_hotkey_active: bool,
     pressed_keys: HashSet<KeyCode>,
     current_flags: CGEventFlags,
+    /// Modifiers of the hotkey driving a `focus_mru_*` cycle; releasing them
+    /// commits the cycle.
+    mru_cycle_modifiers: Option<Modifiers>,
 }
 
 impl Default for State {
@@ -79,6 +82,7 @@ impl Default for State {
             disable_hotkey_active: false,
             pressed_keys: HashSet::default(),
             current_flags: CGEventFlags::empty(),
+            mru_cycle_modifiers: None,
         }
     }
 }
@@ -375,6 +379,14 @@ impl EventTap {
         true
     }
 
+    fn commit_mru_cycle(&self) {
+        if let Some(wm_sender) = &self.wm_sender {
+            wm_sender.send(WmEvent::Command(WmCommand::ReactorCommand(
+                reactor::Command::Layout(LC::FocusMruCommit),
+            )));
+        }
+    }
+
     fn handle_gesture_event(&self, handler: &SwipeHandler, nsevent: &NSEvent) {
         let cfg = &handler.cfg;
         let state = &handler.state;
@@ -511,6 +523,14 @@ impl EventTap {
             }
         }
 
+        if let Some(held) = state.mru_cycle_modifiers {
+            let active = modifiers_from_flags_with_keys(state.current_flags, &state.pressed_keys);
+            if !active.intersects(held) {
+                state.mru_cycle_modifiers = None;
+                self.commit_mru_cycle();
+            }
+        }
+
         if event_type == CGEventType::KeyDown {
             if let Some(key_code) = key_code_opt {
                 let hotkey = Hotkey::new(
@@ -523,9 +543,24 @@ impl EventTap {
                 };
                 if let Some(commands) = commands {
                     if let Some(wm_sender) = &self.wm_sender {
+                        let cycles_mru = commands.iter().any(is_mru_cycle_command);
                         for cmd in commands {
                             wm_sender.send(WmEvent::Command(cmd));
                         }
+                        if cycles_mru {
+                            // Shift usually reverses the cycle, so only the other
+                            // modifiers need to be released to commit it.
+                            let mut held = hotkey.modifiers;
+                            held.remove(Modifiers::SHIFT);
+                            if held == Modifiers::empty() {
+                                held = hotkey.modifiers;
+                            }
+                            if held == Modifiers::empty() {
+                                self.commit_mru_cycle();
+                            } else {
+                                state.mru_cycle_modifiers = Some(held);
+                            }
+                        }
                         return false;
                     } else {
                         debug!(?hotkey, "Hotkey triggered but no WM sender available");
@@ -538,6 +573,13 @@ impl EventTap {
     }
 }
 
+fn is_mru_cycle_command(cmd: &WmCommand) -> bool {
+    matches!(
+        cmd,
+        WmCommand::ReactorCommand(reactor::Command::Layout(LC::FocusMruNext | LC::FocusMruPrev))
+    )
+}
+
 unsafe extern "C-unwind" fn mouse_callback(
     _proxy: CGEventTapProxy,
     event_type: CGEventType,
@@ -31,6 +31,7 @@ use events::system::SystemEventHandler;
 use events::window::WindowEventHandler;
 use main_window::MainWindowTracker;
 use managers::LayoutManager;
+use nix::libc;
 use objc2_core_foundation::{CGPoint, CGRect, CGSize};
 pub use replay::{Record, replay};
 use serde::{Deserialize, Serialize};
@@ -46,12 +47,14 @@ use crate::actor::raise_manager::{self, RaiseManager, RaiseRequest};
 use crate::actor::reactor::events::window_discovery::WindowDiscoveryHandler;
 use crate::actor::{self, corner_indicator, menu_bar, stack_line};
 use crate::common::collections::{BTreeMap, HashMap, HashSet};
-use crate::common::config::Config;
+use crate::common::config::{self, Config, WindowCriteria};
 use crate::common::log::MetricsCommand;
+use crate::layout_engine::snapshot::LayoutSnapshotInfo;
 use crate::layout_engine::{self as layout, Direction, LayoutCommand, LayoutEngine, LayoutEvent};
 use crate::model::VirtualWorkspaceId;
 use crate::model::tx_store::WindowTxStore;
-use crate::model::virtual_workspace::AppRuleResult;
+use crate::model::virtual_workspace::{AppRuleExplanation, AppRuleQuery, AppRuleResult};
+use crate::model::window_identity::{WindowIdentity, WindowMatcher};
 use crate::sys::event::MouseState;
 use crate::sys::executor::Executor;
 use crate::sys::geometry::{CGRectDef, CGRectExt};
@@ -65,6 +68,9 @@ use crate::sys::window_server::{
 pub type Sender = actor::Sender<Event>;
 type Receiver = actor::Receiver<Event>;
 
+/// How long windows of a restored layout have to reappear after startup.
+const RESTORED_WINDOW_GRACE_PERIOD: Duration = Duration::from_secs(60);
+
 use std::path::PathBuf;
 
 use crate::model::server::{
@@ -123,6 +129,9 @@ pub enum Event {
         visible_windows: Vec<(WindowId, WindowInfo)>,
         window_server_info: Vec<WindowServerInfo>,
     },
+    /// An app was launched while rift is running, rather than found running
+    /// at startup. Arrives before the app's `ApplicationLaunched`.
+    ApplicationStarted(pid_t),
     ApplicationTerminated(pid_t),
     ApplicationThreadTerminated(pid_t),
     ApplicationActivated(pid_t, Quiet),
@@ -176,6 +185,13 @@ pub enum Event {
     /// System woke from sleep; used to re-subscribe SLS notifications.
     SystemWoke,
 
+    /// Time to save the layout so it can be restored after a restart.
+    #[serde(skip)]
+    AutosaveLayout,
+    /// Restored windows that have not reappeared by now are forgotten.
+    #[serde(skip)]
+    PruneRestoredWindows,
+
     #[serde(skip)]
     DisplayChurnBegin,
     #[serde(skip)]
@@ -241,6 +257,20 @@ pub enum Event {
     },
     #[serde(skip)]
     QueryMetrics(r#continue::Sender<serde_json::Value>),
+    #[serde(skip)]
+    QueryAppRules {
+        query: AppRuleQuery,
+        #[serde(skip)]
+        response: r#continue::Sender<AppRuleExplanation>,
+    },
+    #[serde(skip)]
+    QueryWindowAppRules {
+        window_id: WindowId,
+        #[serde(skip)]
+        response: r#continue::Sender<Option<AppRuleExplanation>>,
+    },
+    #[serde(skip)]
+    QueryLayoutSnapshots(r#continue::Sender<Vec<LayoutSnapshotInfo>>),
 
     #[serde(skip)]
     ConfigUpdated(Config),
@@ -255,6 +285,26 @@ pub enum Command {
     Layout(LayoutCommand),
     Metrics(MetricsCommand),
     Reactor(ReactorCommand),
+    Targeted(TargetedCommand),
+}
+
+/// Runs `command` once for every managed window matching `target`, as if each
+/// window were focused in turn.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TargetedCommand {
+    pub target: WindowCriteria,
+    pub command: Box<Command>,
+}
+
+impl TargetedCommand {
+    /// Whether the command can be pointed at a given window. Other commands
+    /// act on the focused window or on no window at all, so they are refused.
+    pub fn is_supported(&self) -> bool {
+        matches!(
+            *self.command,
+            Command::Layout(_) | Command::Reactor(ReactorCommand::CloseWindow { .. })
+        )
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -289,6 +339,28 @@ pub enum ReactorCommand {
         selector: DisplaySelector,
         window_id: Option<u32>,
     },
+    /// Switch to a workspace by name. Workspaces pinned to a display are
+    /// switched on that display, which then receives focus.
+    SwitchToNamedWorkspace(String),
+    /// Move the active workspace, with all its windows, to another display.
+    MoveWorkspaceToDisplay {
+        selector: DisplaySelector,
+    },
+    /// Exchange the active workspace with the one shown on another display.
+    SwapWorkspaceWithDisplay {
+        selector: DisplaySelector,
+    },
+    /// Re-evaluate app rules for every managed window, moving windows to the
+    /// workspaces their rules now name.
+    ReapplyAppRules,
+    /// Save the tiled tree of the active workspace under a name.
+    LayoutSave(String),
+    /// Rebuild a saved tree on the active workspace, pulling in the windows it
+    /// held from wherever they are. Leaves without a window are filled by the
+    /// next matching window that opens.
+    LayoutLoad(String),
+    /// Lay out a `[[workspace_templates]]` entry on the active workspace.
+    ApplyTemplate(String),
 }
 
 #[derive(Debug, Clone)]
@@ -498,12 +570,86 @@ impl Reactor {
                 Executor::run(reactor.run(events, events_tx_clone));
             })
             .unwrap();
+        Self::spawn_termination_watcher(events_tx.clone());
         events_tx
     }
 
+    /// Turns SIGTERM and SIGINT, which `launchctl` sends when the service is
+    /// stopped, into `save_and_exit`, so a clean shutdown saves the layout.
+    /// A second signal, or no exit within a few seconds, ends the process
+    /// anyway, so a stuck reactor cannot keep rift from being stopped.
+    fn spawn_termination_watcher(events_tx: Sender) {
+        const SIGNALS: [libc::c_int; 2] = [libc::SIGTERM, libc::SIGINT];
+        const EXIT_TIMEOUT: libc::timespec = libc::timespec { tv_sec: 5, tv_nsec: 0 };
+        let set_handlers = |handler| {
+            for signal in SIGNALS {
+                unsafe { libc::signal(signal, handler) };
+            }
+        };
+        // kqueue only reports signals whose default action is disabled.
+        set_handlers(libc::SIG_IGN);
+        thread::spawn(move || unsafe {
+            let kq = libc::kqueue();
+            if kq < 0 {
+                set_handlers(libc::SIG_DFL);
+                return;
+            }
+
+            let mut changes: [libc::kevent; 2] = std::mem::zeroed();
+            for (change, signal) in changes.iter_mut().zip(SIGNALS) {
+                change.ident = signal as libc::uintptr_t;
+                change.filter = libc::EVFILT_SIGNAL as i16;
+                change.flags = (libc::EV_ADD | libc::EV_ENABLE) as u16;
+            }
+            let reg = libc::kevent(
+                kq,
+                changes.as_ptr(),
+                changes.len() as libc::c_int,
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null(),
+            );
+            if reg < 0 {
+                let _ = libc::close(kq);
+                set_handlers(libc::SIG_DFL);
+                return;
+            }
+
+            let mut event: libc::kevent = std::mem::zeroed();
+            let n = libc::kevent(
+                kq,
+                std::ptr::null(),
+                0,
+                &mut event as *mut libc::kevent,
+                1,
+                std::ptr::null(),
+            );
+            if n <= 0 {
+                let _ = libc::close(kq);
+                set_handlers(libc::SIG_DFL);
+                return;
+            }
+            events_tx.send(Event::Command(Command::Reactor(ReactorCommand::SaveAndExit)));
+            let n = libc::kevent(
+                kq,
+                std::ptr::null(),
+                0,
+                &mut event as *mut libc::kevent,
+                1,
+                &EXIT_TIMEOUT,
+            );
+            if n > 0 {
+                warn!("Received a second termination signal, exiting without saving");
+            } else {
+                warn!("Did not exit in time after a termination signal, exiting now");
+            }
+            std::process::exit(1);
+        });
+    }
+
     pub fn new(
         config: Config,
-        layout_engine: LayoutEngine,
+        mut layout_engine: LayoutEngine,
         mut record: Record,
         broadcast_tx: BroadcastSender,
         window_notify: Option<(crate::actor::window_notify::Sender, WindowTxStore)>,
@@ -512,6 +658,7 @@ impl Reactor {
     ) -> Reactor {
         // FIXME: Remove apps that are no longer running from restored state.
         record.start(&config, &layout_engine);
+        let restored_windows = WindowMatcher::new(layout_engine.take_window_identities());
         let (raise_manager_tx, _rx) = actor::channel();
         let (window_notify_tx, window_tx_store) = match window_notify {
             Some((tx, store)) => (Some(tx), store),
@@ -522,7 +669,7 @@ impl Reactor {
             one_space,
             debug_mode,
             app_manager: managers::AppManager::new(),
-            layout_manager: managers::LayoutManager { layout_engine },
+            layout_manager: managers::LayoutManager { layout_engine, restored_windows },
             window_manager: managers::WindowManager {
                 windows: HashMap::default(),
                 window_ids: HashMap::default(),
@@ -825,10 +972,26 @@ impl Reactor {
         self.communication_manager.raise_manager_tx = raise_manager_tx.clone();
 
         let event_tap_tx = self.communication_manager.event_tap_tx.clone();
+        let autosave_interval = Duration::from_secs(self.config.settings.layout_autosave_interval);
+        let autosave_task = Self::run_autosave(events_tx.clone(), autosave_interval);
         let reactor_task = self.run_reactor_loop(events);
         let raise_manager_task = RaiseManager::run(raise_manager_rx, events_tx, event_tap_tx);
 
-        let _ = tokio::join!(reactor_task, raise_manager_task);
+        let _ = tokio::join!(reactor_task, raise_manager_task, autosave_task);
+    }
+
+    /// Gives restored windows time to reappear before forgetting the rest, then
+    /// saves the layout every `interval` (never if it is zero).
+    async fn run_autosave(events_tx: Sender, interval: Duration) {
+        Timer::sleep(RESTORED_WINDOW_GRACE_PERIOD).await;
+        events_tx.send(Event::PruneRestoredWindows);
+        if interval.is_zero() {
+            return;
+        }
+        let mut timer = Timer::repeating(interval, interval);
+        while timer.next().await.is_some() {
+            events_tx.send(Event::AutosaveLayout);
+        }
     }
 
     async fn run_reactor_loop(mut self, mut events: Receiver) {
@@ -900,6 +1063,9 @@ impl Reactor {
             Event::QueryApplications(..)
                 | Event::QueryLayoutState { .. }
                 | Event::QueryMetrics(..)
+                | Event::QueryAppRules { .. }
+                | Event::QueryWindowAppRules { .. }
+                | Event::QueryLayoutSnapshots(..)
                 | Event::QueryWindowInfo { .. }
                 | Event::QueryWindows { .. }
                 | Event::QueryWorkspaces { .. }
@@ -976,6 +1142,9 @@ impl Reactor {
                     main_window,
                 );
             }
+            Event::ApplicationStarted(pid) => {
+                AppEventHandler::handle_application_started(self, pid);
+            }
             Event::ApplicationTerminated(pid) => {
                 AppEventHandler::handle_application_terminated(self, pid);
             }
@@ -1059,6 +1228,13 @@ impl Reactor {
                 WindowEventHandler::handle_mouse_moved_over_window(self, wsid);
             }
             Event::SystemWoke => SystemEventHandler::handle_system_woke(self),
+            Event::AutosaveLayout => {
+                if let Err(e) = self.save_layout() {
+                    warn!("Could not autosave layout: {e}");
+                }
+                self.save_placement_memory();
+            }
+            Event::PruneRestoredWindows => self.prune_restored_windows(),
             Event::MissionControlNativeEntered => {
                 SpaceEventHandler::handle_mission_control_native_entered(self);
             }
@@ -1077,6 +1253,9 @@ impl Reactor {
             Event::Command(Command::Metrics(cmd)) => {
                 CommandEventHandler::handle_command_metrics(self, cmd);
             }
+            Event::Command(Command::Targeted(targeted)) => {
+                CommandEventHandler::handle_command_targeted(self, targeted);
+            }
             Event::ConfigUpdated(new_cfg) => {
                 CommandEventHandler::handle_config_updated(self, new_cfg);
             }
@@ -1151,9 +1330,38 @@ impl Reactor {
                     self, &selector, window_id,
                 );
             }
+            Event::Command(Command::Reactor(ReactorCommand::MoveWorkspaceToDisplay {
+                selector,
+            })) => {
+                CommandEventHandler::handle_command_reactor_move_workspace_to_display(
+                    self, &selector, false,
+                );
+            }
+            Event::Command(Command::Reactor(ReactorCommand::SwapWorkspaceWithDisplay {
+                selector,
+            })) => {
+                CommandEventHandler::handle_command_reactor_move_workspace_to_display(
+                    self, &selector, true,
+                );
+            }
+            Event::Command(Command::Reactor(ReactorCommand::SwitchToNamedWorkspace(name))) => {
+                CommandEventHandler::handle_command_reactor_switch_to_named_workspace(self, &name);
+            }
             Event::Command(Command::Reactor(ReactorCommand::CloseWindow { window_server_id })) => {
                 CommandEventHandler::handle_command_reactor_close_window(self, window_server_id)
             }
+            Event::Command(Command::Reactor(ReactorCommand::ReapplyAppRules)) => {
+                CommandEventHandler::handle_command_reactor_reapply_app_rules(self)
+            }
+            Event::Command(Command::Reactor(ReactorCommand::LayoutSave(name))) => {
+                CommandEventHandler::handle_command_reactor_layout_save(self, &name)
+            }
+            Event::Command(Command::Reactor(ReactorCommand::LayoutLoad(name))) => {
+                CommandEventHandler::handle_command_reactor_layout_load(self, &name)
+            }
+            Event::Command(Command::Reactor(ReactorCommand::ApplyTemplate(name))) => {
+                CommandEventHandler::handle_command_reactor_apply_template(self, &name)
+            }
             _ => (),
         }
 
@@ -1246,6 +1454,7 @@ impl Reactor {
             is_focused: self.main_window() == Some(window_id),
             bundle_id: preferred_name,
             window_server_id: window_state.window_server_id.map(|wsid| wsid.as_u32()),
+            marks: self.layout_manager.layout_engine.window_marks(window_id),
         })
     }
 
@@ -1388,6 +1597,15 @@ impl Reactor {
     ) {
         let mut seen_displays: HashSet<String> = HashSet::default();
 
+        // The main screen is always first.
+        let main_display = self
+            .space_manager
+            .screens
+            .first()
+            .map(|screen| screen.display_uuid.clone())
+            .filter(|uuid| !uuid.is_empty());
+        self.layout_manager.layout_engine.set_main_display(main_display);
+
         for (screen, space_opt) in self.space_manager.screens.iter().zip(spaces.iter()) {
             let Some(space) = space_opt else {
                 continue;
@@ -1548,6 +1766,143 @@ impl Reactor {
         self.process_windows_for_app_rules(window_id.pid, vec![window_id], app_info);
     }
 
+    /// Records where windows live so the app's windows return there when it is
+    /// launched again. The memory is written out by [`Self::save_placement_memory`].
+    fn remember_window_placements(&mut self, window_ids: impl IntoIterator<Item = WindowId>) {
+        if !self.config.virtual_workspaces.remember_app_placements {
+            return;
+        }
+        for wid in window_ids {
+            let Some(window) = self.window_manager.windows.get(&wid) else {
+                continue;
+            };
+            if !window.is_manageable {
+                continue;
+            }
+            let Some(bundle_id) =
+                self.app_manager.apps.get(&wid.pid).and_then(|app| app.info.bundle_id.as_deref())
+            else {
+                continue;
+            };
+            self.layout_manager.layout_engine.remember_window_placement(
+                wid,
+                bundle_id,
+                &window.title,
+                window.frame_monotonic,
+            );
+        }
+    }
+
+    /// Writes remembered placements to disk if any changed. Done when an app
+    /// quits, on autosave and on exit rather than on every window change.
+    fn save_placement_memory(&mut self) {
+        if let Err(e) = self
+            .layout_manager
+            .layout_engine
+            .virtual_workspace_manager_mut()
+            .placement_memory_mut()
+            .save()
+        {
+            warn!("Could not save placement memory: {e}");
+        }
+    }
+
+    /// Describes a live window for app-rule and template matching.
+    fn app_rule_query(&self, wid: WindowId) -> Option<AppRuleQuery> {
+        let window = self.window_manager.windows.get(&wid)?;
+        let app_info = self.app_manager.apps.get(&wid.pid).map(|app| &app.info);
+        Some(AppRuleQuery {
+            app_id: app_info.and_then(|info| info.bundle_id.clone()),
+            app_name: app_info.and_then(|info| info.localized_name.clone()),
+            title: Some(window.title.clone()),
+            ax_role: window.ax_role.clone(),
+            ax_subrole: window.ax_subrole.clone(),
+        })
+    }
+
+    fn window_identity(&self, wid: WindowId) -> Option<WindowIdentity> {
+        let window = self.window_manager.windows.get(&wid)?;
+        let bundle_id = window.bundle_id.clone().or_else(|| {
+            self.app_manager.apps.get(&wid.pid).and_then(|app| app.info.bundle_id.clone())
+        });
+        Some(WindowIdentity {
+            bundle_id,
+            title: window.title.clone(),
+            window_server_id: window.window_server_id,
+            frame: window.frame_monotonic,
+        })
+    }
+
+    /// Lets a newly seen window take the place of the restored window it most
+    /// likely is, so that it lands where that window was saved, or else fill a
+    /// placeholder left by `layout_load`.
+    fn adopt_restored_window(&mut self, wid: WindowId) {
+        if self.layout_manager.restored_windows.is_empty()
+            && !self.layout_manager.layout_engine.has_placeholders()
+        {
+            return;
+        }
+        if !self.window_manager.windows.get(&wid).is_some_and(|w| w.is_manageable) {
+            return;
+        }
+        let Some(identity) = self.window_identity(wid) else {
+            return;
+        };
+        if self.layout_manager.restored_windows.forget_reused_id(wid, &identity) {
+            // The restored window with this id is gone; its leaf must not be
+            // mistaken for the place of the new window.
+            debug!(?wid, "Forgetting restored window whose id was reused");
+            self.send_layout_event(LayoutEvent::WindowRemoved(wid));
+        }
+        match self.layout_manager.restored_windows.take_match(wid, &identity) {
+            Some(old) => {
+                debug!(?old, ?wid, "Matched restored window");
+                self.layout_manager.layout_engine.replace_window(old, wid);
+            }
+            None => {
+                if let Some(window) = self.app_rule_query(wid) {
+                    self.layout_manager.layout_engine.fill_placeholder(wid, &window);
+                }
+            }
+        }
+    }
+
+    /// Forgets restored windows that never reappeared. Ids that a live window
+    /// has since taken are left to that window.
+    fn prune_restored_windows(&mut self) {
+        let mut stale = self.layout_manager.restored_windows.take_unmatched();
+        stale.retain(|wid| !self.window_manager.windows.contains_key(wid));
+        if stale.is_empty() {
+            return;
+        }
+        debug!(count = stale.len(), "Forgetting restored windows that did not reappear");
+        for wid in stale {
+            self.send_layout_event(LayoutEvent::WindowRemoved(wid));
+        }
+        self.update_layout_or_warn(false, false);
+    }
+
+    /// Saves the layout, along with what is needed to recognize its windows
+    /// after a restart, to the restore file.
+    pub(crate) fn save_layout(&mut self) -> std::io::Result<()> {
+        let mut identities = self.layout_manager.restored_windows.unmatched().clone();
+        let live: Vec<WindowId> = self
+            .window_manager
+            .windows
+            .iter()
+            .filter(|(_, window)| window.is_manageable)
+            .map(|(&wid, _)| wid)
+            .collect();
+        for wid in live {
+            if let Some(identity) = self.window_identity(wid) {
+                identities.insert(wid, identity);
+            }
+        }
+        let engine = &mut self.layout_manager.layout_engine;
+        engine.set_window_identities(identities);
+        engine.save(config::restore_file())
+    }
+
     fn try_apply_pending_space_change(&mut self) {
         if let Some(mut pending) = self.pending_space_change_manager.pending_space_change.take() {
             if pending.spaces.len() == self.space_manager.screens.len() {
@@ -1804,6 +2159,9 @@ impl Reactor {
         let response = self.layout_manager.layout_engine.handle_event(event);
         self.prepare_refocus_after_layout_event(&event_clone);
         self.handle_layout_response(response, None);
+        for (wid, selector) in self.layout_manager.layout_engine.take_rule_display_moves() {
+            CommandEventHandler::move_window_to_display(self, &selector, wid);
+        }
         for space in self.space_manager.iter_known_spaces() {
             self.layout_manager.layout_engine.debug_tree_desc(space, "after event", false);
         }
@@ -24,6 +24,16 @@ impl AppEventHandler {
         reactor.on_windows_discovered_with_app_info(pid, visible_windows, vec![], Some(info));
     }
 
+    pub fn handle_application_started(reactor: &mut Reactor, pid: i32) {
+        if !reactor.app_manager.apps.contains_key(&pid) {
+            reactor
+                .layout_manager
+                .layout_engine
+                .virtual_workspace_manager_mut()
+                .mark_app_launched(pid);
+        }
+    }
+
     pub fn handle_application_terminated(reactor: &mut Reactor, pid: i32) {
         if let Some(app) = reactor.app_manager.apps.get_mut(&pid) {
             if let Err(e) = app.handle.send(crate::actor::app::Request::Terminate) {
@@ -33,6 +43,10 @@ impl AppEventHandler {
     }
 
     pub fn handle_application_thread_terminated(reactor: &mut Reactor, pid: i32) {
+        let windows: Vec<WindowId> =
+            reactor.window_manager.windows.keys().filter(|wid| wid.pid == pid).copied().collect();
+        reactor.remember_window_placements(windows);
+        reactor.save_placement_memory();
         reactor.app_manager.apps.remove(&pid);
         reactor.send_layout_event(LayoutEvent::AppClosed(pid));
     }
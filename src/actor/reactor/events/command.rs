@@ -1,17 +1,21 @@
 use tracing::{error, info, warn};
 
 use super::super::Screen;
-use crate::actor::app::{AppThreadHandle, Quiet, WindowId};
+use crate::actor::app::{AppThreadHandle, Quiet, WindowId, pid_t};
 use crate::actor::reactor::transaction_manager::TransactionId;
-use crate::actor::reactor::{DisplaySelector, Reactor, WorkspaceSwitchOrigin};
+use crate::actor::reactor::{
+    Command, DisplaySelector, Reactor, ReactorCommand, TargetedCommand, WorkspaceSwitchOrigin,
+};
 use crate::actor::corner_indicator::Event as CornerIndicatorEvent;
 use crate::actor::stack_line::Event as StackLineEvent;
 use crate::actor::wm_controller::WmEvent;
 use crate::actor::{menu_bar, raise_manager};
-use crate::common::collections::HashMap;
+use crate::common::collections::{BTreeMap, HashMap};
 use crate::common::config::{self as config, Config};
 use crate::common::log::{MetricsCommand, handle_command};
 use crate::layout_engine::{EventResponse, LayoutCommand, LayoutEvent};
+use crate::model::placement_memory::PlacementMemory;
+use crate::model::virtual_workspace::{AppRuleQuery, app_rule_matches, compile_title_regex};
 use crate::sys::screen::{SpaceId, order_visible_spaces_by_position};
 use crate::sys::window_server::{self as window_server, WindowServerId};
 
@@ -34,6 +38,7 @@ pub(crate) fn generate_debug_tree_text(reactor: &Reactor) -> String {
                             title: w.title.clone(),
                             bundle_id: w.bundle_id.clone(),
                             frame: w.frame_monotonic,
+                            marks: reactor.layout_manager.layout_engine.window_marks(wid),
                         }
                     })
                 }
@@ -51,7 +56,23 @@ pub struct CommandEventHandler;
 
 impl CommandEventHandler {
     pub fn handle_command_layout(reactor: &mut Reactor, cmd: LayoutCommand) {
-        info!(?cmd);
+        Self::handle_command_layout_for(reactor, cmd, None);
+    }
+
+    /// Runs a layout command, on the layout of `target` when given and on the
+    /// workspace shown on the command display otherwise.
+    fn handle_command_layout_for(
+        reactor: &mut Reactor,
+        cmd: LayoutCommand,
+        target: Option<WindowId>,
+    ) {
+        info!(?cmd, ?target);
+        let command_space = match target {
+            Some(wid) => {
+                reactor.layout_manager.layout_engine.window_location(wid).map(|(space, _)| space)
+            }
+            None => reactor.workspace_command_space(),
+        };
         let visible_spaces_input: Vec<(SpaceId, _)> = reactor
             .space_manager
             .screens
@@ -78,15 +99,33 @@ impl CommandEventHandler {
 
         let visible_spaces = order_visible_spaces_by_position(visible_spaces_input.iter().cloned());
 
-        let is_workspace_switch = matches!(
-            cmd,
-            LayoutCommand::NextWorkspace(_)
-                | LayoutCommand::PrevWorkspace(_)
-                | LayoutCommand::SwitchToWorkspace(_)
-                | LayoutCommand::SwitchToLastWorkspace
-        );
+        let is_workspace_switch = match &cmd {
+            // These only switch when the window they focus is on a hidden
+            // workspace.
+            LayoutCommand::FocusBack
+            | LayoutCommand::FocusMruNext
+            | LayoutCommand::FocusMruPrev => {
+                reactor.layout_manager.layout_engine.focus_history_switches_workspace(&cmd)
+            }
+            _ => matches!(
+                cmd,
+                LayoutCommand::NextWorkspace(_)
+                    | LayoutCommand::PrevWorkspace(_)
+                    | LayoutCommand::SwitchToWorkspace(_)
+                    | LayoutCommand::SwitchToLastWorkspace
+                    | LayoutCommand::WorkspaceBack
+                    | LayoutCommand::WorkspaceForward
+                    | LayoutCommand::DeleteWorkspace { .. }
+                    | LayoutCommand::SwapWorkspaces { .. }
+                    | LayoutCommand::ToggleView(_)
+                    | LayoutCommand::FocusMark(_)
+                    | LayoutCommand::SwapWithMark(_)
+                    | LayoutCommand::MoveWindowToWorkspace { follow: true, .. }
+                    | LayoutCommand::MoveWindowToNewWorkspace { follow: true, .. }
+            ),
+        };
         let workspace_space = if is_workspace_switch {
-            let space = reactor.workspace_command_space();
+            let space = command_space;
             if let Some(space) = space {
                 reactor.store_current_floating_positions(space);
             }
@@ -107,7 +146,14 @@ impl CommandEventHandler {
             | LayoutCommand::PrevWorkspace(_)
             | LayoutCommand::SwitchToWorkspace(_)
             | LayoutCommand::CreateWorkspace
-            | LayoutCommand::SwitchToLastWorkspace => {
+            | LayoutCommand::SwitchToLastWorkspace
+            | LayoutCommand::WorkspaceBack
+            | LayoutCommand::WorkspaceForward
+            | LayoutCommand::DeleteWorkspace { .. }
+            | LayoutCommand::SwapWorkspaces { .. }
+            | LayoutCommand::ToggleView(_)
+            | LayoutCommand::FocusMark(_)
+            | LayoutCommand::SwapWithMark(_) => {
                 if let Some(space) = workspace_space {
                     reactor
                         .layout_manager
@@ -117,8 +163,17 @@ impl CommandEventHandler {
                     EventResponse::default()
                 }
             }
-            LayoutCommand::MoveWindowToWorkspace { .. } => {
-                if let Some(space) = reactor.workspace_command_space() {
+            LayoutCommand::MoveWindowToWorkspace { .. }
+            | LayoutCommand::MoveWindowToNewWorkspace { .. }
+            | LayoutCommand::MoveContainerToWorkspace { .. }
+            | LayoutCommand::RenameWorkspace { .. }
+            | LayoutCommand::MoveWorkspaceToIndex { .. }
+            | LayoutCommand::ToggleTag(_)
+            | LayoutCommand::FocusBack
+            | LayoutCommand::FocusMruNext
+            | LayoutCommand::FocusMruPrev
+            | LayoutCommand::FocusMruCommit => {
+                if let Some(space) = command_space {
                     reactor
                         .layout_manager
                         .layout_engine
@@ -127,12 +182,20 @@ impl CommandEventHandler {
                     EventResponse::default()
                 }
             }
-            _ => reactor.layout_manager.layout_engine.handle_command(
-                reactor.workspace_command_space(),
-                &visible_spaces,
-                &visible_space_centers,
-                cmd,
-            ),
+            _ => match target {
+                Some(wid) => reactor.layout_manager.layout_engine.handle_command_for_window(
+                    wid,
+                    &visible_spaces,
+                    &visible_space_centers,
+                    cmd,
+                ),
+                None => reactor.layout_manager.layout_engine.handle_command(
+                    command_space,
+                    &visible_spaces,
+                    &visible_space_centers,
+                    cmd,
+                ),
+            },
         };
 
         reactor.handle_layout_response(response, workspace_space);
@@ -151,8 +214,72 @@ impl CommandEventHandler {
         handle_command(cmd);
     }
 
+    pub fn handle_command_targeted(reactor: &mut Reactor, targeted: TargetedCommand) {
+        if !targeted.is_supported() {
+            warn!(command = ?targeted.command, "Command cannot be run on targeted windows");
+            return;
+        }
+        let rule = targeted.target.to_app_rule();
+        let title_regex = match rule.title_regex.as_deref().filter(|re| !re.is_empty()) {
+            Some(pattern) => match compile_title_regex(pattern) {
+                Ok(re) => Some(re),
+                Err(e) => {
+                    warn!("Invalid title_regex '{}' in command target: {}", pattern, e);
+                    return;
+                }
+            },
+            None => None,
+        };
+
+        let engine = &reactor.layout_manager.layout_engine;
+        let mut targets: Vec<WindowId> = reactor
+            .window_manager
+            .windows
+            .iter()
+            .filter(|(wid, window)| {
+                if !window.is_manageable || engine.window_location(**wid).is_none() {
+                    return false;
+                }
+                let info = reactor.app_manager.apps.get(&wid.pid).map(|app| &app.info);
+                app_rule_matches(
+                    &rule,
+                    title_regex.as_ref(),
+                    info.and_then(|i| i.bundle_id.as_deref()),
+                    info.and_then(|i| i.localized_name.as_deref()),
+                    Some(window.title.as_str()),
+                    window.ax_role.as_deref(),
+                    window.ax_subrole.as_deref(),
+                )
+            })
+            .map(|(wid, _)| *wid)
+            .collect();
+        targets.sort_unstable();
+        info!(target = ?targeted.target, count = targets.len(), "Running targeted command");
+
+        // Windows on hidden workspaces are targets too: layout commands run on
+        // the layout of each window's own workspace, shown or not.
+        for wid in targets {
+            match &*targeted.command {
+                Command::Layout(cmd) => {
+                    let Some(saved) = reactor.layout_manager.layout_engine.target_window(wid)
+                    else {
+                        continue;
+                    };
+                    Self::handle_command_layout_for(reactor, cmd.clone(), Some(wid));
+                    reactor.layout_manager.layout_engine.restore_selection(saved);
+                }
+                Command::Reactor(ReactorCommand::CloseWindow { .. }) => {
+                    reactor.request_close_window(wid)
+                }
+                _ => unreachable!("unsupported targeted commands are refused above"),
+            }
+        }
+    }
+
     pub fn handle_config_updated(reactor: &mut Reactor, new_cfg: Config) {
         let old_keys = reactor.config.keys.clone();
+        let old_app_rules = reactor.config.virtual_workspaces.app_rules.clone();
+        let remembered_placements = reactor.config.virtual_workspaces.remember_app_placements;
 
         reactor.config = new_cfg;
         reactor
@@ -165,6 +292,11 @@ impl CommandEventHandler {
             .layout_engine
             .update_virtual_workspace_settings(&reactor.config.virtual_workspaces);
 
+        reactor
+            .layout_manager
+            .layout_engine
+            .set_workspace_templates(reactor.config.workspace_templates.clone());
+
         reactor.drag_manager.update_config(reactor.config.settings.window_snapping);
 
         if let Some(tx) = &reactor.communication_manager.stack_line_tx {
@@ -191,6 +323,26 @@ impl CommandEventHandler {
             }
         }
 
+        if remembered_placements != reactor.config.virtual_workspaces.remember_app_placements {
+            reactor.save_placement_memory();
+            let memory = if remembered_placements {
+                PlacementMemory::default()
+            } else {
+                PlacementMemory::load(config::placements_file())
+            };
+            reactor
+                .layout_manager
+                .layout_engine
+                .virtual_workspace_manager_mut()
+                .set_placement_memory(memory);
+        }
+
+        if reactor.config.virtual_workspaces.reapply_app_rules_on_config_reload
+            && old_app_rules != reactor.config.virtual_workspaces.app_rules
+        {
+            Self::handle_command_reactor_reapply_app_rules(reactor);
+        }
+
         let _ = reactor.update_layout_or_warn(false, true);
 
         if old_keys != reactor.config.keys {
@@ -228,7 +380,10 @@ impl CommandEventHandler {
     }
 
     pub fn handle_command_reactor_save_and_exit(reactor: &mut Reactor) {
-        match reactor.layout_manager.layout_engine.save(config::restore_file()) {
+        let windows: Vec<WindowId> = reactor.window_manager.windows.keys().copied().collect();
+        reactor.remember_window_placements(windows);
+        reactor.save_placement_memory();
+        match reactor.save_layout() {
             Ok(()) => std::process::exit(0),
             Err(e) => {
                 error!("Could not save layout: {e}");
@@ -385,6 +540,111 @@ impl CommandEventHandler {
         }
     }
 
+    pub fn handle_command_reactor_switch_to_named_workspace(reactor: &mut Reactor, name: &str) {
+        let pinned_space = reactor
+            .layout_manager
+            .layout_engine
+            .virtual_workspace_manager()
+            .space_for_pinned_workspace(name)
+            .filter(|space| reactor.is_space_active(*space));
+        let Some(space) = pinned_space.or_else(|| reactor.workspace_command_space()) else {
+            warn!(workspace = name, "Workspace switch ignored: no active space");
+            return;
+        };
+
+        let index = reactor
+            .layout_manager
+            .layout_engine
+            .virtual_workspace_manager_mut()
+            .list_workspaces(space)
+            .iter()
+            .position(|(_, workspace_name)| workspace_name == name);
+        let Some(index) = index else {
+            warn!(
+                workspace = name,
+                ?space,
+                "Workspace switch ignored: no workspace with that name"
+            );
+            return;
+        };
+
+        reactor.store_current_floating_positions(space);
+        reactor.workspace_switch_manager.start_workspace_switch(WorkspaceSwitchOrigin::Manual);
+        let response = reactor
+            .layout_manager
+            .layout_engine
+            .handle_virtual_workspace_command(space, &LayoutCommand::SwitchToWorkspace(index));
+        let focused = response.focus_window.is_some();
+        reactor.handle_layout_response(response, Some(space));
+
+        // Bring focus to the pinned workspace's display even when it has no window to focus.
+        if pinned_space.is_some() && !focused {
+            if let Some(uuid) =
+                reactor.space_manager.screen_by_space(space).map(|s| s.display_uuid.clone())
+            {
+                Self::handle_command_reactor_focus_display(reactor, &DisplaySelector::Uuid(uuid));
+            }
+        }
+    }
+
+    pub fn handle_command_reactor_move_workspace_to_display(
+        reactor: &mut Reactor,
+        selector: &DisplaySelector,
+        swap: bool,
+    ) {
+        let Some(source_space) = reactor.workspace_command_space() else {
+            warn!("Workspace display move ignored: no active space");
+            return;
+        };
+        let Some(source_frame) =
+            reactor.space_manager.screen_by_space(source_space).map(|screen| screen.frame)
+        else {
+            return;
+        };
+        let origin = Some(source_frame.mid());
+        let Some((target_space, target_frame)) = reactor
+            .screen_for_selector(selector, origin)
+            .and_then(|screen| screen.space.map(|space| (space, screen.frame)))
+        else {
+            warn!(?selector, "Workspace display move ignored: no target display");
+            return;
+        };
+        if target_space == source_space || !reactor.is_space_active(target_space) {
+            warn!(
+                ?selector,
+                ?target_space,
+                "Workspace display move ignored: target display is the source or inactive"
+            );
+            return;
+        }
+
+        reactor.store_current_floating_positions(source_space);
+        if swap {
+            reactor.store_current_floating_positions(target_space);
+        }
+        reactor.workspace_switch_manager.start_workspace_switch(WorkspaceSwitchOrigin::Manual);
+
+        let engine = &mut reactor.layout_manager.layout_engine;
+        let (response, focus_space) = if swap {
+            let response = engine.swap_workspace_between_spaces(
+                source_space,
+                source_frame,
+                target_space,
+                target_frame,
+            );
+            (response, source_space)
+        } else {
+            let response = engine.move_workspace_to_space(
+                source_space,
+                source_frame,
+                target_space,
+                target_frame,
+            );
+            (response, target_space)
+        };
+        reactor.handle_layout_response(response, Some(focus_space));
+    }
+
     pub fn handle_command_reactor_move_window_to_display(
         reactor: &mut Reactor,
         selector: &DisplaySelector,
@@ -424,6 +684,14 @@ impl CommandEventHandler {
             return;
         };
 
+        Self::move_window_to_display(reactor, selector, window_id);
+    }
+
+    pub fn move_window_to_display(
+        reactor: &mut Reactor,
+        selector: &DisplaySelector,
+        window_id: WindowId,
+    ) {
         let (window_server_id, window_frame) = match reactor.window_manager.windows.get(&window_id)
         {
             Some(state) => (state.window_server_id, state.frame_monotonic),
@@ -548,4 +816,122 @@ impl CommandEventHandler {
             warn!("Close window command ignored because no window is tracked");
         }
     }
+
+    pub fn handle_command_reactor_reapply_app_rules(reactor: &mut Reactor) {
+        let mut windows_by_pid: BTreeMap<pid_t, Vec<WindowId>> = BTreeMap::new();
+        for (&wid, window) in &reactor.window_manager.windows {
+            if window.is_manageable {
+                windows_by_pid.entry(wid.pid).or_default().push(wid);
+            }
+        }
+
+        let mut moved = 0;
+        for (pid, mut wids) in windows_by_pid {
+            let Some(app_info) = reactor.app_manager.apps.get(&pid).map(|app| app.info.clone())
+            else {
+                continue;
+            };
+            wids.sort();
+            for &wid in &wids {
+                let Some(space) = reactor.best_space_for_window_id(wid) else {
+                    continue;
+                };
+                if !reactor.is_space_active(space) {
+                    continue;
+                }
+                let Some(window) = reactor.window_manager.windows.get(&wid) else {
+                    continue;
+                };
+                let query = AppRuleQuery {
+                    app_id: app_info.bundle_id.clone(),
+                    app_name: app_info.localized_name.clone(),
+                    title: Some(window.title.clone()),
+                    ax_role: window.ax_role.clone(),
+                    ax_subrole: window.ax_subrole.clone(),
+                };
+                if reactor
+                    .layout_manager
+                    .layout_engine
+                    .virtual_workspace_manager_mut()
+                    .reapply_rule_workspace(wid, space, &query)
+                    .is_some()
+                {
+                    moved += 1;
+                }
+            }
+            // Syncs the layout trees with the new assignments and re-applies
+            // the floating and unmanaged decisions of the rules.
+            reactor.process_windows_for_app_rules(pid, wids, app_info);
+        }
+
+        info!(moved, "Re-applied app rules");
+        let _ = reactor.update_layout_or_warn(false, false);
+    }
+
+    pub fn handle_command_reactor_layout_save(reactor: &mut Reactor, name: &str) {
+        let Some(space) = reactor.workspace_command_space() else {
+            warn!(layout = name, "Layout save ignored: no active space");
+            return;
+        };
+        let identities: BTreeMap<WindowId, _> = reactor
+            .window_manager
+            .windows
+            .keys()
+            .filter_map(|&wid| Some((wid, reactor.window_identity(wid)?)))
+            .collect();
+        let saved = reactor
+            .layout_manager
+            .layout_engine
+            .save_layout_snapshot(space, name, |wid| identities.get(&wid).cloned());
+        match saved {
+            Ok(windows) => info!(layout = name, windows, "Saved layout"),
+            Err(e) => warn!(layout = name, "Could not save layout: {e}"),
+        }
+    }
+
+    pub fn handle_command_reactor_layout_load(reactor: &mut Reactor, name: &str) {
+        let Some(space) = reactor.workspace_command_space() else {
+            warn!(layout = name, "Layout load ignored: no active space");
+            return;
+        };
+        let windows = Self::manageable_window_queries(reactor);
+        let loaded =
+            reactor.layout_manager.layout_engine.load_layout_snapshot(space, name, &windows);
+        match loaded {
+            Ok(response) => {
+                info!(layout = name, "Loaded layout");
+                reactor.handle_layout_response(response, None);
+                let _ = reactor.update_layout_or_warn(false, false);
+            }
+            Err(e) => warn!(layout = name, "Could not load layout: {e}"),
+        }
+    }
+
+    pub fn handle_command_reactor_apply_template(reactor: &mut Reactor, name: &str) {
+        let Some(space) = reactor.workspace_command_space() else {
+            warn!(template = name, "Template ignored: no active space");
+            return;
+        };
+        let windows = Self::manageable_window_queries(reactor);
+        let applied =
+            reactor.layout_manager.layout_engine.apply_workspace_template(space, name, &windows);
+        match applied {
+            Ok(response) => {
+                info!(template = name, "Applied workspace template");
+                reactor.handle_layout_response(response, None);
+                let _ = reactor.update_layout_or_warn(false, false);
+            }
+            Err(e) => warn!(template = name, "Could not apply workspace template: {e}"),
+        }
+    }
+
+    fn manageable_window_queries(reactor: &Reactor) -> BTreeMap<WindowId, AppRuleQuery> {
+        reactor
+            .window_manager
+            .windows
+            .iter()
+            .filter(|(_, window)| window.is_manageable)
+            .filter_map(|(&wid, _)| Some((wid, reactor.app_rule_query(wid)?)))
+            .collect()
+    }
 }
@@ -52,6 +52,7 @@ impl WindowEventHandler {
 
         let server_id = window_state.window_server_id;
         reactor.window_manager.windows.insert(wid, window_state);
+        reactor.adopt_restored_window(wid);
 
         if is_manageable {
             if let Some(space) = reactor.best_space_for_window(&frame, server_id) {
@@ -86,6 +87,7 @@ impl WindowEventHandler {
         if !reactor.window_manager.windows.contains_key(&wid) {
             return false;
         }
+        reactor.remember_window_placements([wid]);
         let window_server_id =
             reactor.window_manager.windows.get(&wid).and_then(|w| w.window_server_id);
         if let Some(ws_id) = window_server_id {
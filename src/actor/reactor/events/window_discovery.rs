@@ -265,6 +265,7 @@ impl WindowDiscoveryHandler {
                     );
                     state.is_manageable = manageable;
                     reactor.window_manager.windows.insert(*wid, state);
+                    reactor.adopt_restored_window(*wid);
                 }
                 if let Some(wsid) = info.sys_id {
                     reactor.window_manager.window_ids.insert(wsid, *wid);
@@ -317,6 +318,7 @@ impl WindowDiscoveryHandler {
             );
             state.is_manageable = manageable;
             reactor.window_manager.windows.insert(wid, state);
+            reactor.adopt_restored_window(wid);
         }
     }
 
@@ -20,6 +20,7 @@ use crate::actor::{corner_indicator, event_tap, menu_bar, raise_manager, stack_l
 use crate::common::collections::{HashMap, HashSet};
 use crate::common::config::WindowSnappingSettings;
 use crate::layout_engine::LayoutEngine;
+use crate::model::window_identity::WindowMatcher;
 use crate::sys::screen::SpaceId;
 use crate::sys::window_server::{WindowServerId, WindowServerInfo};
 
@@ -191,6 +192,8 @@ pub struct RecordingManager {
 /// Manages layout engine state
 pub struct LayoutManager {
     pub layout_engine: LayoutEngine,
+    /// Windows of the restored layout that no live window has claimed yet.
+    pub restored_windows: WindowMatcher,
 }
 
 pub type LayoutResult = Vec<(SpaceId, Vec<(WindowId, CGRect)>)>;
@@ -7,7 +7,7 @@ use crate::common::collections::HashSet;
 use crate::model::server::{
     ApplicationData, DisplayData, LayoutStateData, WindowData, WorkspaceData,
 };
-use crate::model::virtual_workspace::VirtualWorkspaceId;
+use crate::model::virtual_workspace::{AppRuleExplanation, AppRuleQuery, VirtualWorkspaceId};
 use crate::sys::screen::{SpaceId, get_active_space_number};
 
 impl Reactor {
@@ -45,6 +45,17 @@ impl Reactor {
                 let displays = self.handle_displays_query();
                 response.send(displays);
             }
+            Event::QueryAppRules { query, response } => {
+                let explanation = self.handle_app_rules_query(&query);
+                response.send(explanation);
+            }
+            Event::QueryWindowAppRules { window_id, response } => {
+                let explanation = self.handle_window_app_rules_query(window_id);
+                response.send(explanation);
+            }
+            Event::QueryLayoutSnapshots(response) => {
+                response.send(self.layout_manager.layout_engine.layout_snapshots().list());
+            }
             _ => {}
         }
     }
@@ -238,6 +249,15 @@ impl Reactor {
         self.create_window_data(window_id)
     }
 
+    fn handle_app_rules_query(&self, query: &AppRuleQuery) -> AppRuleExplanation {
+        self.layout_manager.layout_engine.virtual_workspace_manager().explain_app_rules(query)
+    }
+
+    fn handle_window_app_rules_query(&self, window_id: WindowId) -> Option<AppRuleExplanation> {
+        let query = self.app_rule_query(window_id)?;
+        Some(self.handle_app_rules_query(&query))
+    }
+
     fn handle_applications_query(&self) -> Vec<ApplicationData> {
         self.app_manager
             .apps
@@ -11,7 +11,7 @@ use super::{Event, Reactor};
 use crate::actor::app::{AppThreadHandle, Request};
 use crate::actor::{self};
 use crate::common::config::Config;
-use crate::layout_engine::LayoutEngine;
+use crate::layout_engine::{LayoutEngine, migration};
 
 thread_local! {
     static DESERIALIZE_THREAD_HANDLE: RefCell<Option<AppThreadHandle>> = RefCell::new(None);
@@ -53,9 +53,10 @@ impl Record {
 
     pub(super) fn start(&mut self, config: &Config, layout: &LayoutEngine) {
         let Some(file) = self.file() else { return };
+        let header = migration::header();
         let config = ron::ser::to_string(&config).unwrap();
         let layout = layout.serialize_to_string();
-        write!(file, "{config}\n{layout}\n").unwrap();
+        write!(file, "{header}\n{config}\n{layout}\n").unwrap();
     }
 
     pub(super) fn on_event(&mut self, event: &Event) {
@@ -74,8 +75,18 @@ pub fn replay(
     let handle = AppThreadHandle::new_for_test(tx);
     DESERIALIZE_THREAD_HANDLE.with(|h| h.borrow_mut().replace(handle));
     let mut lines = file.lines();
-    let config = ron::de::from_str(&lines.next().expect("Empty restore file")?)?;
-    let layout = ron::de::from_str(&lines.next().expect("Expected layout line")?)?;
+    let mut first = lines.next().expect("Empty restore file")?;
+    // Recordings made before the layout format was versioned start with the config.
+    let version = match migration::parse_header(&first) {
+        Some(version) => {
+            first = lines.next().expect("Expected config line")?;
+            version
+        }
+        None => 1,
+    };
+    let config = ron::de::from_str(&first)?;
+    let layout =
+        LayoutEngine::deserialize_versioned(version, lines.next().expect("Expected layout line")?)?;
     let (broadcast_tx, _) = actor::channel();
     let mut reactor = Reactor::new(config, layout, Record::new(None), broadcast_tx, None, false, false);
     std::thread::spawn(move || {
@@ -15,7 +15,7 @@ use serde_json;
 use strum::VariantNames;
 use tracing::{debug, error, info, instrument, warn};
 
-use crate::common::config::WorkspaceSelector;
+use crate::common::config::{MoveWindowTarget, WorkspaceSelector};
 use crate::sys::app::{NSRunningApplicationExt, pid_t};
 
 pub type Sender = actor::Sender<WmEvent>;
@@ -67,9 +67,22 @@ pub enum WmCmd {
     NextWorkspace,
     PrevWorkspace,
     SwitchToWorkspace(WorkspaceSelector),
-    MoveWindowToWorkspace(WorkspaceSelector),
+    MoveWindowToWorkspace(MoveWindowTarget),
+    MoveWindowToNewWorkspace {
+        #[serde(default)]
+        follow: bool,
+    },
+    MoveContainerToWorkspace(WorkspaceSelector),
     CreateWorkspace,
     SwitchToLastWorkspace,
+    WorkspaceBack,
+    WorkspaceForward,
+    RenameWorkspace(String),
+    DeleteWorkspace,
+    MoveWorkspaceToIndex(usize),
+    SwapWorkspaces(WorkspaceSelector),
+    ToggleTag(WorkspaceSelector),
+    ToggleView(WorkspaceSelector),
 
     ShowMissionControlAll,
     ShowMissionControlCurrent,
@@ -182,6 +195,11 @@ impl WmController {
                 | Command(Wm(crate::actor::wm_controller::WmCmd::PrevWorkspace))
                 | Command(Wm(crate::actor::wm_controller::WmCmd::SwitchToWorkspace(_)))
                 | Command(Wm(crate::actor::wm_controller::WmCmd::SwitchToLastWorkspace))
+                | Command(Wm(crate::actor::wm_controller::WmCmd::WorkspaceBack))
+                | Command(Wm(crate::actor::wm_controller::WmCmd::WorkspaceForward))
+                | Command(Wm(crate::actor::wm_controller::WmCmd::DeleteWorkspace))
+                | Command(Wm(crate::actor::wm_controller::WmCmd::SwapWorkspaces(_)))
+                | Command(Wm(crate::actor::wm_controller::WmCmd::ToggleView(_)))
                 | SpaceChanged(_)
         ) && let Some(tx) = &self.mission_control_tx
         {
@@ -220,6 +238,7 @@ impl WmController {
                 }
             }
             AppLaunch(pid, info) => {
+                self.events_tx.send(Event::ApplicationStarted(pid));
                 self.new_app(pid, info);
             }
             AppGloballyActivated(pid) => {
@@ -311,6 +330,15 @@ impl WmController {
                     layout::LayoutCommand::PrevWorkspace(None),
                 )));
             }
+            Command(Wm(SwitchToWorkspace(WorkspaceSelector::Name(name))))
+                if !self.config.config.virtual_workspaces.workspace_displays.is_empty() =>
+            {
+                // With pinned workspaces each display has its own workspace list, so
+                // names are resolved by the reactor against the live lists.
+                self.events_tx.send(reactor::Event::Command(reactor::Command::Reactor(
+                    reactor::ReactorCommand::SwitchToNamedWorkspace(name),
+                )));
+            }
             Command(Wm(SwitchToWorkspace(ws_sel))) => {
                 let maybe_index: Option<usize> = match &ws_sel {
                     WorkspaceSelector::Index(i) => Some(*i),
@@ -334,8 +362,9 @@ impl WmController {
                     );
                 }
             }
-            Command(Wm(MoveWindowToWorkspace(ws_sel))) => {
-                let maybe_index: Option<usize> = match &ws_sel {
+            Command(Wm(MoveWindowToWorkspace(target))) => {
+                let ws_sel = target.workspace();
+                let maybe_index: Option<usize> = match ws_sel {
                     WorkspaceSelector::Index(i) => Some(*i),
                     WorkspaceSelector::Name(name) => self
                         .config
@@ -351,6 +380,7 @@ impl WmController {
                         layout::LayoutCommand::MoveWindowToWorkspace {
                             workspace: workspace_index,
                             window_id: None,
+                            follow: target.follow(),
                         },
                     )));
                 } else {
@@ -360,6 +390,36 @@ impl WmController {
                     );
                 }
             }
+            Command(Wm(MoveWindowToNewWorkspace { follow })) => {
+                self.events_tx.send(reactor::Event::Command(reactor::Command::Layout(
+                    layout::LayoutCommand::MoveWindowToNewWorkspace { window_id: None, follow },
+                )));
+            }
+            Command(Wm(MoveContainerToWorkspace(ws_sel))) => {
+                let maybe_index: Option<usize> = match &ws_sel {
+                    WorkspaceSelector::Index(i) => Some(*i),
+                    WorkspaceSelector::Name(name) => self
+                        .config
+                        .config
+                        .virtual_workspaces
+                        .workspace_names
+                        .iter()
+                        .position(|n| n == name),
+                };
+
+                if let Some(workspace_index) = maybe_index {
+                    self.events_tx.send(reactor::Event::Command(reactor::Command::Layout(
+                        layout::LayoutCommand::MoveContainerToWorkspace {
+                            workspace: workspace_index,
+                        },
+                    )));
+                } else {
+                    tracing::warn!(
+                        "Hotkey requested move container to workspace {:?} but it could not be resolved; ignoring",
+                        ws_sel
+                    );
+                }
+            }
             Command(Wm(CreateWorkspace)) => {
                 self.events_tx.send(reactor::Event::Command(reactor::Command::Layout(
                     layout::LayoutCommand::CreateWorkspace,
@@ -370,6 +430,84 @@ impl WmController {
                     layout::LayoutCommand::SwitchToLastWorkspace,
                 )));
             }
+            Command(Wm(WorkspaceBack)) => {
+                self.events_tx.send(reactor::Event::Command(reactor::Command::Layout(
+                    layout::LayoutCommand::WorkspaceBack,
+                )));
+            }
+            Command(Wm(WorkspaceForward)) => {
+                self.events_tx.send(reactor::Event::Command(reactor::Command::Layout(
+                    layout::LayoutCommand::WorkspaceForward,
+                )));
+            }
+            Command(Wm(RenameWorkspace(name))) => {
+                self.events_tx.send(reactor::Event::Command(reactor::Command::Layout(
+                    layout::LayoutCommand::RenameWorkspace { workspace: None, name },
+                )));
+            }
+            Command(Wm(DeleteWorkspace)) => {
+                self.events_tx.send(reactor::Event::Command(reactor::Command::Layout(
+                    layout::LayoutCommand::DeleteWorkspace { workspace: None },
+                )));
+            }
+            Command(Wm(MoveWorkspaceToIndex(index))) => {
+                self.events_tx.send(reactor::Event::Command(reactor::Command::Layout(
+                    layout::LayoutCommand::MoveWorkspaceToIndex { workspace: None, index },
+                )));
+            }
+            Command(Wm(SwapWorkspaces(other))) => {
+                // Workspaces can be renamed and reordered, so names are
+                // resolved against the live list rather than the config.
+                self.events_tx.send(reactor::Event::Command(reactor::Command::Layout(
+                    layout::LayoutCommand::SwapWorkspaces { workspace: None, other },
+                )));
+            }
+            Command(Wm(ToggleTag(ws_sel))) => {
+                let maybe_index: Option<usize> = match &ws_sel {
+                    WorkspaceSelector::Index(i) => Some(*i),
+                    WorkspaceSelector::Name(name) => self
+                        .config
+                        .config
+                        .virtual_workspaces
+                        .workspace_names
+                        .iter()
+                        .position(|n| n == name),
+                };
+
+                if let Some(index) = maybe_index {
+                    self.events_tx.send(reactor::Event::Command(reactor::Command::Layout(
+                        layout::LayoutCommand::ToggleTag(index),
+                    )));
+                } else {
+                    tracing::warn!(
+                        "Hotkey requested toggle tag {:?} but it could not be resolved; ignoring",
+                        ws_sel
+                    );
+                }
+            }
+            Command(Wm(ToggleView(ws_sel))) => {
+                let maybe_index: Option<usize> = match &ws_sel {
+                    WorkspaceSelector::Index(i) => Some(*i),
+                    WorkspaceSelector::Name(name) => self
+                        .config
+                        .config
+                        .virtual_workspaces
+                        .workspace_names
+                        .iter()
+                        .position(|n| n == name),
+                };
+
+                if let Some(index) = maybe_index {
+                    self.events_tx.send(reactor::Event::Command(reactor::Command::Layout(
+                        layout::LayoutCommand::ToggleView(index),
+                    )));
+                } else {
+                    tracing::warn!(
+                        "Hotkey requested toggle view {:?} but it could not be resolved; ignoring",
+                        ws_sel
+                    );
+                }
+            }
             Command(Wm(ShowMissionControlAll)) => {
                 if let Some(tx) = &self.mission_control_tx {
                     let _ = tx.try_send(mission_control::Event::ShowAll);
@@ -5,6 +5,7 @@ use clap::{Parser, Subcommand};
 use rift_wm::actor::reactor::{self, DisplaySelector};
 use rift_wm::ipc::{RiftCommand, RiftMachClient, RiftRequest, RiftResponse};
 use rift_wm::layout_engine as layout;
+use rift_wm::model::virtual_workspace::AppRuleQuery;
 use rift_wm::sys::window_server::WindowServerId;
 use serde_json::Value;
 
@@ -25,6 +26,10 @@ enum Commands {
     },
     /// Execute commands in rift
     Execute {
+        /// Run the command on every managed window matching these criteria, e.g.
+        /// 'app_name~=Slack' or 'app_id=com.apple.Safari,title~=Docs'
+        #[arg(long = "where", value_name = "CRITERIA")]
+        criteria: Option<String>,
         #[command(subcommand)]
         command: ExecuteCommands,
     },
@@ -33,6 +38,16 @@ enum Commands {
         #[command(subcommand)]
         subscribe: SubscribeCommands,
     },
+    /// Debug app rule matching
+    Rules {
+        #[command(subcommand)]
+        rules: RulesCommands,
+    },
+    /// Save, list and load named layouts
+    Layout {
+        #[command(subcommand)]
+        layout: SavedLayoutCommands,
+    },
     /// Manage the launchd service for rift
     Service {
         #[command(subcommand)]
@@ -78,6 +93,37 @@ enum QueryCommands {
     Metrics,
 }
 
+#[derive(Subcommand)]
+enum RulesCommands {
+    /// Show which app rules match a window with these properties, and which one wins
+    Test {
+        #[arg(long)]
+        app_id: Option<String>,
+        #[arg(long)]
+        app_name: Option<String>,
+        #[arg(long)]
+        title: Option<String>,
+        #[arg(long)]
+        ax_role: Option<String>,
+        #[arg(long)]
+        ax_subrole: Option<String>,
+    },
+    /// Show which app rules match a live window, and which one wins
+    Explain { window_id: String },
+    /// Re-apply app rules to all existing windows
+    Reapply,
+}
+
+#[derive(Subcommand)]
+enum SavedLayoutCommands {
+    /// List saved layouts
+    List,
+    /// Save the active workspace's layout under a name
+    Save { name: String },
+    /// Load a saved layout into the active workspace
+    Load { name: String },
+}
+
 #[derive(Subcommand)]
 enum ExecuteCommands {
     /// Window management commands
@@ -126,6 +172,20 @@ enum WindowCommands {
     Focus {
         direction: String, // up, down, left, right
     },
+    /// Focus the previously focused window, switching workspace if needed
+    FocusBack,
+    /// Step to the next window in most-recently-used order
+    FocusMruNext,
+    /// Step to the previous window in most-recently-used order
+    FocusMruPrev,
+    /// Mark the focused window with a name (moves the mark if already used)
+    Mark { name: String },
+    /// Remove all marks from the focused window
+    Unmark,
+    /// Focus the window with the given mark, switching workspace or display if needed
+    FocusMark { name: String },
+    /// Swap the focused window with the window carrying the given mark
+    SwapWithMark { name: String },
     /// Toggle window floating state
     ToggleFloat,
     /// Toggle fullscreen mode (fills the whole screen, ignores outer gaps)
@@ -163,11 +223,53 @@ enum WorkspaceCommands {
     MoveWindow {
         workspace_id: usize,
         window_id: Option<u32>,
+        /// Switch to the workspace afterwards
+        #[arg(long)]
+        follow: bool,
     },
+    /// Move current window to a newly created workspace
+    MoveWindowToNew {
+        window_id: Option<u32>,
+        /// Switch to the new workspace afterwards
+        #[arg(long)]
+        follow: bool,
+    },
+    /// Move the selected container, keeping its structure, to workspace
+    MoveContainer { workspace_id: usize },
     /// Create a new workspace
     Create,
     /// Switch to the last workspace
     Last,
+    /// Go back to the previously shown workspace in the history
+    Back,
+    /// Go forward again after `back`
+    Forward,
+    /// Rename a workspace (defaults to the active workspace)
+    Rename {
+        name: String,
+        #[arg(long)]
+        workspace_id: Option<usize>,
+    },
+    /// Delete a workspace, moving its windows to a fallback workspace
+    Delete {
+        #[arg(long)]
+        workspace_id: Option<usize>,
+    },
+    /// Move a workspace to a new position in the workspace list
+    MoveToIndex {
+        index: usize,
+        #[arg(long)]
+        workspace_id: Option<usize>,
+    },
+    /// Swap the contents of two workspaces (defaults to swapping with the active workspace)
+    Swap {
+        other: usize,
+        #[arg(long)]
+        workspace_id: Option<usize>,
+    },    /// Add or remove a workspace tag on the focused window
+    ToggleTag { workspace_id: usize },
+    /// Show or hide a workspace's windows alongside the active workspace
+    ToggleView { workspace_id: usize },
 }
 
 #[derive(Subcommand)]
@@ -206,6 +308,10 @@ enum LayoutCommands {
     Unjoin,
     /// Toggle floating on the focused selection (tree focus)
     ToggleFocusFloat,
+    /// Revert the last command that reshaped the layout (move, join, group, resize...)
+    Undo,
+    /// Reapply the last layout change reverted with undo
+    Redo,
 }
 
 #[derive(Subcommand)]
@@ -329,6 +435,30 @@ enum DisplayCommands {
         #[arg(long)]
         window_id: Option<u32>,
     },
+    /// Move the active workspace and all its windows to a display by direction, index, or UUID.
+    MoveWorkspace {
+        /// Direction relative to the current display (left, right, up, down).
+        #[arg(long)]
+        direction: Option<String>,
+        /// Display index (0-based).
+        #[arg(long)]
+        index: Option<usize>,
+        /// Display UUID.
+        #[arg(long)]
+        uuid: Option<String>,
+    },
+    /// Swap the active workspace with the one shown on a display by direction, index, or UUID.
+    SwapWorkspace {
+        /// Direction relative to the current display (left, right, up, down).
+        #[arg(long)]
+        direction: Option<String>,
+        /// Display index (0-based).
+        #[arg(long)]
+        index: Option<usize>,
+        /// Display UUID.
+        #[arg(long)]
+        uuid: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -426,8 +556,10 @@ fn main() {
 fn build_request(command: Commands) -> Result<RiftRequest, String> {
     match command {
         Commands::Query { query } => build_query_request(query),
-        Commands::Execute { command } => build_execute_request(command),
+        Commands::Execute { criteria, command } => build_execute_request(command, criteria),
         Commands::Subscribe { subscribe } => build_subscribe_request(subscribe),
+        Commands::Rules { rules } => build_rules_request(rules),
+        Commands::Layout { layout } => build_saved_layout_request(layout),
         Commands::Service { .. } => Err(
             "Service commands are handled locally and should not be sent to the rift server."
                 .to_string(),
@@ -447,6 +579,46 @@ fn build_query_request(query: QueryCommands) -> Result<RiftRequest, String> {
     }
 }
 
+fn build_rules_request(rules: RulesCommands) -> Result<RiftRequest, String> {
+    match rules {
+        RulesCommands::Test {
+            app_id,
+            app_name,
+            title,
+            ax_role,
+            ax_subrole,
+        } => Ok(RiftRequest::TestAppRules {
+            query: AppRuleQuery {
+                app_id,
+                app_name,
+                title,
+                ax_role,
+                ax_subrole,
+            },
+        }),
+        RulesCommands::Explain { window_id } => Ok(RiftRequest::ExplainAppRules { window_id }),
+        RulesCommands::Reapply => {
+            let command = RiftCommand::Reactor(reactor::Command::Reactor(
+                reactor::ReactorCommand::ReapplyAppRules,
+            ));
+            let command = serde_json::to_string(&command)
+                .map_err(|e| format!("Failed to serialize command: {}", e))?;
+            Ok(RiftRequest::ExecuteCommand { command, args: vec![] })
+        }
+    }
+}
+
+fn build_saved_layout_request(layout: SavedLayoutCommands) -> Result<RiftRequest, String> {
+    let command = match layout {
+        SavedLayoutCommands::List => return Ok(RiftRequest::ListLayouts),
+        SavedLayoutCommands::Save { name } => reactor::ReactorCommand::LayoutSave(name),
+        SavedLayoutCommands::Load { name } => reactor::ReactorCommand::LayoutLoad(name),
+    };
+    let command = serde_json::to_string(&RiftCommand::Reactor(reactor::Command::Reactor(command)))
+        .map_err(|e| format!("Failed to serialize command: {}", e))?;
+    Ok(RiftRequest::ExecuteCommand { command, args: vec![] })
+}
+
 fn build_subscribe_request(sub: SubscribeCommands) -> Result<RiftRequest, String> {
     match sub {
         SubscribeCommands::Mach { event } => Ok(RiftRequest::Subscribe { event }),
@@ -459,7 +631,10 @@ fn build_subscribe_request(sub: SubscribeCommands) -> Result<RiftRequest, String
     }
 }
 
-fn build_execute_request(execute: ExecuteCommands) -> Result<RiftRequest, String> {
+fn build_execute_request(
+    execute: ExecuteCommands,
+    criteria: Option<String>,
+) -> Result<RiftRequest, String> {
     let rift_command = match execute {
         ExecuteCommands::Window { window_cmd } => map_window_command(window_cmd)?,
         ExecuteCommands::Workspace { workspace_cmd } => map_workspace_command(workspace_cmd)?,
@@ -477,6 +652,19 @@ fn build_execute_request(execute: ExecuteCommands) -> Result<RiftRequest, String
         )),
     };
 
+    let rift_command = match (criteria, rift_command) {
+        (None, rift_command) => rift_command,
+        (Some(criteria), RiftCommand::Reactor(command)) => {
+            let target = criteria.parse().map_err(|e| format!("Invalid --where: {}", e))?;
+            let targeted = reactor::TargetedCommand { target, command: Box::new(command) };
+            if !targeted.is_supported() {
+                return Err("--where can only be used with window and layout commands".to_string());
+            }
+            RiftCommand::Reactor(reactor::Command::Targeted(targeted))
+        }
+        (Some(_), _) => return Err("--where cannot be used with config commands".to_string()),
+    };
+
     if let RiftCommand::Config(rift_wm::common::config::ConfigCommand::GetConfig) = &rift_command {
         return Ok(RiftRequest::GetConfig);
     }
@@ -513,6 +701,25 @@ fn map_window_command(cmd: WindowCommands) -> Result<RiftCommand, String> {
         WindowCommands::Focus { direction } => Ok(RiftCommand::Reactor(reactor::Command::Layout(
             LC::MoveFocus(direction.into()),
         ))),
+        WindowCommands::FocusBack => {
+            Ok(RiftCommand::Reactor(reactor::Command::Layout(LC::FocusBack)))
+        }
+        WindowCommands::FocusMruNext => {
+            Ok(RiftCommand::Reactor(reactor::Command::Layout(LC::FocusMruNext)))
+        }
+        WindowCommands::FocusMruPrev => {
+            Ok(RiftCommand::Reactor(reactor::Command::Layout(LC::FocusMruPrev)))
+        }
+        WindowCommands::Mark { name } => {
+            Ok(RiftCommand::Reactor(reactor::Command::Layout(LC::Mark(name))))
+        }
+        WindowCommands::Unmark => Ok(RiftCommand::Reactor(reactor::Command::Layout(LC::Unmark))),
+        WindowCommands::FocusMark { name } => {
+            Ok(RiftCommand::Reactor(reactor::Command::Layout(LC::FocusMark(name))))
+        }
+        WindowCommands::SwapWithMark { name } => {
+            Ok(RiftCommand::Reactor(reactor::Command::Layout(LC::SwapWithMark(name))))
+        }
         WindowCommands::ToggleFloat => Ok(RiftCommand::Reactor(reactor::Command::Layout(
             LC::ToggleWindowFloating,
         ))),
@@ -567,11 +774,18 @@ fn map_workspace_command(cmd: WorkspaceCommands) -> Result<RiftCommand, String>
         WorkspaceCommands::Switch { workspace_id } => Ok(RiftCommand::Reactor(
             reactor::Command::Layout(LC::SwitchToWorkspace(workspace_id)),
         )),
-        WorkspaceCommands::MoveWindow { workspace_id, window_id } => Ok(RiftCommand::Reactor(
-            reactor::Command::Layout(LC::MoveWindowToWorkspace {
+        WorkspaceCommands::MoveWindow { workspace_id, window_id, follow } => {
+            Ok(RiftCommand::Reactor(reactor::Command::Layout(LC::MoveWindowToWorkspace {
                 workspace: workspace_id,
                 window_id,
-            }),
+                follow,
+            })))
+        }
+        WorkspaceCommands::MoveWindowToNew { window_id, follow } => Ok(RiftCommand::Reactor(
+            reactor::Command::Layout(LC::MoveWindowToNewWorkspace { window_id, follow }),
+        )),
+        WorkspaceCommands::MoveContainer { workspace_id } => Ok(RiftCommand::Reactor(
+            reactor::Command::Layout(LC::MoveContainerToWorkspace { workspace: workspace_id }),
         )),
         WorkspaceCommands::Create => Ok(RiftCommand::Reactor(reactor::Command::Layout(
             LC::CreateWorkspace,
@@ -579,6 +793,36 @@ fn map_workspace_command(cmd: WorkspaceCommands) -> Result<RiftCommand, String>
         WorkspaceCommands::Last => Ok(RiftCommand::Reactor(reactor::Command::Layout(
             LC::SwitchToLastWorkspace,
         ))),
+        WorkspaceCommands::Back => {
+            Ok(RiftCommand::Reactor(reactor::Command::Layout(LC::WorkspaceBack)))
+        }
+        WorkspaceCommands::Forward => {
+            Ok(RiftCommand::Reactor(reactor::Command::Layout(LC::WorkspaceForward)))
+        }
+        WorkspaceCommands::Rename { name, workspace_id } => Ok(RiftCommand::Reactor(
+            reactor::Command::Layout(LC::RenameWorkspace { workspace: workspace_id, name }),
+        )),
+        WorkspaceCommands::Delete { workspace_id } => Ok(RiftCommand::Reactor(
+            reactor::Command::Layout(LC::DeleteWorkspace { workspace: workspace_id }),
+        )),
+        WorkspaceCommands::MoveToIndex { index, workspace_id } => Ok(RiftCommand::Reactor(
+            reactor::Command::Layout(LC::MoveWorkspaceToIndex {
+                workspace: workspace_id,
+                index,
+            }),
+        )),
+        WorkspaceCommands::Swap { other, workspace_id } => Ok(RiftCommand::Reactor(
+            reactor::Command::Layout(LC::SwapWorkspaces {
+                workspace: workspace_id,
+                other: rift_wm::common::config::WorkspaceSelector::Index(other),
+            }),
+        )),
+        WorkspaceCommands::ToggleTag { workspace_id } => Ok(RiftCommand::Reactor(
+            reactor::Command::Layout(LC::ToggleTag(workspace_id)),
+        )),
+        WorkspaceCommands::ToggleView { workspace_id } => Ok(RiftCommand::Reactor(
+            reactor::Command::Layout(LC::ToggleView(workspace_id)),
+        )),
     }
 }
 
@@ -614,6 +858,12 @@ fn map_layout_command(cmd: LayoutCommands) -> Result<RiftCommand, String> {
         LayoutCommands::ToggleFocusFloat => Ok(RiftCommand::Reactor(reactor::Command::Layout(
             LC::ToggleFocusFloating,
         ))),
+        LayoutCommands::Undo => {
+            Ok(RiftCommand::Reactor(reactor::Command::Layout(LC::UndoLayout)))
+        }
+        LayoutCommands::Redo => {
+            Ok(RiftCommand::Reactor(reactor::Command::Layout(LC::RedoLayout)))
+        }
     }
 }
 
@@ -744,6 +994,20 @@ fn map_display_command(cmd: DisplayCommands) -> Result<RiftCommand, String> {
                 window_id,
             },
         ))),
+        DisplayCommands::MoveWorkspace { direction, index, uuid } => {
+            Ok(RiftCommand::Reactor(reactor::Command::Reactor(
+                reactor::ReactorCommand::MoveWorkspaceToDisplay {
+                    selector: build_display_selector(direction, index, uuid)?,
+                },
+            )))
+        }
+        DisplayCommands::SwapWorkspace { direction, index, uuid } => {
+            Ok(RiftCommand::Reactor(reactor::Command::Reactor(
+                reactor::ReactorCommand::SwapWorkspaceWithDisplay {
+                    selector: build_display_selector(direction, index, uuid)?,
+                },
+            )))
+        }
     }
 }
 
@@ -18,11 +18,13 @@ use rift_wm::actor::debug_tree::DebugTree;
 use rift_wm::actor::stack_line::StackLine;
 use rift_wm::actor::window_notify as window_notify_actor;
 use rift_wm::actor::wm_controller::{self, WmController};
-use rift_wm::common::config::{Config, config_file, restore_file};
+use rift_wm::common::config::{Config, config_file, layouts_file, placements_file, restore_file};
 use rift_wm::common::log;
 use rift_wm::common::util::execute_startup_commands;
 use rift_wm::ipc;
-use rift_wm::layout_engine::LayoutEngine;
+use rift_wm::layout_engine::{LayoutEngine, migration};
+use rift_wm::layout_engine::snapshot::LayoutSnapshots;
+use rift_wm::model::placement_memory::PlacementMemory;
 use rift_wm::model::tx_store::WindowTxStore;
 use rift_wm::sys::accessibility::ensure_accessibility_permission;
 use rift_wm::sys::executor::Executor;
@@ -30,6 +32,7 @@ use rift_wm::sys::screen::{CoordinateConverter, displays_have_separate_spaces};
 use rift_wm::sys::service::{ServiceCommands, handle_service_command};
 use rift_wm::sys::skylight::{CGSEventType, KnownCGSEvent};
 use tokio::join;
+use tracing::warn;
 
 embed_plist::embed_info_plist!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/Info.plist"));
 
@@ -50,12 +53,14 @@ struct Cli {
     no_animate: bool,
 
     /// Check whether the restore file can be loaded without actually starting
-    /// the window manager.
+    /// the window manager, reporting its format version and whether it can be
+    /// migrated to the current one.
     #[arg(long)]
     validate: bool,
 
-    /// Restore the configuration saved with the save_and_exit command. This is
-    /// only useful within the same session.
+    /// Restore the layout saved with the save_and_exit command or by autosave.
+    /// Windows are matched back up by app, title, window server id and frame,
+    /// so this also works after apps relaunch or the system restarts.
     #[arg(long)]
     restore: bool,
 
@@ -139,7 +144,7 @@ Enable it in System Settings > Desktop & Dock (Mission Control) and restart Rift
     config.settings.default_disable |= opt.default_disable;
 
     if opt.validate {
-        LayoutEngine::load(restore_file()).unwrap();
+        validate_restore_file();
         return;
     }
 
@@ -147,8 +152,23 @@ Enable it in System Settings > Desktop & Dock (Mission Control) and restart Rift
 
     let (broadcast_tx, broadcast_rx) = rift_wm::actor::channel();
 
-    let layout = if opt.restore {
-        LayoutEngine::load(restore_file()).unwrap()
+    let restored = if opt.restore || config.settings.restore_layout_on_start {
+        match LayoutEngine::load(restore_file()) {
+            Ok(layout) => Some(layout),
+            Err(e) => {
+                // There is nothing to restore on the first run.
+                if restore_file().exists() {
+                    warn!("Could not restore layout, starting with a fresh one: {e}");
+                }
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let mut layout = if let Some(mut layout) = restored {
+        layout.set_layout_settings(&config.settings.layout);
+        layout
     } else {
         LayoutEngine::new(
             &config.virtual_workspaces,
@@ -156,6 +176,13 @@ Enable it in System Settings > Desktop & Dock (Mission Control) and restart Rift
             Some(broadcast_tx.clone()),
         )
     };
+    if config.virtual_workspaces.remember_app_placements {
+        layout
+            .virtual_workspace_manager_mut()
+            .set_placement_memory(PlacementMemory::load(placements_file()));
+    }
+    layout.set_layout_snapshots(LayoutSnapshots::load(layouts_file()));
+    layout.set_workspace_templates(config.workspace_templates.clone());
     let (event_tap_tx, event_tap_rx) = rift_wm::actor::channel();
     let (menu_tx, menu_rx) = rift_wm::actor::channel();
     let (stack_line_tx, stack_line_rx) = rift_wm::actor::channel();
@@ -301,6 +328,37 @@ Enable it in System Settings > Desktop & Dock (Mission Control) and restart Rift
     });
 }
 
+fn validate_restore_file() {
+    let path = restore_file();
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!("Could not read {}: {e}", path.display());
+            process::exit(1);
+        }
+    };
+    let (version, _) = migration::split_header(&contents);
+    println!(
+        "{}: layout format {version} (current format is {})",
+        path.display(),
+        migration::LAYOUT_FORMAT_VERSION
+    );
+    if version == migration::LAYOUT_FORMAT_VERSION {
+        println!("No migration needed");
+    } else if migration::can_migrate(version) {
+        println!("Can migrate to format {}", migration::LAYOUT_FORMAT_VERSION);
+    } else {
+        println!("Cannot migrate: this build does not know format {version}");
+    }
+    match LayoutEngine::load(path) {
+        Ok(_) => println!("Layout loads successfully"),
+        Err(e) => {
+            eprintln!("Layout fails to load: {e:#}");
+            process::exit(1);
+        }
+    }
+}
+
 #[cfg(panic = "unwind")]
 fn install_panic_hook() {
     // Abort on panic instead of propagating panics to the main thread.
@@ -2,10 +2,12 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use anyhow::bail;
+use objc2_core_foundation::{CGPoint, CGRect, CGSize};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use super::collections::HashMap;
+use crate::actor::reactor::{Command, DisplaySelector};
 use crate::actor::wm_controller::WmCommand;
 use crate::sys::hotkey::{Hotkey, HotkeySpec};
 
@@ -58,6 +60,8 @@ pub enum ConfigCommand {
 
 pub fn data_dir() -> PathBuf { dirs::home_dir().unwrap().join(".rift") }
 pub fn restore_file() -> PathBuf { data_dir().join("layout.ron") }
+pub fn placements_file() -> PathBuf { data_dir().join("placements.ron") }
+pub fn layouts_file() -> PathBuf { data_dir().join("layouts.ron") }
 pub fn config_file() -> PathBuf {
     dirs::home_dir().unwrap().join(".config").join("rift").join("config.toml")
 }
@@ -81,10 +85,39 @@ pub struct VirtualWorkspaceSettings {
     pub default_workspace: usize,
     #[serde(default)]
     pub reapply_app_rules_on_title_change: bool,
+    /// Re-apply app rules to existing windows when a config reload changes them.
+    #[serde(default)]
+    pub reapply_app_rules_on_config_reload: bool,
+    /// Remember where each app's windows last lived and return new windows of
+    /// the app there when no app rule places them.
+    #[serde(default)]
+    pub remember_app_placements: bool,
+    /// Keep exactly one trailing empty workspace, creating workspaces on demand
+    /// and removing empty inactive ones beyond `default_workspace_count`.
+    #[serde(default)]
+    pub dynamic: bool,
+    /// Pins named workspaces to a display, keyed by workspace name. Values are a
+    /// display UUID or `"main"` for the primary display. Pinned workspaces only
+    /// exist on that display's space.
+    #[serde(default)]
+    pub workspace_displays: HashMap<String, String>,
+    #[serde(default)]
+    pub workspace_model: WorkspaceModel,
     #[serde(default)]
     pub app_rules: Vec<AppWorkspaceRule>,
 }
 
+/// How virtual workspaces relate to displays.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkspaceModel {
+    /// Every display (macOS space) has its own independent set of workspaces.
+    #[default]
+    PerDisplay,
+    /// One shared set of workspaces; any workspace can be shown on any display.
+    Global,
+}
+
 // Allow specifying a workspace by numeric index or by name in the config.
 // This supports both `workspace = 2` and `workspace = "coding"` in app rules.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
@@ -94,6 +127,30 @@ pub enum WorkspaceSelector {
     Name(String),
 }
 
+/// Target of `move_window_to_workspace`: either a bare selector or a table
+/// such as `{ workspace = 3, follow = true }`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+#[serde(untagged)]
+pub enum MoveWindowTarget {
+    Workspace(WorkspaceSelector),
+    Detailed {
+        workspace: WorkspaceSelector,
+        #[serde(default)]
+        follow: bool,
+    },
+}
+
+impl MoveWindowTarget {
+    pub fn workspace(&self) -> &WorkspaceSelector {
+        match self {
+            MoveWindowTarget::Workspace(workspace)
+            | MoveWindowTarget::Detailed { workspace, .. } => workspace,
+        }
+    }
+
+    pub fn follow(&self) -> bool { matches!(self, MoveWindowTarget::Detailed { follow: true, .. }) }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct AppWorkspaceRule {
@@ -131,6 +188,310 @@ pub struct AppWorkspaceRule {
     /// non-empty string and will be compared against the accessibility subrole
     /// reported by the AX APIs for a window (exact string match).
     pub ax_subrole: Option<String>,
+
+    /// Optional: Initial frame for floating windows, e.g.
+    /// `{ x = "70%", y = 40, w = 480, h = "30%" }`. Numbers are points measured
+    /// from the display's top-left corner; strings ending in `%` are relative to
+    /// the display's size.
+    pub frame: Option<RuleFrame>,
+    /// Center floating windows on their display when they first appear.
+    #[serde(default)]
+    pub center: bool,
+    /// Optional: Display that new matching windows are moved to (index, UUID or
+    /// direction from the display they opened on).
+    pub display: Option<DisplaySelector>,
+    /// Make new matching windows fullscreen: tiled windows fill their layout and
+    /// floating windows cover the display.
+    #[serde(default)]
+    pub fullscreen: bool,
+    /// Whether new matching windows may take focus (defaults to true). `false`
+    /// hands focus back to the previously focused window.
+    #[serde(default = "yes")]
+    pub focus: bool,
+    /// Optional: Bundle identifier of an app whose tiled window new matching
+    /// windows are stacked with.
+    pub stack_with: Option<String>,
+}
+
+impl Default for AppWorkspaceRule {
+    /// A rule without criteria or actions, with the same defaults as an empty
+    /// config entry.
+    fn default() -> Self {
+        AppWorkspaceRule {
+            app_id: None,
+            workspace: None,
+            floating: false,
+            manage: true,
+            app_name: None,
+            title_regex: None,
+            title_substring: None,
+            ax_role: None,
+            ax_subrole: None,
+            frame: None,
+            center: false,
+            display: None,
+            fullscreen: false,
+            focus: true,
+            stack_with: None,
+        }
+    }
+}
+
+impl AppWorkspaceRule {
+    /// Whether the rule does anything beyond choosing a workspace and floating.
+    pub fn has_placement_actions(&self) -> bool {
+        self.frame.is_some()
+            || self.center
+            || self.display.is_some()
+            || self.fullscreen
+            || !self.focus
+            || self.stack_with.is_some()
+    }
+}
+
+/// One component of an app rule `frame`: points, or a percentage of the
+/// display such as `"50%"`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(try_from = "RuleLengthRepr", into = "RuleLengthRepr")]
+pub enum RuleLength {
+    Points(f64),
+    Percent(f64),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RuleLengthRepr {
+    Number(f64),
+    Text(String),
+}
+
+impl TryFrom<RuleLengthRepr> for RuleLength {
+    type Error = String;
+
+    fn try_from(repr: RuleLengthRepr) -> Result<Self, Self::Error> {
+        match repr {
+            RuleLengthRepr::Number(points) => Ok(RuleLength::Points(points)),
+            RuleLengthRepr::Text(text) => text
+                .trim()
+                .strip_suffix('%')
+                .and_then(|percent| percent.trim().parse().ok())
+                .map(RuleLength::Percent)
+                .ok_or_else(|| {
+                    format!("expected points or a percentage like \"50%\", got {:?}", text)
+                }),
+        }
+    }
+}
+
+impl From<RuleLength> for RuleLengthRepr {
+    fn from(length: RuleLength) -> Self {
+        match length {
+            RuleLength::Points(points) => RuleLengthRepr::Number(points),
+            RuleLength::Percent(percent) => RuleLengthRepr::Text(format!("{}%", percent)),
+        }
+    }
+}
+
+impl RuleLength {
+    fn resolve(self, extent: f64) -> f64 {
+        match self {
+            RuleLength::Points(points) => points,
+            RuleLength::Percent(percent) => extent * percent / 100.0,
+        }
+    }
+}
+
+/// Initial frame set by an app rule; see [`AppWorkspaceRule::frame`].
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+pub struct RuleFrame {
+    pub x: RuleLength,
+    pub y: RuleLength,
+    pub w: RuleLength,
+    pub h: RuleLength,
+}
+
+impl RuleFrame {
+    /// The frame on `screen`, with positions measured from its top-left corner.
+    pub fn resolve(&self, screen: CGRect) -> CGRect {
+        let CGSize { width, height } = screen.size;
+        CGRect::new(
+            CGPoint::new(
+                screen.origin.x + self.x.resolve(width),
+                screen.origin.y + self.y.resolve(height),
+            ),
+            CGSize::new(self.w.resolve(width), self.h.resolve(height)),
+        )
+    }
+}
+
+/// Selects windows for a command, using the same fields and matching as
+/// [`AppWorkspaceRule`].
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct WindowCriteria {
+    pub app_id: Option<String>,
+    pub app_name: Option<String>,
+    pub title_regex: Option<String>,
+    pub title_substring: Option<String>,
+    pub ax_role: Option<String>,
+    pub ax_subrole: Option<String>,
+}
+
+impl WindowCriteria {
+    /// The criteria as an app rule, so they can be checked by the rule matcher.
+    pub fn to_app_rule(&self) -> AppWorkspaceRule {
+        AppWorkspaceRule {
+            app_id: self.app_id.clone(),
+            workspace: None,
+            floating: false,
+            manage: true,
+            app_name: self.app_name.clone(),
+            title_regex: self.title_regex.clone(),
+            title_substring: self.title_substring.clone(),
+            ax_role: self.ax_role.clone(),
+            ax_subrole: self.ax_subrole.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Parses comma-separated `key=value` clauses, as used by `rift-cli execute
+/// --where`. `title=` matches a substring and `title~=` a regex; `app_name`
+/// accepts either operator and always matches loosely.
+impl FromStr for WindowCriteria {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut criteria = WindowCriteria::default();
+        for clause in criteria_clauses(s).iter().map(|c| c.trim()).filter(|c| !c.is_empty()) {
+            let (key, regex, value) = if let Some((key, value)) = clause.split_once("~=") {
+                (key, true, value)
+            } else if let Some((key, value)) = clause.split_once('=') {
+                (key, false, value)
+            } else {
+                return Err(format!("expected key=value or key~=value, got '{clause}'"));
+            };
+            let value = Some(value.trim().to_string());
+            match (key.trim(), regex) {
+                ("app_id", false) => criteria.app_id = value,
+                ("app_name", _) => criteria.app_name = value,
+                ("title", true) | ("title_regex", _) => criteria.title_regex = value,
+                ("title", false) | ("title_substring", _) => criteria.title_substring = value,
+                ("ax_role", false) => criteria.ax_role = value,
+                ("ax_subrole", false) => criteria.ax_subrole = value,
+                (key, true) => return Err(format!("'{key}' does not support '~='")),
+                (key, false) => return Err(format!("unknown window criterion '{key}'")),
+            }
+        }
+        if criteria == WindowCriteria::default() {
+            return Err("window criteria cannot be empty".to_string());
+        }
+        Ok(criteria)
+    }
+}
+
+/// Splits `s` only at the commas that begin a new `key=` or `key~=` clause, so
+/// values such as `title~=a{1,3}` keep their commas.
+fn criteria_clauses(s: &str) -> Vec<String> {
+    let starts_clause = |piece: &str| {
+        let piece = piece.trim_start();
+        let key_len = piece
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(piece.len());
+        let rest = &piece[key_len..];
+        key_len > 0 && (rest.starts_with('=') || rest.starts_with("~="))
+    };
+    let mut clauses: Vec<String> = Vec::new();
+    for piece in s.split(',') {
+        match clauses.last_mut() {
+            Some(clause) if !starts_clause(piece) => {
+                clause.push(',');
+                clause.push_str(piece);
+            }
+            _ => clauses.push(piece.to_string()),
+        }
+    }
+    clauses
+}
+
+/// A tiled tree laid out on a workspace when it is first created, or by the
+/// `apply_template` command. Its leaves are slots that the windows matching
+/// their criteria fill as they join the workspace.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WorkspaceTemplate {
+    pub name: String,
+    /// Names of the workspaces that get the template when they are created.
+    #[serde(default)]
+    pub workspaces: Vec<String>,
+    pub layout: TemplateNode,
+}
+
+fn default_template_weight() -> f64 { 1.0 }
+
+/// A node of a [`WorkspaceTemplate`]: a container with `kind` and `children`,
+/// or a window slot described by [`WindowCriteria`] fields.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(untagged)]
+pub enum TemplateNode {
+    Container {
+        kind: crate::layout_engine::LayoutKind,
+        /// Share of the parent container, relative to the siblings.
+        #[serde(default = "default_template_weight")]
+        weight: f64,
+        children: Vec<TemplateNode>,
+    },
+    Window {
+        #[serde(flatten)]
+        criteria: WindowCriteria,
+        #[serde(default = "default_template_weight")]
+        weight: f64,
+    },
+}
+
+impl WorkspaceTemplate {
+    pub fn validate(&self) -> Vec<String> {
+        fn check(node: &TemplateNode, template: &str, issues: &mut Vec<String>) {
+            match node {
+                TemplateNode::Container { weight, children, .. } => {
+                    if children.is_empty() {
+                        issues.push(format!("Template '{template}' has an empty container"));
+                    }
+                    if *weight <= 0.0 {
+                        issues.push(format!("Template '{template}' has a non-positive weight"));
+                    }
+                    for child in children {
+                        check(child, template, issues);
+                    }
+                }
+                TemplateNode::Window { criteria, weight } => {
+                    if *criteria == WindowCriteria::default() {
+                        issues.push(format!(
+                            "Template '{template}' has a slot without app_id, app_name, title_regex, title_substring, ax_role or ax_subrole"
+                        ));
+                    }
+                    if let Some(regex) = &criteria.title_regex
+                        && crate::model::virtual_workspace::compile_title_regex(regex).is_err()
+                    {
+                        issues.push(format!(
+                            "Template '{template}' has an invalid title_regex '{regex}'"
+                        ));
+                    }
+                    if *weight <= 0.0 {
+                        issues.push(format!("Template '{template}' has a non-positive weight"));
+                    }
+                }
+            }
+        }
+
+        let mut issues = Vec::new();
+        if self.name.is_empty() {
+            issues.push("Workspace templates must have a name".to_string());
+        }
+        check(&self.layout, &self.name, &mut issues);
+        issues
+    }
 }
 
 impl Default for VirtualWorkspaceSettings {
@@ -144,6 +505,11 @@ impl Default for VirtualWorkspaceSettings {
             workspace_names: default_workspace_names(),
             default_workspace: 0,
             reapply_app_rules_on_title_change: false,
+            reapply_app_rules_on_config_reload: false,
+            remember_app_placements: false,
+            dynamic: false,
+            workspace_displays: HashMap::default(),
+            workspace_model: WorkspaceModel::default(),
             app_rules: Vec::new(),
         }
     }
@@ -163,7 +529,8 @@ impl VirtualWorkspaceSettings {
             ));
         }
 
-        if self.workspace_names.len() > self.default_workspace_count {
+        // Dynamic workspaces can grow past the default count and pick up the extra names.
+        if !self.dynamic && self.workspace_names.len() > self.default_workspace_count {
             issues.push("More workspace names provided than default_workspace_count".to_string());
         }
 
@@ -174,6 +541,26 @@ impl VirtualWorkspaceSettings {
             ));
         }
 
+        if self.workspace_model == WorkspaceModel::Global {
+            if !self.workspace_displays.is_empty() {
+                issues.push(
+                    "workspace_displays has no effect with workspace_model = \"global\"".to_string(),
+                );
+            }
+            if self.dynamic {
+                issues.push("dynamic has no effect with workspace_model = \"global\"".to_string());
+            }
+        }
+
+        for (name, display) in &self.workspace_displays {
+            if name.is_empty() || display.is_empty() {
+                issues.push(format!(
+                    "workspace_displays entry '{}' = '{}' must have a non-empty workspace name and display",
+                    name, display
+                ));
+            }
+        }
+
         // Validate rules and check duplicates in a single pass
         let mut seen_app_ids = crate::common::collections::HashSet::default();
         let mut seen_app_names = crate::common::collections::HashSet::default();
@@ -266,6 +653,20 @@ impl VirtualWorkspaceSettings {
                     issues.push(format!("Duplicate ax_subrole '{}' in rule {}", ax_sub, index));
                 }
             }
+
+            if (rule.frame.is_some() || rule.center) && !rule.floating {
+                issues.push(format!(
+                    "App rule {} sets frame or center but only floating windows are placed by them",
+                    index
+                ));
+            }
+
+            if !rule.manage && rule.has_placement_actions() {
+                issues.push(format!(
+                    "App rule {} sets placement actions on windows it does not manage",
+                    index
+                ));
+            }
         }
 
         issues
@@ -283,6 +684,8 @@ struct ConfigFile {
     /// e.g., "comb1" = "Alt + Shift" allows using "comb1 + C" in keys
     #[serde(default)]
     modifier_combinations: HashMap<String, String>,
+    #[serde(default)]
+    workspace_templates: Vec<WorkspaceTemplate>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -290,6 +693,7 @@ pub struct Config {
     pub settings: Settings,
     pub keys: Vec<(Hotkey, WmCommand)>,
     pub virtual_workspaces: VirtualWorkspaceSettings,
+    pub workspace_templates: Vec<WorkspaceTemplate>,
 }
 
 unsafe impl Send for Config {}
@@ -342,6 +746,19 @@ pub struct Settings {
     /// Enable hot-reloading of the config file when it changes
     #[serde(default = "yes")]
     pub hot_reload: bool,
+
+    /// Seconds between saves of the layout to the restore file, which
+    /// `rift --restore` loads. 0 disables autosaving; the layout is still
+    /// saved by `save_and_exit` and when rift is terminated. Read once at
+    /// startup, so changing it takes effect after restarting rift.
+    #[serde(default = "default_layout_autosave_interval")]
+    pub layout_autosave_interval: u64,
+
+    /// Restore the saved layout on startup, as if `--restore` was passed.
+    /// Lets the launchd service restore layouts without changing its
+    /// arguments.
+    #[serde(default)]
+    pub restore_layout_on_start: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default, Copy)]
@@ -510,6 +927,8 @@ fn default_mission_control_fade_duration_ms() -> f64 { 180.0 }
 
 fn default_drag_swap_fraction() -> f64 { 0.3 }
 
+fn default_layout_autosave_interval() -> u64 { 60 }
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum HorizontalPlacement {
@@ -545,6 +964,36 @@ pub struct LayoutSettings {
     /// Automatically convert newly created groups to stacks
     #[serde(default = "yes")]
     pub auto_stack_on_group: bool,
+    /// Split the selected window along its longer side when a new window is
+    /// added after it (traditional mode only)
+    #[serde(default)]
+    pub autotile: bool,
+    /// Centered-master layout configuration (used when mode = "centered_master")
+    #[serde(default)]
+    pub centered_master: CenteredMasterSettings,
+    /// Tree normalization applied after every layout mutation (traditional mode)
+    #[serde(default)]
+    pub normalization: NormalizationSettings,
+}
+
+/// Layout tree normalization (traditional mode only)
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(deny_unknown_fields)]
+pub struct NormalizationSettings {
+    /// Remove containers that hold a single child and merge nested containers
+    /// that share their parent's orientation into the parent.
+    #[serde(default)]
+    pub flatten_containers: bool,
+    /// Force nested containers to the opposite orientation of their parent
+    /// instead of merging them.
+    #[serde(default)]
+    pub opposite_orientation_for_nested_containers: bool,
+}
+
+impl NormalizationSettings {
+    pub fn is_enabled(&self) -> bool {
+        self.flatten_containers || self.opposite_orientation_for_nested_containers
+    }
 }
 
 /// Layout mode enum
@@ -556,6 +1005,8 @@ pub enum LayoutMode {
     Traditional,
     /// Binary space partitioning tiling
     Bsp,
+    /// Centered master column with side stacks (for ultrawide displays)
+    CenteredMaster,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
@@ -584,6 +1035,11 @@ pub struct StackSettings {
     /// - "horizontal"/"vertical": explicitly use a specific orientation
     #[serde(default = "default_stack_orientation")]
     pub default_orientation: StackDefaultOrientation,
+
+    /// Accordion padding - how much of each collapsed window in an accordion
+    /// container stays visible next to the selected window (in pixels).
+    #[serde(default = "default_accordion_padding")]
+    pub accordion_padding: f64,
 }
 
 /// Gap configuration for window spacing
@@ -643,11 +1099,50 @@ pub struct GapOverride {
     pub inner: Option<InnerGaps>,
 }
 
+/// Centered-master layout configuration
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CenteredMasterSettings {
+    /// Fraction of the usable width given to the master column (0.1 - 0.9)
+    #[serde(default = "default_master_ratio")]
+    pub master_ratio: f64,
+    /// Maximum width (in pixels) of a window when it is alone on the workspace.
+    /// The window is centered when the screen is wider than this.
+    #[serde(default)]
+    pub single_window_max_width: Option<f64>,
+    /// Display-specific overrides keyed by display UUID
+    #[serde(default)]
+    pub per_display: HashMap<String, CenteredMasterOverride>,
+}
+
+/// Overrides for the centered-master layout on a per-display basis
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct CenteredMasterOverride {
+    /// Override the master column ratio for the display
+    #[serde(default)]
+    pub master_ratio: Option<f64>,
+    /// Override the single window maximum width for the display
+    #[serde(default)]
+    pub single_window_max_width: Option<f64>,
+}
+
+impl Default for CenteredMasterSettings {
+    fn default() -> Self {
+        Self {
+            master_ratio: default_master_ratio(),
+            single_window_max_width: None,
+            per_display: HashMap::default(),
+        }
+    }
+}
+
 impl Default for StackSettings {
     fn default() -> Self {
         Self {
             stack_offset: default_stack_offset(),
             default_orientation: default_stack_orientation(),
+            accordion_padding: default_accordion_padding(),
         }
     }
 }
@@ -691,10 +1186,62 @@ impl LayoutSettings {
 
         issues.extend(self.gaps.validate());
 
+        issues.extend(self.centered_master.validate());
+
         issues
     }
 }
 
+impl CenteredMasterSettings {
+    pub fn validate(&self) -> Vec<String> {
+        fn check(ratio: Option<f64>, max_width: Option<f64>) -> Vec<String> {
+            let mut issues = Vec::new();
+            if let Some(ratio) = ratio {
+                if !(0.1..=0.9).contains(&ratio) {
+                    issues.push(format!(
+                        "centered_master.master_ratio must be between 0.1 and 0.9, got {ratio}"
+                    ));
+                }
+            }
+            if let Some(width) = max_width {
+                if width <= 0.0 {
+                    issues.push(format!(
+                        "centered_master.single_window_max_width must be positive, got {width}"
+                    ));
+                }
+            }
+            issues
+        }
+
+        let mut issues = check(Some(self.master_ratio), self.single_window_max_width);
+        for (uuid, overrides) in &self.per_display {
+            for issue in check(overrides.master_ratio, overrides.single_window_max_width) {
+                issues.push(format!("per_display[{uuid}] {issue}"));
+            }
+        }
+        issues
+    }
+
+    pub fn effective_for_display(&self, display_uuid: Option<&str>) -> CenteredMasterSettings {
+        let mut resolved = CenteredMasterSettings {
+            master_ratio: self.master_ratio,
+            single_window_max_width: self.single_window_max_width,
+            per_display: HashMap::default(),
+        };
+        if let Some(uuid) = display_uuid {
+            if let Some(overrides) = self.per_display.get(uuid) {
+                if let Some(ratio) = overrides.master_ratio {
+                    resolved.master_ratio = ratio;
+                }
+                if let Some(width) = overrides.single_window_max_width {
+                    resolved.single_window_max_width = Some(width);
+                }
+            }
+        }
+        resolved
+    }
+}
+
 impl StackSettings {
     pub fn validate(&self) -> Vec<String> {
         let mut issues = Vec::new();
@@ -706,6 +1253,13 @@ impl StackSettings {
             ));
         }
 
+        if self.accordion_padding < 0.0 {
+            issues.push(format!(
+                "accordion_padding must be non-negative, got {}",
+                self.accordion_padding
+            ));
+        }
+
         issues
     }
 }
@@ -814,6 +1368,10 @@ fn yes() -> bool { true }
 
 fn default_stack_offset() -> f64 { 40.0 }
 
+fn default_master_ratio() -> f64 { 0.5 }
+
+fn default_accordion_padding() -> f64 { 30.0 }
+
 fn default_stack_orientation() -> StackDefaultOrientation { StackDefaultOrientation::Perpendicular }
 
 fn default_animation_duration() -> f64 { 0.3 }
@@ -873,6 +1431,7 @@ impl Config {
                 .collect(),
             virtual_workspaces: self.virtual_workspaces.clone(),
             modifier_combinations: HashMap::default(),
+            workspace_templates: self.workspace_templates.clone(),
         };
 
         let toml_string = toml::to_string_pretty(&config_file)?;
@@ -896,6 +1455,14 @@ impl Config {
         // Validate virtual workspace settings
         issues.extend(self.virtual_workspaces.validate());
 
+        let mut template_names = crate::common::collections::HashSet::default();
+        for template in &self.workspace_templates {
+            issues.extend(template.validate());
+            if !template_names.insert(template.name.as_str()) {
+                issues.push(format!("Duplicate workspace template name '{}'", template.name));
+            }
+        }
+
         issues
     }
 
@@ -1124,12 +1691,18 @@ impl Config {
                     let Ok(hotkey) = Hotkey::from_str(&normalized_key) else {
                         bail!("Could not parse hotkey: {key}");
                     };
+                    if let WmCommand::ReactorCommand(Command::Targeted(targeted)) = &cmd
+                        && !targeted.is_supported()
+                    {
+                        bail!("The command bound to {key} cannot be run on targeted windows");
+                    }
                     keys.push((hotkey, cmd));
                 }
                 Ok(Config {
                     settings: c.settings,
                     keys,
                     virtual_workspaces: c.virtual_workspaces,
+                    workspace_templates: c.workspace_templates,
                 })
             }
             Err(e) => {
@@ -1201,6 +1774,153 @@ mod tests {
         assert!(!cfg.keys.is_empty());
     }
 
+    #[test]
+    fn test_window_criteria_from_str() {
+        let criteria: WindowCriteria = "app_name~=Slack, title~=^Docs".parse().unwrap();
+        assert_eq!(criteria.app_name.as_deref(), Some("Slack"));
+        assert_eq!(criteria.title_regex.as_deref(), Some("^Docs"));
+
+        let criteria: WindowCriteria = "app_id=com.apple.Safari,title=Inbox".parse().unwrap();
+        assert_eq!(criteria.app_id.as_deref(), Some("com.apple.Safari"));
+        assert_eq!(criteria.title_substring.as_deref(), Some("Inbox"));
+
+        // Commas inside a value stay part of it.
+        let criteria: WindowCriteria = "title~=a{1,3}, b,app_name=Mail".parse().unwrap();
+        assert_eq!(criteria.title_regex.as_deref(), Some("a{1,3}, b"));
+        assert_eq!(criteria.app_name.as_deref(), Some("Mail"));
+
+        assert!("".parse::<WindowCriteria>().is_err());
+        assert!("app_id~=Safari".parse::<WindowCriteria>().is_err());
+        assert!("colour=red".parse::<WindowCriteria>().is_err());
+    }
+
+    #[test]
+    fn test_targeted_key_bindings() {
+        let toml = r#"
+            [keys."Alt + F"]
+            target = { app_id = "com.apple.Safari", title_regex = "Docs" }
+            command = "toggle_window_floating"
+        "#;
+        let cfg = Config::parse(toml).unwrap();
+        let Some((_, WmCommand::ReactorCommand(Command::Targeted(targeted)))) = cfg.keys.first()
+        else {
+            panic!("expected a targeted command, got {:?}", cfg.keys);
+        };
+        assert_eq!(targeted.target.app_id.as_deref(), Some("com.apple.Safari"));
+        assert_eq!(targeted.target.title_regex.as_deref(), Some("Docs"));
+        assert_eq!(
+            *targeted.command,
+            Command::Layout(crate::layout_engine::LayoutCommand::ToggleWindowFloating)
+        );
+
+        let toml = r#"
+            [keys]
+            "Alt + Q" = { target = { app_id = "com.apple.Safari" }, command = "save_and_exit" }
+        "#;
+        assert!(Config::parse(toml).is_err());
+    }
+
+    #[test]
+    fn test_app_rule_placement_actions() {
+        let toml = r#"
+            [settings]
+            animate = false
+
+            [keys]
+
+            [[virtual_workspaces.app_rules]]
+            app_id = "com.apple.QuickTimePlayerX"
+            floating = true
+            frame = { x = "75%", y = 40, w = 480, h = "25%" }
+            focus = false
+            display = 1
+        "#;
+
+        let cfg = Config::parse(toml).unwrap();
+        let rule = &cfg.virtual_workspaces.app_rules[0];
+        assert!(rule.has_placement_actions());
+        assert_eq!(rule.display, Some(DisplaySelector::Index(1)));
+
+        let screen = CGRect::new(CGPoint::new(100.0, 0.0), CGSize::new(2000.0, 1200.0));
+        let frame = rule.frame.unwrap().resolve(screen);
+        assert_eq!(frame.origin, CGPoint::new(1600.0, 40.0));
+        assert_eq!(frame.size, CGSize::new(480.0, 300.0));
+
+        assert!(cfg.virtual_workspaces.validate().is_empty());
+    }
+
+    #[test]
+    fn test_workspace_templates() {
+        let toml = r#"
+            [settings]
+            animate = false
+
+            [keys]
+
+            [[workspace_templates]]
+            name = "review"
+            workspaces = ["code"]
+            layout = { kind = "horizontal", children = [
+                { app_id = "com.example.editor" },
+                { kind = "vertical_stack", weight = 0.6, children = [
+                    { app_id = "com.example.term" },
+                    { app_name = "Browser", title_substring = "Pull Request" },
+                ] },
+            ] }
+
+            [[workspace_templates]]
+            name = "broken"
+            layout = { kind = "vertical", children = [{ weight = 2.0 }] }
+        "#;
+
+        let cfg = Config::parse(toml).unwrap();
+        let TemplateNode::Container { kind, children, .. } = &cfg.workspace_templates[0].layout
+        else {
+            panic!("expected a container at the root");
+        };
+        assert_eq!(*kind, crate::layout_engine::LayoutKind::Horizontal);
+        assert!(matches!(
+            &children[0],
+            TemplateNode::Window { criteria, weight }
+                if criteria.app_id.as_deref() == Some("com.example.editor") && *weight == 1.0
+        ));
+        assert!(matches!(
+            &children[1],
+            TemplateNode::Container { weight, children, .. }
+                if *weight == 0.6 && children.len() == 2
+        ));
+
+        assert!(cfg.workspace_templates[0].validate().is_empty());
+        assert_eq!(cfg.workspace_templates[1].validate().len(), 1);
+    }
+
+    #[test]
+    fn test_move_window_to_workspace_forms() {
+        use crate::actor::wm_controller::{WmCmd, WmCommand};
+
+        let toml = r#"
+            [keys]
+            "Alt + 1" = { move_window_to_workspace = 1 }
+            "Alt + 2" = { move_window_to_workspace = { workspace = "coding", follow = true } }
+            "Alt + N" = { move_window_to_new_workspace = { follow = true } }
+        "#;
+
+        let cfg = Config::parse(toml).unwrap();
+        let commands: Vec<_> = cfg.keys.iter().map(|(_, cmd)| cmd.clone()).collect();
+        assert!(commands.contains(&WmCommand::Wm(WmCmd::MoveWindowToWorkspace(
+            MoveWindowTarget::Workspace(WorkspaceSelector::Index(1))
+        ))));
+        assert!(commands.contains(&WmCommand::Wm(WmCmd::MoveWindowToWorkspace(
+            MoveWindowTarget::Detailed {
+                workspace: WorkspaceSelector::Name("coding".into()),
+                follow: true,
+            }
+        ))));
+        assert!(commands.contains(&WmCommand::Wm(WmCmd::MoveWindowToNewWorkspace {
+            follow: true
+        })));
+    }
+
     #[test]
     fn test_levenshtein_suggests() {
         let err =
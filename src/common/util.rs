@@ -1,5 +1,20 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
 use tracing::{error, trace};
 
+/// Writes `contents` to a temporary file next to `path` and renames it over
+/// `path`, so a crash mid-write never leaves a truncated file behind.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let mut file = File::create(&tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
 pub fn parse_command(command: &str) -> Vec<String> {
     let mut parts = Vec::new();
     let mut current_part = String::new();
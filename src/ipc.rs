@@ -123,6 +123,9 @@ impl MachHandler {
             Event::QueryApplications(response) => std::mem::forget(response),
             Event::QueryLayoutState { response, .. } => std::mem::forget(response),
             Event::QueryMetrics(response) => std::mem::forget(response),
+            Event::QueryAppRules { response, .. } => std::mem::forget(response),
+            Event::QueryWindowAppRules { response, .. } => std::mem::forget(response),
+            Event::QueryLayoutSnapshots(response) => std::mem::forget(response),
             _ => {}
         }
     }
@@ -326,6 +329,63 @@ impl MachHandler {
                 }
             }
 
+            RiftRequest::TestAppRules { query } => {
+                match self.perform_query(|tx| Event::QueryAppRules { query, response: tx }) {
+                    Ok(explanation) => RiftResponse::Success {
+                        data: serde_json::to_value(explanation).unwrap(),
+                    },
+                    Err(e) => {
+                        error!("{}", e);
+                        RiftResponse::Error {
+                            error: serde_json::json!({ "message": "Failed to get app rule response", "details": format!("{}", e) }),
+                        }
+                    }
+                }
+            }
+
+            RiftRequest::ExplainAppRules { window_id } => {
+                let window_id = match crate::actor::app::WindowId::from_debug_string(&window_id) {
+                    Some(wid) => wid,
+                    None => {
+                        error!("Invalid window_id format: {}", window_id);
+                        return RiftResponse::Error {
+                            error: serde_json::json!({ "message": "Invalid window_id format", "window_id": window_id }),
+                        };
+                    }
+                };
+
+                match self
+                    .perform_query(|tx| Event::QueryWindowAppRules { window_id, response: tx })
+                {
+                    Ok(Some(explanation)) => RiftResponse::Success {
+                        data: serde_json::to_value(explanation).unwrap(),
+                    },
+                    Ok(None) => RiftResponse::Error {
+                        error: serde_json::json!({ "message": "Window not found" }),
+                    },
+                    Err(e) => {
+                        error!("{}", e);
+                        RiftResponse::Error {
+                            error: serde_json::json!({ "message": "Failed to get app rule response", "details": format!("{}", e) }),
+                        }
+                    }
+                }
+            }
+
+            RiftRequest::ListLayouts => {
+                match self.perform_query(|tx| Event::QueryLayoutSnapshots(tx)) {
+                    Ok(layouts) => RiftResponse::Success {
+                        data: serde_json::to_value(layouts).unwrap(),
+                    },
+                    Err(e) => {
+                        error!("{}", e);
+                        RiftResponse::Error {
+                            error: serde_json::json!({ "message": "Failed to get layouts response", "details": format!("{}", e) }),
+                        }
+                    }
+                }
+            }
+
             RiftRequest::GetMetrics => match self.perform_query(|tx| Event::QueryMetrics(tx)) {
                 Ok(metrics) => RiftResponse::Success { data: metrics },
                 Err(e) => {
@@ -21,6 +21,16 @@ pub enum RiftRequest {
     GetApplications,
     GetMetrics,
     GetConfig,
+    /// Dry-run app-rule matching for a described window.
+    TestAppRules {
+        query: crate::model::virtual_workspace::AppRuleQuery,
+    },
+    /// Explain which app rules match a live window.
+    ExplainAppRules {
+        window_id: String,
+    },
+    /// List the layouts saved with `layout_save`.
+    ListLayouts,
     ExecuteCommand {
         command: String,
         args: Vec<String>,
@@ -1,15 +1,24 @@
 pub mod engine;
 mod floating;
+mod focus_history;
 pub(crate) mod graph;
+mod layout_history;
+pub mod migration;
+pub mod snapshot;
 pub mod systems;
 pub mod utils;
 mod workspaces;
 
 pub use engine::{EventResponse, LayoutCommand, LayoutEngine, LayoutEvent};
 pub(crate) use floating::FloatingManager;
+pub(crate) use focus_history::FocusHistory;
 pub use graph::{Direction, LayoutKind, Orientation};
+pub(crate) use layout_history::{LayoutHistory, WorkspaceTree};
 pub(crate) use systems::LayoutId;
-pub use systems::{BspLayoutSystem, LayoutSystem, LayoutSystemKind, TraditionalLayoutSystem};
+pub use systems::{
+    BspLayoutSystem, CenteredMasterLayoutSystem, LayoutExtras, LayoutSystem, LayoutSystemKind,
+    TraditionalLayoutSystem,
+};
 pub(crate) use workspaces::WorkspaceLayouts;
 
 pub use crate::model::virtual_workspace::{
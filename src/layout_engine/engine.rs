@@ -1,21 +1,32 @@
 use std::cmp::Ordering;
 use std::fs::{self, File};
-use std::io::{Read, Write};
+use std::io::Read;
 use std::path::PathBuf;
 
 use objc2_core_foundation::{CGPoint, CGRect, CGSize};
 use serde::{Deserialize, Serialize};
 use tracing::{debug, info, warn};
 
-use super::{Direction, FloatingManager, LayoutId, LayoutSystemKind, WorkspaceLayouts};
+use super::{
+    Direction, FloatingManager, FocusHistory, LayoutHistory, LayoutId, LayoutSystemKind,
+    WorkspaceLayouts, WorkspaceTree, migration,
+};
 use crate::actor::app::{AppInfo, WindowId, pid_t};
 use crate::actor::broadcast::{BroadcastEvent, BroadcastSender};
-use crate::common::collections::{HashMap, HashSet};
-use crate::common::config::LayoutSettings;
+use crate::actor::reactor::DisplaySelector;
+use crate::common::collections::{BTreeMap, HashMap, HashSet};
+use crate::common::config::{LayoutSettings, WorkspaceSelector, WorkspaceTemplate};
+use crate::common::util::write_atomically;
 use crate::layout_engine::LayoutSystem;
+use crate::layout_engine::snapshot::{
+    LayoutSnapshot, LayoutSnapshots, Placeholder, SnapshotNode, SnapshotWindow, Slot,
+    is_placeholder, placeholder_id,
+};
 use crate::model::virtual_workspace::{
-    AppRuleAssignment, AppRuleResult, VirtualWorkspaceId, VirtualWorkspaceManager,
+    AppRuleActions, AppRuleAssignment, AppRuleQuery, AppRuleResult, VirtualWorkspaceId,
+    VirtualWorkspaceManager,
 };
+use crate::model::window_identity::WindowIdentity;
 use crate::sys::screen::SpaceId;
 
 #[derive(Debug, Clone)]
@@ -69,11 +80,69 @@ pub enum LayoutCommand {
     MoveWindowToWorkspace {
         workspace: usize,
         window_id: Option<u32>,
+        /// Switch to the target workspace afterwards, focusing the window.
+        #[serde(default)]
+        follow: bool,
+    },
+    MoveWindowToNewWorkspace {
+        window_id: Option<u32>,
+        #[serde(default)]
+        follow: bool,
+    },
+    /// Moves the selected node, with everything under it, to a workspace.
+    MoveContainerToWorkspace {
+        workspace: usize,
     },
     CreateWorkspace,
     SwitchToLastWorkspace,
+    /// Step back through the workspaces previously shown on the display.
+    WorkspaceBack,
+    /// Step forward again after `WorkspaceBack`.
+    WorkspaceForward,
+    // `workspace: None` targets the active workspace.
+    RenameWorkspace {
+        workspace: Option<usize>,
+        name: String,
+    },
+    DeleteWorkspace {
+        workspace: Option<usize>,
+    },
+    MoveWorkspaceToIndex {
+        workspace: Option<usize>,
+        index: usize,
+    },
+    SwapWorkspaces {
+        workspace: Option<usize>,
+        /// A position or name in the display's current workspace list.
+        other: WorkspaceSelector,
+    },
+    /// Adds or removes a workspace tag on the focused window.
+    ToggleTag(usize),
+    /// Shows or hides a workspace's windows alongside the active workspace.
+    ToggleView(usize),
 
     SwapWindows(crate::actor::app::WindowId, crate::actor::app::WindowId),
+
+    /// Focus the previously focused window, switching workspace if needed.
+    FocusBack,
+    /// Step through recently focused windows; the choice is committed with
+    /// `FocusMruCommit`, sent when the hotkey's modifiers are released.
+    FocusMruNext,
+    FocusMruPrev,
+    FocusMruCommit,
+
+    /// Label the focused window; a mark names one window at a time.
+    Mark(String),
+    /// Remove every mark from the focused window.
+    Unmark,
+    /// Focus the marked window, switching workspace or display if needed.
+    FocusMark(String),
+    /// Exchange the focused window with the marked one.
+    SwapWithMark(String),
+    /// Revert the last command that reshaped a tiled tree.
+    UndoLayout,
+    /// Reapply the last command reverted with `UndoLayout`.
+    RedoLayout,
 }
 
 #[non_exhaustive]
@@ -106,6 +175,41 @@ pub struct EventResponse {
     pub focus_window: Option<WindowId>,
 }
 
+/// Drops placeholders, which have no window to raise or focus, from `response`.
+fn without_placeholders(mut response: EventResponse) -> EventResponse {
+    response.raise_windows.retain(|wid| !is_placeholder(*wid));
+    response.focus_window = response.focus_window.filter(|wid| !is_placeholder(*wid));
+    response
+}
+
+/// Drops placeholders, which have no window to show, from the tabs of a stack.
+/// Returns `None` if the stack holds nothing else.
+fn group_without_placeholders(mut group: GroupContainerInfo) -> Option<GroupContainerInfo> {
+    let selected = group.window_ids.get(group.selected_index).copied();
+    let count = group.window_ids.len();
+    group.window_ids.retain(|wid| !is_placeholder(*wid));
+    let dropped = count - group.window_ids.len();
+    if dropped == 0 {
+        return Some(group);
+    }
+    if group.window_ids.is_empty() {
+        return None;
+    }
+    group.total_count = group.total_count.saturating_sub(dropped);
+    group.selected_index = selected
+        .and_then(|selected| group.window_ids.iter().position(|&wid| wid == selected))
+        .unwrap_or(0);
+    Some(group)
+}
+
+/// The focus and layout selection [`LayoutEngine::target_window`] replaced.
+#[derive(Debug, Clone, Copy)]
+pub struct TargetedSelection {
+    focused: Option<WindowId>,
+    layout: Option<LayoutId>,
+    window: Option<WindowId>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct LayoutEngine {
     tree: LayoutSystemKind,
@@ -113,6 +217,34 @@ pub struct LayoutEngine {
     floating: FloatingManager,
     #[serde(skip)]
     focused_window: Option<WindowId>,
+    #[serde(skip)]
+    focus_history: FocusHistory,
+    /// Undo and redo steps for commands that reshape tiled trees.
+    #[serde(skip)]
+    layout_history: LayoutHistory,
+    /// i3-style marks: each name labels at most one window.
+    #[serde(default)]
+    marks: BTreeMap<String, WindowId>,
+    /// Marks loaded with a saved layout. Window ids do not outlive their
+    /// session, so these only become marks again once their window has been
+    /// matched to a live one through `replace_window`. Saved along with
+    /// `marks`, so a save made before the match does not lose them.
+    #[serde(default)]
+    restored_marks: BTreeMap<String, WindowId>,
+    /// What identified each window when the layout was saved, so a restored
+    /// layout can find its windows again after they got new ids.
+    #[serde(default)]
+    window_identities: BTreeMap<WindowId, WindowIdentity>,
+    /// Leaves of loaded layout snapshots still waiting for a window to fill
+    /// them, keyed by the placeholder window holding their place. They only
+    /// wait for the session they were loaded in: [`LayoutEngine::load`] drops
+    /// them, so a saved leaf cannot claim some later window of its app.
+    #[serde(default)]
+    placeholders: BTreeMap<WindowId, Placeholder>,
+    #[serde(skip)]
+    layout_snapshots: LayoutSnapshots,
+    #[serde(skip)]
+    workspace_templates: Vec<WorkspaceTemplate>,
     virtual_workspace_manager: VirtualWorkspaceManager,
     #[serde(skip)]
     layout_settings: LayoutSettings,
@@ -122,11 +254,31 @@ pub struct LayoutEngine {
     space_display_map: HashMap<SpaceId, Option<String>>,
     #[serde(skip)]
     display_last_space: HashMap<String, SpaceId>,
+    #[serde(skip)]
+    app_bundle_ids: HashMap<pid_t, String>,
+    /// Rule actions placing new floating windows, applied on their first layout.
+    #[serde(skip)]
+    pending_rule_placements: HashMap<WindowId, AppRuleActions>,
+    /// New windows an app rule sends to another display, drained by the reactor.
+    #[serde(skip)]
+    pending_display_moves: Vec<(WindowId, DisplaySelector)>,
+    /// New windows whose rule says `focus = false`; focusing one hands focus back.
+    #[serde(skip)]
+    unfocused_rule_windows: HashSet<WindowId>,
 }
 
 impl LayoutEngine {
     pub fn set_layout_settings(&mut self, settings: &LayoutSettings) {
         self.layout_settings = settings.clone();
+        match &mut self.tree {
+            LayoutSystemKind::Traditional(s) => {
+                s.set_accordion_padding(settings.stack.accordion_padding);
+                s.set_normalization(settings.normalization);
+                s.set_autotile(settings.autotile);
+            }
+            LayoutSystemKind::CenteredMaster(s) => s.set_settings(&settings.centered_master),
+            LayoutSystemKind::Bsp(_) => {}
+        }
     }
 
     pub fn update_virtual_workspace_settings(
@@ -134,12 +286,16 @@ impl LayoutEngine {
         settings: &crate::common::config::VirtualWorkspaceSettings,
     ) {
         self.virtual_workspace_manager.update_settings(settings);
+        for space in self.workspace_layouts.spaces() {
+            self.reconcile_workspaces(space);
+        }
     }
 
     pub fn layout_mode(&self) -> &'static str {
         match &self.tree {
             LayoutSystemKind::Traditional(_) => "traditional",
             LayoutSystemKind::Bsp(_) => "bsp",
+            LayoutSystemKind::CenteredMaster(_) => "centered_master",
         }
     }
 
@@ -229,6 +385,32 @@ impl LayoutEngine {
         self.tree.resize_selection_by(layout, resize_amount);
     }
 
+    /// Moves the selection of `layout` with `step` until it lands on a window,
+    /// stepping over placeholders. If it can only reach placeholders, the
+    /// selection stays where it was.
+    fn step_selection(
+        &mut self,
+        layout: LayoutId,
+        step: impl Fn(&mut LayoutSystemKind, LayoutId) -> (Option<WindowId>, Vec<WindowId>),
+    ) -> (Option<WindowId>, Vec<WindowId>) {
+        let previous = self.tree.selected_window(layout);
+        let mut skipped = HashSet::default();
+        loop {
+            let (focus_window, raise_windows) = step(&mut self.tree, layout);
+            match focus_window {
+                Some(wid) if is_placeholder(wid) => {
+                    if !skipped.insert(wid) {
+                        if let Some(previous) = previous {
+                            let _ = self.tree.select_window(layout, previous);
+                        }
+                        return (None, Vec::new());
+                    }
+                }
+                _ => return (focus_window, raise_windows),
+            }
+        }
+    }
+
     fn apply_focus_response(&mut self, space: SpaceId, layout: LayoutId, response: &EventResponse) {
         if let Some(wid) = response.focus_window {
             self.focused_window = Some(wid);
@@ -488,8 +670,16 @@ impl LayoutEngine {
         if self.focused_window == Some(wid) {
             self.focused_window = None;
         }
+        self.focus_history.remove(wid);
+        if !preserve_floating {
+            self.marks.retain(|_, marked| *marked != wid);
+            self.restored_marks.retain(|_, marked| *marked != wid);
+        }
+        self.pending_rule_placements.remove(&wid);
+        self.unfocused_rule_windows.remove(&wid);
 
         if let Some(space) = affected_space {
+            self.reconcile_workspaces(space);
             self.broadcast_windows_changed(space);
         }
 
@@ -527,12 +717,26 @@ impl LayoutEngine {
     }
 
     pub fn update_space_display(&mut self, space: SpaceId, display_uuid: Option<String>) {
+        let removed =
+            self.virtual_workspace_manager.set_space_display(space, display_uuid.clone());
         if let Some(uuid) = display_uuid {
             self.space_display_map.insert(space, Some(uuid.clone()));
             self.display_last_space.insert(uuid, space);
         } else {
             self.space_display_map.remove(&space);
         }
+        if !removed.is_empty() || self.virtual_workspace_manager.has_display_pins() {
+            self.sync_workspace_layouts(space, removed);
+        }
+    }
+
+    pub fn set_main_display(&mut self, display_uuid: Option<String>) {
+        if self.virtual_workspace_manager.set_main_display(display_uuid) {
+            let spaces: Vec<SpaceId> = self.space_display_map.keys().copied().collect();
+            for space in spaces {
+                self.reconcile_workspaces(space);
+            }
+        }
     }
 
     pub fn last_space_for_display_uuid(&self, display_uuid: &str) -> Option<SpaceId> {
@@ -586,6 +790,20 @@ impl LayoutEngine {
         self.space_display_map.retain(|_, uuid_opt| {
             uuid_opt.as_ref().map(|uuid| active.contains(uuid.as_str())).unwrap_or(false)
         });
+
+        if self.virtual_workspace_manager.is_global() {
+            // Keep the workspaces of disconnected displays on a live one, preferring
+            // the main display.
+            let main_space = self.virtual_workspace_manager.main_display_space();
+            let mut live_spaces: Vec<SpaceId> = self.space_display_map.keys().copied().collect();
+            live_spaces.sort_by_key(|space| (Some(*space) != main_space, *space));
+            if self.virtual_workspace_manager.adopt_orphaned_workspaces(&live_spaces) {
+                self.rehome_workspace_layouts();
+                for space in live_spaces {
+                    self.broadcast_workspace_changed(space);
+                }
+            }
+        }
     }
 
     pub fn new(
@@ -597,12 +815,21 @@ impl LayoutEngine {
             VirtualWorkspaceManager::new_with_config(virtual_workspace_config);
 
         let tree = match layout_settings.mode {
-            crate::common::config::LayoutMode::Traditional => LayoutSystemKind::Traditional(
-                crate::layout_engine::TraditionalLayoutSystem::default(),
-            ),
+            crate::common::config::LayoutMode::Traditional => {
+                let mut system = crate::layout_engine::TraditionalLayoutSystem::default();
+                system.set_accordion_padding(layout_settings.stack.accordion_padding);
+                system.set_normalization(layout_settings.normalization);
+                system.set_autotile(layout_settings.autotile);
+                LayoutSystemKind::Traditional(system)
+            }
             crate::common::config::LayoutMode::Bsp => {
                 LayoutSystemKind::Bsp(crate::layout_engine::BspLayoutSystem::default())
             }
+            crate::common::config::LayoutMode::CenteredMaster => {
+                let mut system = crate::layout_engine::CenteredMasterLayoutSystem::default();
+                system.set_settings(&layout_settings.centered_master);
+                LayoutSystemKind::CenteredMaster(system)
+            }
         };
 
         LayoutEngine {
@@ -610,11 +837,23 @@ impl LayoutEngine {
             workspace_layouts: WorkspaceLayouts::default(),
             floating: FloatingManager::new(),
             focused_window: None,
+            focus_history: FocusHistory::default(),
+            layout_history: LayoutHistory::default(),
+            marks: BTreeMap::new(),
+            restored_marks: BTreeMap::new(),
+            window_identities: BTreeMap::new(),
+            placeholders: BTreeMap::new(),
+            layout_snapshots: LayoutSnapshots::default(),
+            workspace_templates: Vec::new(),
             virtual_workspace_manager,
             layout_settings: layout_settings.clone(),
             broadcast_tx,
             space_display_map: HashMap::default(),
             display_last_space: HashMap::default(),
+            app_bundle_ids: HashMap::default(),
+            pending_rule_placements: HashMap::default(),
+            pending_display_moves: Vec::new(),
+            unfocused_rule_windows: HashSet::default(),
         }
     }
 
@@ -652,19 +891,32 @@ impl LayoutEngine {
     }
 
     pub fn handle_event(&mut self, event: LayoutEvent) -> EventResponse {
+        without_placeholders(self.apply_event(event))
+    }
+
+    fn apply_event(&mut self, event: LayoutEvent) -> EventResponse {
         debug!(?event);
         match event {
             LayoutEvent::SpaceExposed(space, size) => {
                 self.debug_tree(space);
+                if self.virtual_workspace_manager.is_global() {
+                    self.rehome_workspace_layouts();
+                }
 
                 let workspaces =
                     self.virtual_workspace_manager_mut().list_workspaces(space).to_vec();
+                let created: Vec<_> = workspaces
+                    .iter()
+                    .map(|(id, _)| *id)
+                    .filter(|id| self.workspace_layouts.layouts(space, *id).is_empty())
+                    .collect();
                 self.workspace_layouts.ensure_active_for_space(
                     space,
                     size,
                     workspaces.into_iter().map(|(id, _)| id),
                     &mut self.tree,
                 );
+                self.apply_templates_to_new_workspaces(space, &created);
             }
             LayoutEvent::WindowsOnScreenUpdated(space, pid, windows_with_titles, app_info) => {
                 self.debug_tree(space);
@@ -679,6 +931,10 @@ impl LayoutEngine {
                     Some(info) => (info.bundle_id.as_deref(), info.localized_name.as_deref()),
                     None => (None, None),
                 };
+                if let Some(bundle_id) = app_bundle_id {
+                    self.app_bundle_ids.insert(pid, bundle_id.to_string());
+                }
+                let mut tiled_rule_actions = Vec::new();
 
                 for (wid, title_opt, ax_role_opt, ax_subrole_opt) in windows_with_titles {
                     let title_ref = title_opt.as_deref();
@@ -705,6 +961,7 @@ impl LayoutEngine {
                                     workspace_id: ws,
                                     floating: was_floating,
                                     prev_rule_decision: false,
+                                    actions: None,
                                 }),
                                 Err(_) => {
                                     warn!(
@@ -721,6 +978,7 @@ impl LayoutEngine {
                         workspace_id: assigned_workspace,
                         floating: rule_says_float,
                         prev_rule_decision,
+                        actions,
                     } = match assignment {
                         Some(assign) => assign,
                         None => continue,
@@ -735,6 +993,72 @@ impl LayoutEngine {
                         self.floating.remove_floating(wid);
                     }
 
+                    if let Some(placement) =
+                        self.virtual_workspace_manager.take_restored_placement(wid)
+                        && placement.floating
+                    {
+                        self.floating.add_floating(wid);
+                        self.floating.add_active(space, pid, wid);
+                        if let Some(frame) = placement.frame {
+                            let frame_space = self
+                                .virtual_workspace_manager
+                                .workspace_space(assigned_workspace)
+                                .unwrap_or(space);
+                            self.virtual_workspace_manager.store_floating_position(
+                                frame_space,
+                                assigned_workspace,
+                                wid,
+                                frame,
+                            );
+                        }
+                    }
+
+                    if let Some(actions) = actions {
+                        self.queue_rule_actions(wid, assigned_workspace, &actions);
+                        if !self.floating.is_floating(wid) {
+                            tiled_rule_actions.push((wid, actions));
+                        }
+                    }
+
+                    if !self.floating.is_floating(wid) {
+                        let window = AppRuleQuery {
+                            app_id: app_bundle_id.map(str::to_string),
+                            app_name: app_name.map(str::to_string),
+                            title: title_opt.clone(),
+                            ax_role: ax_role_opt.clone(),
+                            ax_subrole: ax_subrole_opt.clone(),
+                        };
+                        self.fill_workspace_placeholder(wid, assigned_workspace, &window);
+                    }
+
+                    // App rules can route a window to a workspace pinned to another display.
+                    if let Some(target_space) = self
+                        .virtual_workspace_manager
+                        .workspace_space(assigned_workspace)
+                        .filter(|target| *target != space)
+                    {
+                        if self.floating.is_floating(wid) {
+                            self.floating.remove_active(space, pid, wid);
+                            if self.virtual_workspace_manager.active_workspace(target_space)
+                                == Some(assigned_workspace)
+                            {
+                                self.floating.add_active(target_space, pid, wid);
+                            }
+                        } else if let Some(layout) =
+                            self.workspace_layouts.active(target_space, assigned_workspace)
+                            && !self.tree.contains_window(layout, wid)
+                        {
+                            self.tree.remove_window(wid);
+                            self.tree.add_window_after_selection(layout, wid);
+                        }
+                        self.virtual_workspace_manager_mut().set_last_rule_decision(
+                            target_space,
+                            wid,
+                            rule_says_float,
+                        );
+                        continue;
+                    }
+
                     if !self.floating.is_floating(wid) {
                         windows_by_workspace.entry(assigned_workspace).or_default().push(wid);
                     }
@@ -753,7 +1077,7 @@ impl LayoutEngine {
 
                 for (ws_id, layout) in self.workspace_layouts.active_layouts_for_space(space) {
                     let mut desired = tiled_by_workspace.get(&ws_id).cloned().unwrap_or_default();
-                    for wid in self.virtual_workspace_manager.workspace_windows(space, ws_id) {
+                    for wid in self.virtual_workspace_manager.layout_windows(space, ws_id) {
                         if wid.pid != pid
                             || self.floating.is_floating(wid)
                             || desired.contains(&wid)
@@ -772,6 +1096,11 @@ impl LayoutEngine {
                     self.tree.set_windows_for_app(layout, pid, desired);
                 }
 
+                for (wid, actions) in tiled_rule_actions {
+                    self.apply_tiled_rule_actions(wid, &actions);
+                }
+
+                self.reconcile_workspaces(space);
                 self.broadcast_windows_changed(space);
 
                 self.rebalance_all_layouts();
@@ -782,6 +1111,16 @@ impl LayoutEngine {
 
                 self.virtual_workspace_manager.remove_windows_for_app(pid);
                 self.virtual_workspace_manager.remove_app_floating_positions(pid);
+                self.focus_history.remove_app(pid);
+                self.marks.retain(|_, marked| marked.pid != pid);
+                self.restored_marks.retain(|_, marked| marked.pid != pid);
+                self.app_bundle_ids.remove(&pid);
+                self.pending_rule_placements.retain(|wid, _| wid.pid != pid);
+                self.unfocused_rule_windows.retain(|wid| wid.pid != pid);
+
+                for space in self.workspace_layouts.spaces() {
+                    self.reconcile_workspaces(space);
+                }
             }
             LayoutEvent::WindowAdded(space, wid) => {
                 self.debug_tree(space);
@@ -818,6 +1157,7 @@ impl LayoutEngine {
                     );
                 }
 
+                self.reconcile_workspaces(space);
                 self.broadcast_windows_changed(space);
             }
             LayoutEvent::WindowRemoved(wid) => {
@@ -827,7 +1167,18 @@ impl LayoutEngine {
                 self.remove_window_internal(wid, true);
             }
             LayoutEvent::WindowFocused(space, wid) => {
+                // Only the first focus after a window appears can be the one
+                // its app grabbed for it.
+                let refuse_focus = self.unfocused_rule_windows.remove(&wid);
+                self.unfocused_rule_windows.clear();
+                if refuse_focus && let Some(previous) = self.focused_window.filter(|&w| w != wid) {
+                    return EventResponse {
+                        raise_windows: Vec::new(),
+                        focus_window: Some(previous),
+                    };
+                }
                 self.focused_window = Some(wid);
+                self.focus_history.record(wid);
                 if self.floating.is_floating(wid) {
                     self.floating.set_last_focus(Some(wid));
                 } else {
@@ -880,6 +1231,69 @@ impl LayoutEngine {
         visible_spaces: &[SpaceId],
         visible_space_centers: &HashMap<SpaceId, CGPoint>,
         command: LayoutCommand,
+    ) -> EventResponse {
+        self.run_command(space, None, visible_spaces, visible_space_centers, command)
+    }
+
+    /// Runs `command` on `window_id` in the layout of its own workspace, which
+    /// need not be shown or be on the display the command came from. The
+    /// window must have been made the target with [`Self::target_window`].
+    pub fn handle_command_for_window(
+        &mut self,
+        window_id: WindowId,
+        visible_spaces: &[SpaceId],
+        visible_space_centers: &HashMap<SpaceId, CGPoint>,
+        command: LayoutCommand,
+    ) -> EventResponse {
+        let Some((space, workspace_id)) = self.window_location(window_id) else {
+            return EventResponse::default();
+        };
+        self.run_command(
+            Some(space),
+            Some(workspace_id),
+            visible_spaces,
+            visible_space_centers,
+            command,
+        )
+    }
+
+    /// Runs `command` on the layout of `workspace`, or of the active workspace
+    /// on `space` if `None`, recording it for undo.
+    fn run_command(
+        &mut self,
+        space: Option<SpaceId>,
+        workspace: Option<VirtualWorkspaceId>,
+        visible_spaces: &[SpaceId],
+        visible_space_centers: &HashMap<SpaceId, CGPoint>,
+        command: LayoutCommand,
+    ) -> EventResponse {
+        match command {
+            LayoutCommand::UndoLayout => return self.step_layout_history(true),
+            LayoutCommand::RedoLayout => return self.step_layout_history(false),
+            _ => {}
+        }
+        let workspaces = space
+            .map(|space| self.undoable_workspaces(space, workspace, &command))
+            .unwrap_or_default();
+        let before = self.workspace_trees(&workspaces);
+        let response = self.apply_command(
+            space,
+            workspace,
+            visible_spaces,
+            visible_space_centers,
+            command,
+        );
+        self.record_layout_change(before);
+        without_placeholders(response)
+    }
+
+    fn apply_command(
+        &mut self,
+        space: Option<SpaceId>,
+        workspace: Option<VirtualWorkspaceId>,
+        visible_spaces: &[SpaceId],
+        visible_space_centers: &HashMap<SpaceId, CGPoint>,
+        command: LayoutCommand,
     ) -> EventResponse {
         if let Some(space) = space {
             let layout = self.layout(space);
@@ -897,6 +1311,10 @@ impl LayoutEngine {
             let Some(wid) = self.focused_window else {
                 return EventResponse::default();
             };
+            // Floating bookkeeping belongs to the window's own space, which a
+            // targeted command need not have been run from.
+            let location = self.window_location(wid);
+            let space = location.map(|(space, _)| space).or(space);
             if is_floating {
                 if let Some(space) = space {
                     let assigned_workspace = self
@@ -921,7 +1339,12 @@ impl LayoutEngine {
                 self.floating.remove_floating(wid);
                 self.floating.set_last_focus(None);
             } else {
-                if let Some(space) = space {
+                if let Some(space) = space
+                    && location.is_none_or(|(_, workspace_id)| {
+                        self.virtual_workspace_manager.active_workspace(space)
+                            == Some(workspace_id)
+                    })
+                {
                     self.floating.add_active(space, wid.pid, wid);
                 }
                 self.tree.remove_window(wid);
@@ -935,7 +1358,8 @@ impl LayoutEngine {
         let Some(space) = space else {
             return EventResponse::default();
         };
-        let workspace_id = match self.virtual_workspace_manager.active_workspace(space) {
+        let active = self.virtual_workspace_manager.active_workspace(space);
+        let workspace_id = match workspace.or(active) {
             Some(id) => id,
             None => {
                 warn!("No active virtual workspace for space {:?}", space);
@@ -979,9 +1403,13 @@ impl LayoutEngine {
         match command {
             LayoutCommand::ToggleWindowFloating => unreachable!(),
             LayoutCommand::ToggleFocusFloating => unreachable!(),
+            LayoutCommand::UndoLayout | LayoutCommand::RedoLayout => unreachable!(),
 
             LayoutCommand::SwapWindows(a, b) => {
-                let layout = self.layout(space);
+                // A placeholder's leaf is kept for the window it waits for.
+                if is_placeholder(a) || is_placeholder(b) {
+                    return EventResponse::default();
+                }
                 let _ = self.tree.swap_windows(layout, a, b);
 
                 EventResponse::default()
@@ -999,8 +1427,8 @@ impl LayoutEngine {
                     );
                 }
                 
-                let layout = self.layout(space);
-                let (focus_window, raise_windows) = self.tree.next_sibling_window(layout);
+                let (focus_window, raise_windows) =
+                    self.step_selection(layout, |tree, layout| tree.next_sibling_window(layout));
                 
                 if let Some(wid) = focus_window {
                     EventResponse {
@@ -1023,8 +1451,8 @@ impl LayoutEngine {
                     );
                 }
                 
-                let layout = self.layout(space);
-                let (focus_window, raise_windows) = self.tree.prev_sibling_window(layout);
+                let (focus_window, raise_windows) =
+                    self.step_selection(layout, |tree, layout| tree.prev_sibling_window(layout));
                 
                 if let Some(wid) = focus_window {
                     EventResponse {
@@ -1052,8 +1480,9 @@ impl LayoutEngine {
                 }
                 
                 // Use level-restricted focus movement for tiled windows
-                let layout = self.layout(space);
-                let (focus_window, raise_windows) = self.tree.move_focus_level_restricted(layout, direction);
+                let (focus_window, raise_windows) = self.step_selection(layout, |tree, layout| {
+                    tree.move_focus_level_restricted(layout, direction)
+                });
                 
                 if let Some(wid) = focus_window {
                     EventResponse {
@@ -1155,6 +1584,10 @@ impl LayoutEngine {
                 EventResponse::default()
             }
             LayoutCommand::MoveNode(direction) => {
+                let onto = self.tree.window_in_direction(layout, direction);
+                if self.tree.selected_window(layout).into_iter().chain(onto).any(is_placeholder) {
+                    return EventResponse::default();
+                }
                 self.workspace_layouts.mark_last_saved(space, workspace_id, layout);
                 // Use level-restricted movement - only move within current level
                 self.tree.move_selection_level_restricted(layout, direction);
@@ -1187,8 +1620,36 @@ impl LayoutEngine {
             | LayoutCommand::PrevWorkspace(_)
             | LayoutCommand::SwitchToWorkspace(_)
             | LayoutCommand::MoveWindowToWorkspace { .. }
+            | LayoutCommand::MoveWindowToNewWorkspace { .. }
+            | LayoutCommand::MoveContainerToWorkspace { .. }
             | LayoutCommand::CreateWorkspace
-            | LayoutCommand::SwitchToLastWorkspace => EventResponse::default(),
+            | LayoutCommand::SwitchToLastWorkspace
+            | LayoutCommand::WorkspaceBack
+            | LayoutCommand::WorkspaceForward
+            | LayoutCommand::RenameWorkspace { .. }
+            | LayoutCommand::DeleteWorkspace { .. }
+            | LayoutCommand::MoveWorkspaceToIndex { .. }
+            | LayoutCommand::SwapWorkspaces { .. }
+            | LayoutCommand::ToggleTag(_)
+            | LayoutCommand::ToggleView(_)
+            | LayoutCommand::FocusBack
+            | LayoutCommand::FocusMruNext
+            | LayoutCommand::FocusMruPrev
+            | LayoutCommand::FocusMruCommit
+            | LayoutCommand::FocusMark(_)
+            | LayoutCommand::SwapWithMark(_) => EventResponse::default(),
+            LayoutCommand::Mark(mark) => {
+                if let Some(wid) = self.focused_window {
+                    self.marks.insert(mark, wid);
+                }
+                EventResponse::default()
+            }
+            LayoutCommand::Unmark => {
+                if let Some(wid) = self.focused_window {
+                    self.marks.retain(|_, marked| *marked != wid);
+                }
+                EventResponse::default()
+            }
             LayoutCommand::JoinWindow(direction) => {
                 self.workspace_layouts.mark_last_saved(space, workspace_id, layout);
                 // Use level-restricted joining - only join with siblings at current level
@@ -1199,6 +1660,18 @@ impl LayoutEngine {
                 self.workspace_layouts.mark_last_saved(space, workspace_id, layout);
                 let default_orientation: crate::common::config::StackDefaultOrientation =
                     self.layout_settings.stack.default_orientation;
+
+                // Traditional layouts cycle tiled -> stacked -> accordion -> tiled.
+                if let LayoutSystemKind::Traditional(s) = &mut self.tree {
+                    let accordion_windows = s.convert_stack_of_selection_to_accordion(layout);
+                    if !accordion_windows.is_empty() {
+                        return EventResponse {
+                            raise_windows: accordion_windows,
+                            focus_window: None,
+                        };
+                    }
+                }
+
                 let unstacked_windows =
                     self.tree.unstack_parent_of_selection(layout, default_orientation);
 
@@ -1260,6 +1733,10 @@ impl LayoutEngine {
                         s.toggle_tile_orientation(layout);
                         EventResponse::default()
                     }
+                    LayoutSystemKind::CenteredMaster(s) => {
+                        s.toggle_tile_orientation(layout);
+                        EventResponse::default()
+                    }
                 };
 
                 resp
@@ -1270,8 +1747,7 @@ impl LayoutEngine {
                 }
 
                 self.workspace_layouts.mark_last_saved(space, workspace_id, layout);
-                let resize_amount = 0.05;
-                self.tree.resize_selection_by(layout, resize_amount);
+                self.resize_tiled_selection(space, layout, 0.05);
                 EventResponse::default()
             }
             LayoutCommand::ResizeWindowShrink => {
@@ -1280,8 +1756,7 @@ impl LayoutEngine {
                 }
 
                 self.workspace_layouts.mark_last_saved(space, workspace_id, layout);
-                let resize_amount = -0.05;
-                self.tree.resize_selection_by(layout, resize_amount);
+                self.resize_tiled_selection(space, layout, -0.05);
                 EventResponse::default()
             }
             LayoutCommand::ResizeWindowBy { amount } => {
@@ -1290,7 +1765,7 @@ impl LayoutEngine {
                 }
 
                 self.workspace_layouts.mark_last_saved(space, workspace_id, layout);
-                self.tree.resize_selection_by(layout, amount);
+                self.resize_tiled_selection(space, layout, amount);
                 EventResponse::default()
             }
         }
@@ -1306,15 +1781,76 @@ impl LayoutEngine {
         stack_line_vert: crate::common::config::VerticalPlacement,
     ) -> Vec<(WindowId, CGRect)> {
         let layout = self.layout(space);
-        self.tree.calculate_layout(
+        let frames = self.calculate_tiled_layout(
+            space,
             layout,
             screen,
-            self.layout_settings.stack.stack_offset,
             gaps,
             stack_line_thickness,
             stack_line_horiz,
             stack_line_vert,
-        )
+        );
+        self.record_tiled_frames(&frames);
+        frames
+    }
+
+    /// Lets the layout system see the frames it last produced (used for autotiling).
+    fn record_tiled_frames(&mut self, frames: &[(WindowId, CGRect)]) {
+        if let LayoutSystemKind::Traditional(s) = &mut self.tree {
+            s.set_last_frames(frames);
+        }
+    }
+
+    /// Resizes the selection of a layout, resolving display-specific layout
+    /// settings the same way [`Self::calculate_tiled_layout`] does.
+    fn resize_tiled_selection(&mut self, space: SpaceId, layout: LayoutId, amount: f64) {
+        match &mut self.tree {
+            LayoutSystemKind::CenteredMaster(s) => {
+                let display_uuid = self.space_display_map.get(&space).cloned().flatten();
+                let settings = self
+                    .layout_settings
+                    .centered_master
+                    .effective_for_display(display_uuid.as_deref());
+                s.resize_selection_with_settings(layout, amount, &settings);
+            }
+            tree => tree.resize_selection_by(layout, amount),
+        }
+    }
+
+    /// Calculates tiled frames for a layout, resolving display-specific
+    /// layout settings for the display currently showing `space`. Callers that
+    /// apply the frames record them with [`Self::record_tiled_frames`].
+    fn calculate_tiled_layout(
+        &self,
+        space: SpaceId,
+        layout: LayoutId,
+        screen: CGRect,
+        gaps: &crate::common::config::GapSettings,
+        stack_line_thickness: f64,
+        stack_line_horiz: crate::common::config::HorizontalPlacement,
+        stack_line_vert: crate::common::config::VerticalPlacement,
+    ) -> Vec<(WindowId, CGRect)> {
+        let frames = match &self.tree {
+            LayoutSystemKind::CenteredMaster(s) => {
+                let display_uuid = self.space_display_map.get(&space).cloned().flatten();
+                let settings = self
+                    .layout_settings
+                    .centered_master
+                    .effective_for_display(display_uuid.as_deref());
+                s.calculate_layout_with_settings(layout, screen, gaps, &settings)
+            }
+            tree => tree.calculate_layout(
+                layout,
+                screen,
+                self.layout_settings.stack.stack_offset,
+                gaps,
+                stack_line_thickness,
+                stack_line_horiz,
+                stack_line_vert,
+            ),
+        };
+        // Placeholders have their place in the tree but no window to move.
+        frames.into_iter().filter(|(wid, _)| !is_placeholder(*wid)).collect()
     }
 
     pub fn calculate_layout_with_virtual_workspaces<F>(
@@ -1386,20 +1922,37 @@ impl LayoutEngine {
 
         if let Some(active_workspace_id) = self.virtual_workspace_manager.active_workspace(space) {
             if let Some(layout) = self.workspace_layouts.active(space, active_workspace_id) {
-                let tiled_positions = self.tree.calculate_layout(
+                if self.virtual_workspace_manager.has_tags_or_views() {
+                    self.sync_layout_windows(space, active_workspace_id, layout);
+                }
+                let tiled_positions = self.calculate_tiled_layout(
+                    space,
                     layout,
                     screen,
-                    self.layout_settings.stack.stack_offset,
                     gaps,
                     stack_line_thickness,
                     stack_line_horiz,
                     stack_line_vert,
                 );
+                self.record_tiled_frames(&tiled_positions);
                 for (wid, rect) in tiled_positions {
                     positions.insert(wid, rect);
                 }
             }
 
+            for wid in self.active_floating_windows_in_workspace(space) {
+                if let Some(actions) = self.pending_rule_placements.remove(&wid)
+                    && let Some(frame) = actions.floating_frame(screen, window_size(wid))
+                {
+                    self.virtual_workspace_manager.store_floating_position(
+                        space,
+                        active_workspace_id,
+                        wid,
+                        frame,
+                    );
+                }
+            }
+
             let floating_positions = self
                 .virtual_workspace_manager
                 .get_workspace_floating_positions(space, active_workspace_id);
@@ -1486,7 +2039,7 @@ impl LayoutEngine {
         stack_line_vert: crate::common::config::VerticalPlacement,
     ) -> Vec<GroupContainerInfo> {
         let layout_id = self.layout(space);
-        match &self.tree {
+        let groups = match &self.tree {
             LayoutSystemKind::Traditional(s) => s.collect_group_containers_in_selection_path(
                 layout_id,
                 screen,
@@ -1497,7 +2050,8 @@ impl LayoutEngine {
                 stack_line_vert,
             ),
             _ => Vec::new(),
-        }
+        };
+        groups.into_iter().filter_map(group_without_placeholders).collect()
     }
 
     /// Get the frame and child count of the currently selected node
@@ -1563,10 +2117,10 @@ impl LayoutEngine {
         let mut positions = HashMap::default();
 
         if let Some(layout) = self.workspace_layouts.active(space, workspace_id) {
-            let tiled_positions = self.tree.calculate_layout(
+            let tiled_positions = self.calculate_tiled_layout(
+                space,
                 layout,
                 screen,
-                self.layout_settings.stack.stack_offset,
                 gaps,
                 stack_line_thickness,
                 stack_line_horiz,
@@ -1589,14 +2143,91 @@ impl LayoutEngine {
         positions.into_iter().collect()
     }
 
-    fn get_app_bundle_id_for_window(&self, _window_id: WindowId) -> Option<String> {
-        // The bundle ID is stored in the app info, which we can access via the PID
-        // Note: This would need to be available from the reactor state, but since
-        // we're in the layout engine, we don't have direct access to that.
-        // For now, we'll return None, but this could be improved by passing
-        // app information through the layout calculation or storing it separately.
+    /// Records where a window lives in the placement memory. Returns whether
+    /// the memory changed.
+    pub fn remember_window_placement(
+        &mut self,
+        wid: WindowId,
+        bundle_id: &str,
+        title: &str,
+        frame: CGRect,
+    ) -> bool {
+        let floating = self.floating.is_floating(wid);
+        self.virtual_workspace_manager.remember_placement(wid, bundle_id, title, floating, frame)
+    }
 
-        None
+    fn get_app_bundle_id_for_window(&self, window_id: WindowId) -> Option<String> {
+        self.app_bundle_ids.get(&window_id.pid).cloned()
+    }
+
+    /// Queues the rule actions for a new window that take effect outside the
+    /// tree: its floating frame, display and focus.
+    fn queue_rule_actions(
+        &mut self,
+        wid: WindowId,
+        workspace_id: VirtualWorkspaceId,
+        actions: &AppRuleActions,
+    ) {
+        let placed_before = self
+            .virtual_workspace_manager
+            .workspace_space(workspace_id)
+            .and_then(|space| {
+                self.virtual_workspace_manager.get_floating_position(space, workspace_id, wid)
+            })
+            .is_some();
+        if self.floating.is_floating(wid)
+            && !placed_before
+            && (actions.frame.is_some() || actions.center || actions.fullscreen)
+        {
+            self.pending_rule_placements.insert(wid, actions.clone());
+        }
+        if let Some(display) = &actions.display {
+            self.pending_display_moves.push((wid, display.clone()));
+        }
+        if !actions.focus {
+            self.unfocused_rule_windows.insert(wid);
+        }
+    }
+
+    /// Applies a new tiled window's `stack_with` and `fullscreen` rule actions.
+    fn apply_tiled_rule_actions(&mut self, wid: WindowId, actions: &AppRuleActions) {
+        let Some((space, workspace_id)) = self.window_location(wid) else {
+            return;
+        };
+        let Some(layout) = self.workspace_layouts.active(space, workspace_id) else {
+            return;
+        };
+        if !self.tree.contains_window(layout, wid) {
+            return;
+        }
+
+        let stack_target = actions.stack_with.as_ref().and_then(|app_id| {
+            let candidates = self.virtual_workspace_manager.layout_windows(space, workspace_id);
+            candidates.into_iter().find(|&other| {
+                other != wid
+                    && !self.floating.is_floating(other)
+                    && self.app_bundle_ids.get(&other.pid) == Some(app_id)
+            })
+        });
+        if let Some(target) = stack_target {
+            let orientation = self.layout_settings.stack.default_orientation;
+            self.tree.remove_window(wid);
+            let _ = self.tree.select_window(layout, target);
+            if !self.tree.parent_of_selection_is_stacked(layout) {
+                let _ = self.tree.group_selection(layout, true, orientation);
+                let _ = self.tree.select_window(layout, target);
+            }
+            self.tree.add_window_after_selection(layout, wid);
+        }
+
+        if actions.fullscreen && self.tree.select_window(layout, wid) {
+            let _ = self.tree.toggle_fullscreen_of_selection(layout);
+        }
+    }
+
+    /// Takes the display moves requested by app rules for new windows.
+    pub fn take_rule_display_moves(&mut self) -> Vec<(WindowId, DisplaySelector)> {
+        std::mem::take(&mut self.pending_display_moves)
     }
 
     fn layout(&mut self, space: SpaceId) -> LayoutId {
@@ -1650,51 +2281,550 @@ impl LayoutEngine {
     pub fn load(path: PathBuf) -> anyhow::Result<Self> {
         let mut buf = String::new();
         File::open(path)?.read_to_string(&mut buf)?;
-        Ok(ron::from_str(&buf)?)
+        let (version, body) = migration::split_header(&buf);
+        let mut engine = Self::deserialize_versioned(version, body.to_string())?;
+        let marks = std::mem::take(&mut engine.marks);
+        engine.restored_marks.extend(marks);
+        for placeholder in std::mem::take(&mut engine.placeholders).into_keys() {
+            engine.tree.remove_window(placeholder);
+        }
+        Ok(engine)
+    }
+
+    /// Reads state of format `version`, migrating it to the current format.
+    pub fn deserialize_versioned(version: u32, body: String) -> anyhow::Result<Self> {
+        Ok(ron::from_str(&migration::migrate(version, body)?)?)
     }
 
     pub fn save(&self, path: PathBuf) -> std::io::Result<()> {
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
         }
-        File::create(path)?.write_all(self.serialize_to_string().as_bytes())?;
-        Ok(())
+        let contents = format!("{}\n{}", migration::header(), self.serialize_to_string());
+        write_atomically(&path, contents.as_bytes())
     }
 
     pub fn serialize_to_string(&self) -> String { ron::ser::to_string(&self).unwrap() }
 
-    #[cfg(test)]
-    pub(crate) fn selected_window(&mut self, space: SpaceId) -> Option<WindowId> {
-        let layout = self.layout(space);
-        self.tree.selected_window(layout)
+    /// Sets the window identities saved along with the layout.
+    pub fn set_window_identities(&mut self, identities: BTreeMap<WindowId, WindowIdentity>) {
+        self.window_identities = identities;
     }
 
-    pub fn handle_virtual_workspace_command(
-        &mut self,
-        space: SpaceId,
-        command: &LayoutCommand,
-    ) -> EventResponse {
-        match command {
-            LayoutCommand::NextWorkspace(skip_empty) => {
-                if let Some(current_workspace) =
-                    self.virtual_workspace_manager.active_workspace(space)
-                {
-                    if let Some(next_workspace) = self.virtual_workspace_manager.next_workspace(
-                        space,
-                        current_workspace,
-                        *skip_empty,
-                    ) {
-                        self.virtual_workspace_manager.set_active_workspace(space, next_workspace);
+    /// Takes the window identities the layout was saved with.
+    pub fn take_window_identities(&mut self) -> BTreeMap<WindowId, WindowIdentity> {
+        std::mem::take(&mut self.window_identities)
+    }
 
-                        self.update_active_floating_windows(space);
+    /// Gives the live window `new` everything the layout knows about the
+    /// restored window `old`: its place in the trees, workspace, floating state
+    /// and marks.
+    pub fn replace_window(&mut self, old: WindowId, new: WindowId) {
+        let restored: Vec<String> = self
+            .restored_marks
+            .iter()
+            .filter(|&(_, &marked)| marked == old)
+            .map(|(mark, _)| mark.clone())
+            .collect();
+        for mark in restored {
+            self.restored_marks.remove(&mark);
+            self.marks.entry(mark).or_insert(new);
+        }
+        if old == new {
+            return;
+        }
+        self.tree.replace_window(old, new);
+        self.layout_history.replace_window(old, new);
+        self.floating.replace_window(old, new);
+        self.virtual_workspace_manager.replace_window(old, new);
+        for marked in self.marks.values_mut() {
+            if *marked == old {
+                *marked = new;
+            }
+        }
+        self.focus_history.remove(old);
+        if self.focused_window == Some(old) {
+            self.focused_window = Some(new);
+        }
+    }
 
-                        self.broadcast_workspace_changed(space);
-                        self.broadcast_windows_changed(space);
+    pub fn set_layout_snapshots(&mut self, snapshots: LayoutSnapshots) {
+        self.layout_snapshots = snapshots;
+    }
 
-                        return self.refocus_workspace(space, next_workspace);
-                    }
-                }
-                EventResponse::default()
+    pub fn layout_snapshots(&self) -> &LayoutSnapshots { &self.layout_snapshots }
+
+    pub fn set_workspace_templates(&mut self, templates: Vec<WorkspaceTemplate>) {
+        self.workspace_templates = templates;
+    }
+
+    pub fn has_placeholders(&self) -> bool { !self.placeholders.is_empty() }
+
+    /// Saves the tiled tree of the active workspace on `space` as the snapshot
+    /// `name`, identifying its windows with `identify`. Returns the number of
+    /// windows saved.
+    pub fn save_layout_snapshot(
+        &mut self,
+        space: SpaceId,
+        name: &str,
+        identify: impl Fn(WindowId) -> Option<WindowIdentity>,
+    ) -> anyhow::Result<usize> {
+        let Some(layout) = self
+            .virtual_workspace_manager
+            .active_workspace(space)
+            .and_then(|workspace_id| self.workspace_layouts.active(space, workspace_id))
+        else {
+            anyhow::bail!("no active workspace");
+        };
+        let root = self.tree.snapshot_layout(layout).and_then(|tree| {
+            tree.filter_map_windows(&mut |wid| match self.placeholders.get(&wid) {
+                Some(placeholder) => Some(placeholder.slot.clone()),
+                None => identify(wid)
+                    .map(|identity| Slot::Window(SnapshotWindow::from_identity(&identity))),
+            })
+        });
+        let Some(root) = root else {
+            anyhow::bail!("the workspace has no tiled windows");
+        };
+        let count = root.windows().len();
+        self.layout_snapshots.insert(name.to_string(), LayoutSnapshot { root });
+        self.layout_snapshots.save()?;
+        Ok(count)
+    }
+
+    /// Rebuilds the snapshot `name` as the tiled layout of the active workspace
+    /// on `space`, pulling in the best matching windows of `windows` from
+    /// wherever they live.
+    pub fn load_layout_snapshot(
+        &mut self,
+        space: SpaceId,
+        name: &str,
+        windows: &BTreeMap<WindowId, AppRuleQuery>,
+    ) -> anyhow::Result<EventResponse> {
+        let Some(snapshot) = self.layout_snapshots.get(name).cloned() else {
+            anyhow::bail!("no saved layout named '{name}'");
+        };
+        let Some(workspace_id) = self.virtual_workspace_manager.active_workspace(space) else {
+            anyhow::bail!("no active workspace");
+        };
+        // Only windows the layout manages can be pulled in.
+        let candidates = windows
+            .iter()
+            .filter(|(wid, _)| self.virtual_workspace_manager.locate_window(**wid).is_some())
+            .map(|(&wid, window)| (wid, window))
+            .collect();
+        self.lay_out_workspace(space, workspace_id, snapshot.root, candidates)
+            .ok_or_else(|| anyhow::anyhow!("the saved layout '{name}' is empty"))
+    }
+
+    /// Lays out the workspace template `name` on the active workspace on
+    /// `space`. Tiled windows of the workspace fill the slots they match.
+    pub fn apply_workspace_template(
+        &mut self,
+        space: SpaceId,
+        name: &str,
+        windows: &BTreeMap<WindowId, AppRuleQuery>,
+    ) -> anyhow::Result<EventResponse> {
+        let Some(workspace_id) = self.virtual_workspace_manager.active_workspace(space) else {
+            anyhow::bail!("no active workspace");
+        };
+        let Some(template) = self.workspace_templates.iter().find(|t| t.name == name) else {
+            anyhow::bail!("no workspace template named '{name}'");
+        };
+        let root = SnapshotNode::from(&template.layout);
+        // Templates only arrange the workspace's own windows; app rules decide
+        // which windows belong there.
+        let candidates = windows
+            .iter()
+            .filter(|(wid, _)| {
+                self.virtual_workspace_manager.locate_window(**wid).map(|(_, ws)| ws)
+                    == Some(workspace_id)
+            })
+            .map(|(&wid, window)| (wid, window))
+            .collect();
+        self.lay_out_workspace(space, workspace_id, root, candidates)
+            .ok_or_else(|| anyhow::anyhow!("the workspace template '{name}' is empty"))
+    }
+
+    /// Lays out the templates naming any of `workspaces`, which were just
+    /// created on `space`.
+    fn apply_templates_to_new_workspaces(
+        &mut self,
+        space: SpaceId,
+        workspaces: &[VirtualWorkspaceId],
+    ) {
+        for &workspace_id in workspaces {
+            if self.workspace_layouts.active(space, workspace_id).is_none() {
+                continue;
+            }
+            let Some(name) = self.workspace_name(space, workspace_id) else { continue };
+            let Some(template) =
+                self.workspace_templates.iter().find(|t| t.workspaces.contains(&name))
+            else {
+                continue;
+            };
+            debug!(template = %template.name, workspace = %name, "Applying workspace template");
+            let root = SnapshotNode::from(&template.layout);
+            self.lay_out_workspace(space, workspace_id, root, BTreeMap::new());
+        }
+    }
+
+    /// Replaces the tiled layout of `workspace_id` with `tree`. Each leaf takes
+    /// the best matching window of `candidates`, pulling it over from wherever
+    /// it lives; leaves no window matches become placeholders for the next
+    /// matching window to fill. Tiled windows of the workspace that the tree
+    /// does not place are added after it. Returns `None` if the tree is empty.
+    fn lay_out_workspace(
+        &mut self,
+        space: SpaceId,
+        workspace_id: VirtualWorkspaceId,
+        tree: SnapshotNode<Slot>,
+        mut candidates: BTreeMap<WindowId, &AppRuleQuery>,
+    ) -> Option<EventResponse> {
+        let mut last_placeholder =
+            self.placeholders.keys().map(|wid| wid.idx.get()).max().unwrap_or(0);
+        let mut pulled = Vec::new();
+        let mut new_placeholders = Vec::new();
+        let tree = tree.filter_map_windows(&mut |slot: Slot| {
+            let best = candidates
+                .iter()
+                .filter_map(|(&wid, window)| Some((wid, slot.match_score(window)?)))
+                .max_by(|(a_id, a), (b_id, b)| a.cmp(b).then(b_id.cmp(a_id)))
+                .map(|(wid, _)| wid);
+            if let Some(wid) = best {
+                candidates.remove(&wid);
+                pulled.push(wid);
+                return Some(wid);
+            }
+            last_placeholder += 1;
+            let placeholder = placeholder_id(last_placeholder);
+            new_placeholders.push((placeholder, slot));
+            Some(placeholder)
+        })?;
+
+        for (placeholder, slot) in new_placeholders {
+            self.placeholders.insert(placeholder, Placeholder { slot, workspace: workspace_id });
+        }
+        self.replace_workspace_tree(space, workspace_id, &tree);
+        Some(EventResponse {
+            focus_window: pulled.first().copied(),
+            raise_windows: pulled,
+        })
+    }
+
+    /// Replaces the active tiled layout of `workspace_id` with `tree`, pulling
+    /// the windows it holds over from wherever they live. Tiled windows of the
+    /// workspace that the tree does not hold are added after it, and
+    /// placeholders it drops are forgotten. The layouts kept for other screen
+    /// sizes are left alone, and the replaced layout's state beyond its tree,
+    /// such as fullscreen windows, carries over. Returns the new layout.
+    fn replace_workspace_tree(
+        &mut self,
+        space: SpaceId,
+        workspace_id: VirtualWorkspaceId,
+        tree: &SnapshotNode<WindowId>,
+    ) -> LayoutId {
+        let placed: Vec<WindowId> = tree.windows().into_iter().copied().collect();
+        let old = self.workspace_layouts.active(space, workspace_id);
+        let leftovers: Vec<WindowId> = old
+            .and_then(|old| self.tree.snapshot_layout(old))
+            .map(|old| old.windows().into_iter().copied().collect())
+            .unwrap_or_default();
+        let extras = old.map(|old| self.tree.layout_extras(old)).unwrap_or_default();
+        if let Some(old) = old {
+            self.tree.remove_layout(old);
+        }
+
+        let pulled: Vec<WindowId> =
+            placed.iter().copied().filter(|&wid| !is_placeholder(wid)).collect();
+        let mut affected_spaces = if pulled.is_empty() { vec![] } else { vec![space] };
+        for &wid in &pulled {
+            let from = self.virtual_workspace_manager.locate_window(wid);
+            if from == Some((space, workspace_id)) && !self.floating.is_floating(wid) {
+                // Already tiled here: it left with the replaced layout, and
+                // keeps its place in the layouts for other screen sizes.
+                continue;
+            }
+            if let Some((from, _)) = from
+                && !affected_spaces.contains(&from)
+            {
+                affected_spaces.push(from);
+            }
+            self.tree.remove_window(wid);
+            if self.floating.is_floating(wid) {
+                self.floating.remove_floating(wid);
+                self.virtual_workspace_manager.remove_floating_position(wid);
+            }
+            self.virtual_workspace_manager.assign_window_to_workspace(space, wid, workspace_id);
+        }
+
+        let layout = self.tree.create_layout_from_snapshot(tree);
+        self.tree.set_layout_extras(layout, &extras);
+        for wid in leftovers {
+            if placed.contains(&wid) {
+                continue;
+            }
+            if is_placeholder(wid) {
+                self.placeholders.remove(&wid);
+                self.tree.remove_window(wid);
+            } else {
+                self.tree.add_window_after_selection(layout, wid);
+            }
+        }
+        self.workspace_layouts.set_layout(space, workspace_id, layout);
+
+        for affected in affected_spaces {
+            self.reconcile_workspaces(affected);
+            self.update_active_floating_windows(affected);
+            self.broadcast_windows_changed(affected);
+        }
+        layout
+    }
+
+    /// Puts the new window `wid` in the place of the loaded-snapshot leaf it
+    /// matches best, wherever that is. Returns whether it did.
+    pub fn fill_placeholder(&mut self, wid: WindowId, window: &AppRuleQuery) -> bool {
+        self.fill_best_placeholder(wid, window, |placeholder| {
+            matches!(placeholder.slot, Slot::Window(_))
+        })
+    }
+
+    /// Puts a tiled window joining `workspace_id` in the slot of a matching
+    /// placeholder there, rather than after the selection.
+    fn fill_workspace_placeholder(
+        &mut self,
+        wid: WindowId,
+        workspace_id: VirtualWorkspaceId,
+        window: &AppRuleQuery,
+    ) {
+        if self.placeholders.values().all(|placeholder| placeholder.workspace != workspace_id) {
+            return;
+        }
+        let Some(space) = self.virtual_workspace_manager.workspace_space(workspace_id) else {
+            return;
+        };
+        if self
+            .workspace_layouts
+            .active(space, workspace_id)
+            .is_some_and(|layout| self.tree.contains_window(layout, wid))
+        {
+            return;
+        }
+        self.fill_best_placeholder(wid, window, |placeholder| {
+            placeholder.workspace == workspace_id
+        });
+    }
+
+    /// Puts `wid` in the place of the best matching placeholder that `accept`
+    /// allows, moving it to that placeholder's workspace.
+    fn fill_best_placeholder(
+        &mut self,
+        wid: WindowId,
+        window: &AppRuleQuery,
+        accept: impl Fn(&Placeholder) -> bool,
+    ) -> bool {
+        let Some(placeholder) = self
+            .placeholders
+            .iter()
+            .filter(|(_, placeholder)| accept(placeholder))
+            .filter_map(|(&p, placeholder)| Some((p, placeholder.slot.match_score(window)?)))
+            .max_by(|(a_id, a), (b_id, b)| a.cmp(b).then(b_id.cmp(a_id)))
+            .map(|(p, _)| p)
+        else {
+            return false;
+        };
+        let Some(Placeholder { workspace, .. }) = self.placeholders.remove(&placeholder) else {
+            return false;
+        };
+        let vwm = &mut self.virtual_workspace_manager;
+        let assigned = vwm.locate_window(wid).is_some_and(|(_, ws)| ws == workspace)
+            || vwm
+                .workspace_space(workspace)
+                .is_some_and(|space| vwm.assign_window_to_workspace(space, wid, workspace));
+        if !assigned {
+            self.tree.remove_window(placeholder);
+            return false;
+        }
+        debug!(?placeholder, ?wid, "Filled layout placeholder");
+        self.tree.replace_window(placeholder, wid);
+        self.layout_history.replace_window(placeholder, wid);
+        true
+    }
+
+    /// The workspaces whose tiled trees `command` may reshape, recorded so the
+    /// command can be undone. Commands that only move focus or the selection
+    /// are not undoable.
+    fn undoable_workspaces(
+        &mut self,
+        space: SpaceId,
+        workspace: Option<VirtualWorkspaceId>,
+        command: &LayoutCommand,
+    ) -> Vec<VirtualWorkspaceId> {
+        let active = workspace.or(self.virtual_workspace_manager.active_workspace(space));
+        match command {
+            LayoutCommand::MoveNode(_)
+            | LayoutCommand::JoinWindow(_)
+            | LayoutCommand::UnjoinWindows
+            | LayoutCommand::GroupSelection
+            | LayoutCommand::UngroupSelection
+            | LayoutCommand::UngroupSiblings
+            | LayoutCommand::MoveSelectionToSiblingNext
+            | LayoutCommand::MoveSelectionToSiblingPrev
+            | LayoutCommand::ToggleStack
+            | LayoutCommand::ToggleOrientation
+            | LayoutCommand::ResizeWindowGrow
+            | LayoutCommand::ResizeWindowShrink
+            | LayoutCommand::ResizeWindowBy { .. } => active.into_iter().collect(),
+            LayoutCommand::MoveWindowToWorkspace { workspace, window_id, .. } => {
+                let Some((window_space, source)) = self
+                    .command_window(space, *window_id)
+                    .and_then(|wid| self.virtual_workspace_manager.locate_window(wid))
+                else {
+                    return Vec::new();
+                };
+                let workspaces = self.virtual_workspace_manager.workspace_order(window_space);
+                let target = workspaces.get(*workspace).map(|&(id, _)| id);
+                [Some(source), target].into_iter().flatten().collect()
+            }
+            LayoutCommand::MoveContainerToWorkspace { workspace } => {
+                let workspaces = self.virtual_workspace_manager.workspace_order(space);
+                let target = workspaces.get(*workspace).map(|&(id, _)| id);
+                [active, target].into_iter().flatten().collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// The tiled trees of `workspaces`, as the layout history keeps them.
+    fn workspace_trees(&self, workspaces: &[VirtualWorkspaceId]) -> Vec<WorkspaceTree> {
+        workspaces
+            .iter()
+            .map(|&workspace| {
+                let layout = self
+                    .virtual_workspace_manager
+                    .workspace_space(workspace)
+                    .and_then(|space| self.workspace_layouts.active(space, workspace));
+                WorkspaceTree {
+                    workspace,
+                    tree: layout.and_then(|layout| self.tree.snapshot_layout(layout)),
+                    extras: layout
+                        .map(|layout| self.tree.layout_extras(layout))
+                        .unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+
+    /// Records `before` as an undo step if a command run since changed any of
+    /// its trees.
+    fn record_layout_change(&mut self, before: Vec<WorkspaceTree>) {
+        let workspaces: Vec<_> = before.iter().map(|entry| entry.workspace).collect();
+        if !workspaces.is_empty() && self.workspace_trees(&workspaces) != before {
+            self.layout_history.record(before);
+        }
+    }
+
+    /// Restores the trees of the last undo step, or of the last redo step if
+    /// `undo` is false, keeping the trees they replace to step back again.
+    fn step_layout_history(&mut self, undo: bool) -> EventResponse {
+        let step = if undo {
+            self.layout_history.take_undo()
+        } else {
+            self.layout_history.take_redo()
+        };
+        let Some(step) = step else {
+            debug!(undo, "No layout change to step over");
+            return EventResponse::default();
+        };
+        let workspaces: Vec<_> = step.iter().map(|entry| entry.workspace).collect();
+        let current = self.workspace_trees(&workspaces);
+        if undo {
+            self.layout_history.push_redo(current);
+        } else {
+            self.layout_history.push_undo(current);
+        }
+
+        let mut raise_windows = Vec::new();
+        for WorkspaceTree { workspace, tree, extras } in step {
+            let Some(space) = self.virtual_workspace_manager.workspace_space(workspace) else {
+                continue;
+            };
+            // Windows closed or floated since are left out; windows opened
+            // since stay, after the restored tree.
+            let Some(tree) = tree.and_then(|tree| {
+                tree.filter_map_windows(&mut |wid| self.can_restore_window(wid).then_some(wid))
+            }) else {
+                continue;
+            };
+            let layout = self.replace_workspace_tree(space, workspace, &tree);
+            self.tree.set_layout_extras(layout, &extras);
+            if let Some(focus) = self.focused_window
+                && self.tree.contains_window(layout, focus)
+            {
+                self.tree.select_window(layout, focus);
+            }
+            if self.virtual_workspace_manager.active_workspace(space) == Some(workspace) {
+                raise_windows.extend(self.tree.visible_windows_in_layout(layout));
+            }
+        }
+        EventResponse { raise_windows, focus_window: None }
+    }
+
+    /// Whether `wid` can take its place again in a tree from the history.
+    fn can_restore_window(&self, wid: WindowId) -> bool {
+        if is_placeholder(wid) {
+            self.placeholders.contains_key(&wid)
+        } else {
+            self.virtual_workspace_manager.locate_window(wid).is_some()
+                && !self.floating.is_floating(wid)
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn selected_window(&mut self, space: SpaceId) -> Option<WindowId> {
+        let layout = self.layout(space);
+        self.tree.selected_window(layout)
+    }
+
+    pub fn handle_virtual_workspace_command(
+        &mut self,
+        space: SpaceId,
+        command: &LayoutCommand,
+    ) -> EventResponse {
+        let workspaces = self.undoable_workspaces(space, None, command);
+        let before = self.workspace_trees(&workspaces);
+        let response = self.apply_virtual_workspace_command(space, command);
+        self.reconcile_workspaces(space);
+        self.record_layout_change(before);
+        without_placeholders(response)
+    }
+
+    fn apply_virtual_workspace_command(
+        &mut self,
+        space: SpaceId,
+        command: &LayoutCommand,
+    ) -> EventResponse {
+        match command {
+            LayoutCommand::NextWorkspace(skip_empty) => {
+                if let Some(current_workspace) =
+                    self.virtual_workspace_manager.active_workspace(space)
+                {
+                    if let Some(next_workspace) = self.virtual_workspace_manager.next_workspace(
+                        space,
+                        current_workspace,
+                        *skip_empty,
+                    ) {
+                        self.show_workspace_on_space(space, next_workspace);
+
+                        self.update_active_floating_windows(space);
+
+                        self.broadcast_workspace_changed(space);
+                        self.broadcast_windows_changed(space);
+
+                        return self.refocus_workspace(space, next_workspace);
+                    }
+                }
+                EventResponse::default()
             }
             LayoutCommand::PrevWorkspace(skip_empty) => {
                 if let Some(current_workspace) =
@@ -1705,7 +2835,7 @@ impl LayoutEngine {
                         current_workspace,
                         *skip_empty,
                     ) {
-                        self.virtual_workspace_manager.set_active_workspace(space, prev_workspace);
+                        self.show_workspace_on_space(space, prev_workspace);
 
                         self.update_active_floating_windows(space);
 
@@ -1718,28 +2848,33 @@ impl LayoutEngine {
                 EventResponse::default()
             }
             LayoutCommand::SwitchToWorkspace(workspace_index) => {
-                let workspaces = self.virtual_workspace_manager_mut().list_workspaces(space);
+                let workspaces = self.virtual_workspace_manager.workspace_order(space);
                 if let Some((workspace_id, _)) = workspaces.get(*workspace_index) {
                     let workspace_id = *workspace_id;
                     if self.virtual_workspace_manager.active_workspace(space) == Some(workspace_id)
                     {
                         // Check if workspace_auto_back_and_forth is enabled
                         if self.virtual_workspace_manager.workspace_auto_back_and_forth() {
-                            // Switch to last workspace instead
-                            if let Some(last_workspace) =
-                                self.virtual_workspace_manager.last_workspace(space)
+                            // Toggle to the previous workspace in the history instead
+                            if let Some(target) = self
+                                .virtual_workspace_manager
+                                .workspace_history_target(space, false)
                             {
-                                self.virtual_workspace_manager
-                                    .set_active_workspace(space, last_workspace);
+                                self.bring_workspace_to_space(space, target);
+                                self.virtual_workspace_manager.toggle_workspace_history(
+                                    space,
+                                    target,
+                                    Some(workspace_id),
+                                );
                                 self.update_active_floating_windows(space);
                                 self.broadcast_workspace_changed(space);
                                 self.broadcast_windows_changed(space);
-                                return self.refocus_workspace(space, last_workspace);
+                                return self.refocus_workspace(space, target);
                             }
                         }
                         return EventResponse::default();
                     }
-                    self.virtual_workspace_manager.set_active_workspace(space, workspace_id);
+                    self.show_workspace_on_space(space, workspace_id);
 
                     self.update_active_floating_windows(space);
 
@@ -1753,6 +2888,30 @@ impl LayoutEngine {
             LayoutCommand::MoveWindowToWorkspace {
                 workspace: workspace_index,
                 window_id: maybe_id,
+                follow: true,
+            } => {
+                let Some(window) = self.command_window(space, *maybe_id) else {
+                    return EventResponse::default();
+                };
+                let op_space = self.space_with_window(window).unwrap_or(space);
+                let workspaces = self.virtual_workspace_manager.workspace_order(op_space);
+                let Some(&(target_workspace_id, _)) = workspaces.get(*workspace_index) else {
+                    return EventResponse::default();
+                };
+                let _ = self.apply_virtual_workspace_command(
+                    space,
+                    &LayoutCommand::MoveWindowToWorkspace {
+                        workspace: *workspace_index,
+                        window_id: *maybe_id,
+                        follow: false,
+                    },
+                );
+                self.follow_window_to_workspace(op_space, window, target_workspace_id)
+            }
+            LayoutCommand::MoveWindowToWorkspace {
+                workspace: workspace_index,
+                window_id: maybe_id,
+                follow: false,
             } => {
                 let focused_window = if let Some(spec_u32) = maybe_id {
                     match self.virtual_workspace_manager.find_window_by_idx(space, *spec_u32) {
@@ -1773,7 +2932,7 @@ impl LayoutEngine {
                     inferred_space.unwrap_or(space)
                 };
 
-                let workspaces = self.virtual_workspace_manager_mut().list_workspaces(op_space);
+                let workspaces = self.virtual_workspace_manager.workspace_order(op_space);
                 let Some((target_workspace_id, _)) = workspaces.get(*workspace_index) else {
                     return EventResponse::default();
                 };
@@ -1788,6 +2947,11 @@ impl LayoutEngine {
                 if current_workspace_id == target_workspace_id {
                     return EventResponse::default();
                 }
+                // Global workspaces may currently live on another display's space.
+                let target_space = self
+                    .virtual_workspace_manager
+                    .workspace_space(target_workspace_id)
+                    .unwrap_or(op_space);
 
                 let is_floating = self.floating.is_floating(focused_window);
 
@@ -1800,7 +2964,7 @@ impl LayoutEngine {
                 }
 
                 let assigned = self.virtual_workspace_manager.assign_window_to_workspace(
-                    op_space,
+                    target_space,
                     focused_window,
                     target_workspace_id,
                 );
@@ -1817,10 +2981,15 @@ impl LayoutEngine {
 
                 if !is_floating {
                     if let Some(target_layout) =
-                        self.workspace_layouts.active(op_space, target_workspace_id)
+                        self.workspace_layouts.active(target_space, target_workspace_id)
                     {
                         self.tree.add_window_after_selection(target_layout, focused_window);
                     }
+                } else if target_space != op_space
+                    && self.virtual_workspace_manager.active_workspace(target_space)
+                        == Some(target_workspace_id)
+                {
+                    self.floating.add_active(target_space, focused_window.pid, focused_window);
                 }
 
                 let active_workspace = self.virtual_workspace_manager.active_workspace(op_space);
@@ -1851,40 +3020,595 @@ impl LayoutEngine {
                     }
                 }
 
-                self.virtual_workspace_manager.set_last_focused_window(
-                    op_space,
-                    target_workspace_id,
-                    Some(focused_window),
-                );
+                self.virtual_workspace_manager.set_last_focused_window(
+                    target_space,
+                    target_workspace_id,
+                    Some(focused_window),
+                );
+
+                self.broadcast_windows_changed(op_space);
+                EventResponse::default()
+            }
+            LayoutCommand::MoveWindowToNewWorkspace { window_id, follow } => {
+                let Some(window) = self.command_window(space, *window_id) else {
+                    return EventResponse::default();
+                };
+                let op_space = self.space_with_window(window).unwrap_or(space);
+                let workspace_id =
+                    match self.virtual_workspace_manager.create_workspace(op_space, None) {
+                        Ok(workspace_id) => workspace_id,
+                        Err(e) => {
+                            warn!("Failed to create new workspace: {:?}", e);
+                            return EventResponse::default();
+                        }
+                    };
+                self.sync_workspace_layouts(op_space, Vec::new());
+                let Some(workspace_index) = self
+                    .virtual_workspace_manager
+                    .workspace_order(op_space)
+                    .iter()
+                    .position(|(id, _)| *id == workspace_id)
+                else {
+                    return EventResponse::default();
+                };
+                self.apply_virtual_workspace_command(
+                    space,
+                    &LayoutCommand::MoveWindowToWorkspace {
+                        workspace: workspace_index,
+                        window_id: *window_id,
+                        follow: *follow,
+                    },
+                )
+            }
+            LayoutCommand::MoveContainerToWorkspace { workspace: workspace_index } => {
+                // A floating window has no container to take along.
+                if self.focused_window.is_some_and(|wid| self.floating.is_floating(wid)) {
+                    return self.apply_virtual_workspace_command(
+                        space,
+                        &LayoutCommand::MoveWindowToWorkspace {
+                            workspace: *workspace_index,
+                            window_id: None,
+                            follow: false,
+                        },
+                    );
+                }
+                let Some(current_workspace_id) =
+                    self.virtual_workspace_manager.active_workspace(space)
+                else {
+                    return EventResponse::default();
+                };
+                let workspaces = self.virtual_workspace_manager.workspace_order(space);
+                let Some(&(target_workspace_id, _)) = workspaces.get(*workspace_index) else {
+                    return EventResponse::default();
+                };
+                if target_workspace_id == current_workspace_id {
+                    return EventResponse::default();
+                }
+                let target_space = self
+                    .virtual_workspace_manager
+                    .workspace_space(target_workspace_id)
+                    .unwrap_or(space);
+                let (Some(source_layout), Some(target_layout)) = (
+                    self.workspace_layouts.active(space, current_workspace_id),
+                    self.workspace_layouts.active(target_space, target_workspace_id),
+                ) else {
+                    return EventResponse::default();
+                };
+
+                let source_windows =
+                    self.virtual_workspace_manager.windows_in_active_workspace(space);
+                self.tree.move_selection_to_layout_after_selection(source_layout, target_layout);
+                let moved: Vec<WindowId> = source_windows
+                    .into_iter()
+                    .filter(|&wid| self.tree.contains_window(target_layout, wid))
+                    .collect();
+                let Some(&first_moved) = moved.first() else {
+                    return EventResponse::default();
+                };
+                for &wid in &moved {
+                    self.virtual_workspace_manager.assign_window_to_workspace(
+                        target_space,
+                        wid,
+                        target_workspace_id,
+                    );
+                }
+
+                let last_focused = self.focused_window.filter(|wid| moved.contains(wid));
+                self.virtual_workspace_manager.set_last_focused_window(
+                    target_space,
+                    target_workspace_id,
+                    Some(last_focused.unwrap_or(first_moved)),
+                );
+                self.focused_window = None;
+                self.virtual_workspace_manager.set_last_focused_window(
+                    space,
+                    current_workspace_id,
+                    None,
+                );
+
+                self.broadcast_windows_changed(space);
+                if target_space != space {
+                    self.broadcast_windows_changed(target_space);
+                }
+                let remaining = self.virtual_workspace_manager.windows_in_active_workspace(space);
+                let focus_window =
+                    self.tree.selected_window(source_layout).or(remaining.first().copied());
+                EventResponse { focus_window, raise_windows: vec![] }
+            }
+            LayoutCommand::CreateWorkspace => {
+                match self.virtual_workspace_manager.create_workspace(space, None) {
+                    Ok(_workspace_id) => {
+                        self.broadcast_workspace_changed(space);
+                    }
+                    Err(e) => {
+                        warn!("Failed to create new workspace: {:?}", e);
+                    }
+                }
+                EventResponse::default()
+            }
+            LayoutCommand::SwitchToLastWorkspace => {
+                if let Some(last_workspace) = self.virtual_workspace_manager.last_workspace(space) {
+                    self.show_workspace_on_space(space, last_workspace);
+
+                    self.update_active_floating_windows(space);
+
+                    self.broadcast_workspace_changed(space);
+                    self.broadcast_windows_changed(space);
+
+                    return self.refocus_workspace(space, last_workspace);
+                }
+                EventResponse::default()
+            }
+            LayoutCommand::WorkspaceBack => self.step_workspace_history(space, false),
+            LayoutCommand::WorkspaceForward => self.step_workspace_history(space, true),
+            LayoutCommand::RenameWorkspace { workspace, name } => {
+                let Some(workspace_id) = self.workspace_id_for_command(space, *workspace) else {
+                    return EventResponse::default();
+                };
+                if self.virtual_workspace_manager.rename_workspace(
+                    space,
+                    workspace_id,
+                    name.clone(),
+                ) {
+                    self.broadcast_workspace_changed(space);
+                }
+                EventResponse::default()
+            }
+            LayoutCommand::DeleteWorkspace { workspace } => {
+                let Some(workspace_id) = self.workspace_id_for_command(space, *workspace) else {
+                    return EventResponse::default();
+                };
+                let was_active =
+                    self.virtual_workspace_manager.active_workspace(space) == Some(workspace_id);
+                let tiled_windows: Vec<WindowId> = self
+                    .virtual_workspace_manager
+                    .workspace_windows(space, workspace_id)
+                    .into_iter()
+                    .filter(|wid| !self.floating.is_floating(*wid))
+                    .collect();
+
+                let fallback =
+                    match self.virtual_workspace_manager.delete_workspace(space, workspace_id) {
+                        Ok(fallback) => fallback,
+                        Err(e) => {
+                            warn!("Failed to delete workspace: {:?}", e);
+                            return EventResponse::default();
+                        }
+                    };
+
+                for wid in &tiled_windows {
+                    self.tree.remove_window(*wid);
+                }
+                for layout in self.workspace_layouts.remove_workspace(space, workspace_id) {
+                    self.tree.remove_layout(layout);
+                }
+                if let Some(layout) = self.workspace_layouts.active(space, fallback) {
+                    for wid in tiled_windows {
+                        self.tree.add_window_after_selection(layout, wid);
+                    }
+                }
+
+                self.update_active_floating_windows(space);
+                self.broadcast_workspace_changed(space);
+                self.broadcast_windows_changed(space);
+
+                if was_active {
+                    return self.refocus_workspace(space, fallback);
+                }
+                EventResponse::default()
+            }
+            LayoutCommand::MoveWorkspaceToIndex { workspace, index } => {
+                let Some(workspace_id) = self.workspace_id_for_command(space, *workspace) else {
+                    return EventResponse::default();
+                };
+                if self.virtual_workspace_manager.move_workspace_to_index(
+                    space,
+                    workspace_id,
+                    *index,
+                ) {
+                    self.broadcast_workspace_changed(space);
+                }
+                EventResponse::default()
+            }
+            LayoutCommand::SwapWorkspaces { workspace, other } => {
+                let Some(workspace_id) = self.workspace_id_for_command(space, *workspace) else {
+                    return EventResponse::default();
+                };
+                let other_id = match other {
+                    WorkspaceSelector::Index(index) => {
+                        self.workspace_id_for_command(space, Some(*index))
+                    }
+                    WorkspaceSelector::Name(name) => self
+                        .virtual_workspace_manager
+                        .workspace_order(space)
+                        .into_iter()
+                        .find(|(_, workspace_name)| workspace_name == name)
+                        .map(|(id, _)| id),
+                };
+                let Some(other_id) = other_id else {
+                    return EventResponse::default();
+                };
+                if !self.virtual_workspace_manager.swap_workspaces(space, workspace_id, other_id) {
+                    return EventResponse::default();
+                }
+                self.workspace_layouts.swap_workspaces(space, workspace_id, other_id);
+
+                self.update_active_floating_windows(space);
+                self.broadcast_workspace_changed(space);
+                self.broadcast_windows_changed(space);
+
+                match self.virtual_workspace_manager.active_workspace(space) {
+                    Some(active) if active == workspace_id || active == other_id => {
+                        self.focused_window = None;
+                        self.refocus_workspace(space, active)
+                    }
+                    _ => EventResponse::default(),
+                }
+            }
+            LayoutCommand::ToggleTag(index) => {
+                let Some(workspace_id) = self.workspace_id_for_command(space, Some(*index)) else {
+                    return EventResponse::default();
+                };
+                let Some(wid) = self.focused_window.filter(|wid| {
+                    self.virtual_workspace_manager.workspace_for_window(space, *wid).is_some()
+                }) else {
+                    return EventResponse::default();
+                };
+                if !self.virtual_workspace_manager.toggle_window_tag(space, wid, workspace_id) {
+                    return EventResponse::default();
+                }
+                self.sync_space_layout_windows(space);
+                self.update_active_floating_windows(space);
+                self.broadcast_windows_changed(space);
+
+                if self.virtual_workspace_manager.is_window_in_active_workspace(space, wid) {
+                    return EventResponse::default();
+                }
+                match self.virtual_workspace_manager.active_workspace(space) {
+                    Some(active) => {
+                        self.focused_window = None;
+                        self.refocus_workspace(space, active)
+                    }
+                    None => EventResponse::default(),
+                }
+            }
+            LayoutCommand::ToggleView(index) => {
+                let Some(workspace_id) = self.workspace_id_for_command(space, Some(*index)) else {
+                    return EventResponse::default();
+                };
+                if !self.virtual_workspace_manager.toggle_view(space, workspace_id) {
+                    return EventResponse::default();
+                }
+                self.sync_space_layout_windows(space);
+                self.update_active_floating_windows(space);
+                self.broadcast_windows_changed(space);
+                EventResponse::default()
+            }
+            LayoutCommand::FocusBack => {
+                match self.focus_history.previous(self.focused_window) {
+                    Some(wid) => self.focus_window_anywhere(wid),
+                    None => EventResponse::default(),
+                }
+            }
+            LayoutCommand::FocusMruNext | LayoutCommand::FocusMruPrev => {
+                let forward = matches!(command, LayoutCommand::FocusMruNext);
+                match self.focus_history.cycle(self.focused_window, forward) {
+                    Some(wid) => self.focus_window_anywhere(wid),
+                    None => EventResponse::default(),
+                }
+            }
+            LayoutCommand::FocusMruCommit => {
+                self.focus_history.commit_cycle();
+                EventResponse::default()
+            }
+            LayoutCommand::FocusMark(mark) => match self.marks.get(mark).copied() {
+                Some(wid) => self.focus_window_anywhere(wid),
+                None => EventResponse::default(),
+            },
+            LayoutCommand::SwapWithMark(mark) => self.swap_with_mark(mark),
+            _ => EventResponse::default(),
+        }
+    }
+
+    /// Applies display pins and dynamic workspace bookkeeping for a space and
+    /// keeps the layout state in sync with the workspaces that were added or removed.
+    fn reconcile_workspaces(&mut self, space: SpaceId) {
+        if !self.virtual_workspace_manager.is_dynamic()
+            && !self.virtual_workspace_manager.has_display_pins()
+        {
+            return;
+        }
+        let mut removed = self.virtual_workspace_manager.apply_display_pins(space);
+        removed.extend(self.virtual_workspace_manager.reconcile_dynamic_workspaces(space));
+        self.sync_workspace_layouts(space, removed);
+    }
+
+    fn sync_workspace_layouts(&mut self, space: SpaceId, removed: Vec<VirtualWorkspaceId>) {
+        for workspace_id in &removed {
+            for layout in self.workspace_layouts.remove_workspace(space, *workspace_id) {
+                self.tree.remove_layout(layout);
+            }
+        }
+        self.placeholders.retain(|_, placeholder| !removed.contains(&placeholder.workspace));
+
+        let workspaces = self.virtual_workspace_manager.list_workspaces(space);
+        let created: Vec<_> = workspaces
+            .iter()
+            .map(|(id, _)| *id)
+            .filter(|id| self.workspace_layouts.active(space, *id).is_none())
+            .collect();
+        if !created.is_empty() {
+            self.workspace_layouts.ensure_active_for_new_workspaces(
+                space,
+                workspaces.into_iter().map(|(id, _)| id),
+                &mut self.tree,
+            );
+            self.apply_templates_to_new_workspaces(space, &created);
+        }
+
+        if !created.is_empty() || !removed.is_empty() {
+            self.broadcast_workspace_changed(space);
+        }
+    }
+
+    /// Whether `command`, a focus history command, would focus a window whose
+    /// workspace is hidden and so switch workspaces.
+    pub fn focus_history_switches_workspace(&self, command: &LayoutCommand) -> bool {
+        let target = match command {
+            LayoutCommand::FocusBack => self.focus_history.previous(self.focused_window),
+            LayoutCommand::FocusMruNext | LayoutCommand::FocusMruPrev => {
+                let forward = matches!(command, LayoutCommand::FocusMruNext);
+                self.focus_history.peek_cycle(self.focused_window, forward)
+            }
+            _ => None,
+        };
+        target.is_some_and(|wid| {
+            self.virtual_workspace_manager.locate_window(wid).is_some_and(|(space, _)| {
+                !self.virtual_workspace_manager.is_window_in_active_workspace(space, wid)
+            })
+        })
+    }
+
+    /// Focuses a window wherever it lives, first showing its workspace on its
+    /// display if it is hidden.
+    fn focus_window_anywhere(&mut self, wid: WindowId) -> EventResponse {
+        let Some((space, workspace_id)) = self.virtual_workspace_manager.locate_window(wid) else {
+            return EventResponse::default();
+        };
+        if !self.virtual_workspace_manager.is_window_in_active_workspace(space, wid) {
+            self.show_workspace_on_space(space, workspace_id);
+            self.update_active_floating_windows(space);
+            self.broadcast_workspace_changed(space);
+            self.broadcast_windows_changed(space);
+        }
+        if !self.floating.is_floating(wid)
+            && let Some(active) = self.virtual_workspace_manager.active_workspace(space)
+            && let Some(layout) = self.workspace_layouts.active(space, active)
+        {
+            let _ = self.tree.select_window(layout, wid);
+        }
+        EventResponse {
+            raise_windows: vec![],
+            focus_window: Some(wid),
+        }
+    }
+
+    /// Exchanges the focused window with the one carrying `mark`, including
+    /// their workspaces when they live on different ones.
+    fn swap_with_mark(&mut self, mark: &str) -> EventResponse {
+        let (Some(focused), Some(marked)) = (self.focused_window, self.marks.get(mark).copied())
+        else {
+            return EventResponse::default();
+        };
+        if focused == marked {
+            return EventResponse::default();
+        }
+        let (Some((focused_space, focused_ws)), Some((marked_space, marked_ws))) = (
+            self.virtual_workspace_manager.locate_window(focused),
+            self.virtual_workspace_manager.locate_window(marked),
+        ) else {
+            return EventResponse::default();
+        };
+
+        let focused_tiled = !self.floating.is_floating(focused);
+        let marked_tiled = !self.floating.is_floating(marked);
+        let focused_layout = self.workspace_layouts.active(focused_space, focused_ws);
+        let marked_layout = self.workspace_layouts.active(marked_space, marked_ws);
+
+        if focused_ws != marked_ws {
+            self.virtual_workspace_manager.assign_window_to_workspace(
+                marked_space,
+                focused,
+                marked_ws,
+            );
+            self.virtual_workspace_manager.assign_window_to_workspace(
+                focused_space,
+                marked,
+                focused_ws,
+            );
+        }
 
-                self.broadcast_windows_changed(op_space);
-                EventResponse::default()
+        match (focused_layout, marked_layout) {
+            (Some(focused_layout), Some(marked_layout)) if focused_tiled && marked_tiled => {
+                self.tree.swap_windows_between_layouts(
+                    focused_layout,
+                    focused,
+                    marked_layout,
+                    marked,
+                );
             }
-            LayoutCommand::CreateWorkspace => {
-                match self.virtual_workspace_manager.create_workspace(space, None) {
-                    Ok(_workspace_id) => {
-                        self.broadcast_workspace_changed(space);
-                    }
-                    Err(e) => {
-                        warn!("Failed to create new workspace: {:?}", e);
+            _ if focused_ws != marked_ws => {
+                // A floating window has no tree position to trade, so any tiled
+                // window just joins the layout of its new workspace.
+                let moves = [
+                    (focused, focused_tiled, marked_layout),
+                    (marked, marked_tiled, focused_layout),
+                ];
+                for (wid, tiled, layout) in moves {
+                    if tiled && let Some(layout) = layout {
+                        self.tree.remove_window(wid);
+                        self.tree.add_window_after_selection(layout, wid);
                     }
                 }
-                EventResponse::default()
             }
-            LayoutCommand::SwitchToLastWorkspace => {
-                if let Some(last_workspace) = self.virtual_workspace_manager.last_workspace(space) {
-                    self.virtual_workspace_manager.set_active_workspace(space, last_workspace);
+            _ => {}
+        }
 
-                    self.update_active_floating_windows(space);
+        self.update_active_floating_windows(focused_space);
+        self.broadcast_windows_changed(focused_space);
+        if marked_space != focused_space {
+            self.update_active_floating_windows(marked_space);
+            self.broadcast_windows_changed(marked_space);
+        }
 
-                    self.broadcast_workspace_changed(space);
-                    self.broadcast_windows_changed(space);
+        let focus = if self.virtual_workspace_manager.is_window_in_active_workspace(
+            marked_space,
+            focused,
+        ) {
+            focused
+        } else {
+            marked
+        };
+        EventResponse {
+            raise_windows: vec![],
+            focus_window: Some(focus),
+        }
+    }
 
-                    return self.refocus_workspace(space, last_workspace);
-                }
-                EventResponse::default()
+    /// Brings every layout on `space` in line with the windows its workspace
+    /// tiles once tags and views are taken into account.
+    fn sync_space_layout_windows(&mut self, space: SpaceId) {
+        for (workspace_id, layout) in self.workspace_layouts.active_layouts_for_space(space) {
+            self.sync_layout_windows(space, workspace_id, layout);
+        }
+    }
+
+    fn sync_layout_windows(
+        &mut self,
+        space: SpaceId,
+        workspace_id: VirtualWorkspaceId,
+        layout: LayoutId,
+    ) {
+        let desired: HashSet<WindowId> = self
+            .virtual_workspace_manager
+            .layout_windows(space, workspace_id)
+            .into_iter()
+            .filter(|wid| !self.floating.is_floating(*wid))
+            .collect();
+
+        let mut by_pid: HashMap<pid_t, Vec<WindowId>> = HashMap::default();
+        let mut stale: HashSet<pid_t> = HashSet::default();
+        for wid in self.virtual_workspace_manager.windows_on_space(space) {
+            let wanted = desired.contains(&wid);
+            if wanted {
+                by_pid.entry(wid.pid).or_default().push(wid);
             }
-            _ => EventResponse::default(),
+            if wanted != self.tree.contains_window(layout, wid) {
+                stale.insert(wid.pid);
+            }
+        }
+        for pid in stale {
+            let windows = by_pid.remove(&pid).unwrap_or_default();
+            self.tree.set_windows_for_app(layout, pid, windows);
+        }
+    }
+
+    /// Makes `workspace_id` active on `space`, bringing it over from another
+    /// display first when workspaces are global.
+    fn show_workspace_on_space(&mut self, space: SpaceId, workspace_id: VirtualWorkspaceId) {
+        let previous = self.virtual_workspace_manager.active_workspace(space);
+        self.bring_workspace_to_space(space, workspace_id);
+        self.virtual_workspace_manager.set_active_workspace_from(space, workspace_id, previous);
+    }
+
+    fn step_workspace_history(&mut self, space: SpaceId, forward: bool) -> EventResponse {
+        let Some(target) = self.virtual_workspace_manager.workspace_history_target(space, forward)
+        else {
+            return EventResponse::default();
+        };
+        let previous = self.virtual_workspace_manager.active_workspace(space);
+        self.bring_workspace_to_space(space, target);
+        if !self
+            .virtual_workspace_manager
+            .step_workspace_history(space, target, forward, previous)
+        {
+            return EventResponse::default();
+        }
+
+        self.update_active_floating_windows(space);
+        self.broadcast_workspace_changed(space);
+        self.broadcast_windows_changed(space);
+        self.refocus_workspace(space, target)
+    }
+
+    /// In the global workspace model, moves `workspace_id` onto `space` so it can
+    /// be activated there. If it was visible on another display, that display
+    /// takes over the workspace currently shown on `space`.
+    fn bring_workspace_to_space(&mut self, space: SpaceId, workspace_id: VirtualWorkspaceId) {
+        if !self.virtual_workspace_manager.is_global() {
+            return;
+        }
+        let Some(owner) = self.virtual_workspace_manager.workspace_space(workspace_id) else {
+            return;
+        };
+        if owner == space {
+            return;
+        }
+
+        let visible_on_owner =
+            self.virtual_workspace_manager.active_workspace(owner) == Some(workspace_id);
+        let current = self.virtual_workspace_manager.active_workspace(space);
+        self.virtual_workspace_manager.transfer_workspace(workspace_id, space);
+        if visible_on_owner && let Some(current) = current {
+            self.virtual_workspace_manager.transfer_workspace(current, owner);
+            self.virtual_workspace_manager.set_active_workspace(owner, current);
+        }
+        self.rehome_workspace_layouts();
+
+        if visible_on_owner {
+            self.update_active_floating_windows(owner);
+            self.broadcast_workspace_changed(owner);
+            self.broadcast_windows_changed(owner);
+        }
+    }
+
+    fn rehome_workspace_layouts(&mut self) {
+        let manager = &self.virtual_workspace_manager;
+        self.workspace_layouts.rehome(|ws| manager.workspace_space(ws), &mut self.tree);
+    }
+
+    fn workspace_id_for_command(
+        &mut self,
+        space: SpaceId,
+        workspace: Option<usize>,
+    ) -> Option<VirtualWorkspaceId> {
+        match workspace {
+            Some(index) => self
+                .virtual_workspace_manager
+                .workspace_order(space)
+                .get(index)
+                .map(|(id, _)| *id),
+            None => self.virtual_workspace_manager.active_workspace(space),
         }
     }
 
@@ -1904,6 +3628,148 @@ impl LayoutEngine {
         self.virtual_workspace_manager.active_workspace_idx(space)
     }
 
+    /// Moves the active workspace of `source_space` to `target_space`, where it
+    /// becomes active. Windows, the layout tree and floating positions travel
+    /// with it; floating positions are rescaled to the target screen.
+    pub fn move_workspace_to_space(
+        &mut self,
+        source_space: SpaceId,
+        source_frame: CGRect,
+        target_space: SpaceId,
+        target_frame: CGRect,
+    ) -> EventResponse {
+        if source_space == target_space {
+            return EventResponse::default();
+        }
+        let _ = self.virtual_workspace_manager.list_workspaces(target_space);
+        let Some(workspace_id) = self.virtual_workspace_manager.active_workspace(source_space)
+        else {
+            return EventResponse::default();
+        };
+
+        // The source display must keep a workspace to show.
+        if self.virtual_workspace_manager.list_workspaces(source_space).len() <= 1 {
+            if let Err(e) = self.virtual_workspace_manager.create_workspace(source_space, None) {
+                warn!("Cannot move the only workspace off its display: {:?}", e);
+                return EventResponse::default();
+            }
+        }
+
+        self.carry_workspace(workspace_id, source_frame, target_space, target_frame);
+        self.virtual_workspace_manager.set_active_workspace(target_space, workspace_id);
+        self.finish_workspace_display_change(source_space, target_space);
+        self.refocus_workspace(target_space, workspace_id)
+    }
+
+    /// Exchanges the active workspaces of two spaces, carrying their windows,
+    /// layout trees and floating positions.
+    pub fn swap_workspace_between_spaces(
+        &mut self,
+        source_space: SpaceId,
+        source_frame: CGRect,
+        target_space: SpaceId,
+        target_frame: CGRect,
+    ) -> EventResponse {
+        if source_space == target_space {
+            return EventResponse::default();
+        }
+        let _ = self.virtual_workspace_manager.list_workspaces(source_space);
+        let _ = self.virtual_workspace_manager.list_workspaces(target_space);
+        let (Some(source_ws), Some(target_ws)) = (
+            self.virtual_workspace_manager.active_workspace(source_space),
+            self.virtual_workspace_manager.active_workspace(target_space),
+        ) else {
+            return EventResponse::default();
+        };
+
+        self.carry_workspace(source_ws, source_frame, target_space, target_frame);
+        self.carry_workspace(target_ws, target_frame, source_space, source_frame);
+        self.virtual_workspace_manager.set_active_workspace(target_space, source_ws);
+        self.virtual_workspace_manager.set_active_workspace(source_space, target_ws);
+        self.finish_workspace_display_change(source_space, target_space);
+        self.refocus_workspace(source_space, target_ws)
+    }
+
+    /// The window a workspace command acts on: the one with index `window_id`,
+    /// or the focused window.
+    fn command_window(&self, space: SpaceId, window_id: Option<u32>) -> Option<WindowId> {
+        match window_id {
+            Some(idx) => self.virtual_workspace_manager.find_window_by_idx(space, idx),
+            None => self.focused_window,
+        }
+    }
+
+    /// Switches `space` to the workspace `window` was just moved to, with the
+    /// window focused.
+    fn follow_window_to_workspace(
+        &mut self,
+        space: SpaceId,
+        window: WindowId,
+        workspace_id: VirtualWorkspaceId,
+    ) -> EventResponse {
+        let window_space =
+            self.virtual_workspace_manager.workspace_space(workspace_id).unwrap_or(space);
+        if self.virtual_workspace_manager.workspace_for_window(window_space, window)
+            != Some(workspace_id)
+        {
+            return EventResponse::default();
+        }
+        self.virtual_workspace_manager.set_last_focused_window(
+            window_space,
+            workspace_id,
+            Some(window),
+        );
+        if self.virtual_workspace_manager.active_workspace(space) == Some(workspace_id) {
+            return EventResponse {
+                focus_window: Some(window),
+                raise_windows: vec![],
+            };
+        }
+        let Some(workspace_index) = self
+            .virtual_workspace_manager
+            .workspace_order(space)
+            .iter()
+            .position(|(id, _)| *id == workspace_id)
+        else {
+            return EventResponse::default();
+        };
+        let switch = LayoutCommand::SwitchToWorkspace(workspace_index);
+        self.apply_virtual_workspace_command(space, &switch)
+    }
+
+    fn carry_workspace(
+        &mut self,
+        workspace_id: VirtualWorkspaceId,
+        from_frame: CGRect,
+        to_space: SpaceId,
+        to_frame: CGRect,
+    ) {
+        self.virtual_workspace_manager.transfer_workspace(workspace_id, to_space);
+        self.virtual_workspace_manager.rescale_floating_positions(
+            to_space,
+            workspace_id,
+            from_frame,
+            to_frame,
+        );
+        self.rehome_workspace_layouts();
+        // Re-key the tree for the new screen size; tiled frames follow from it.
+        self.workspace_layouts.ensure_active_for_space(
+            to_space,
+            to_frame.size,
+            [workspace_id],
+            &mut self.tree,
+        );
+    }
+
+    fn finish_workspace_display_change(&mut self, source_space: SpaceId, target_space: SpaceId) {
+        for space in [source_space, target_space] {
+            self.reconcile_workspaces(space);
+            self.update_active_floating_windows(space);
+            self.broadcast_workspace_changed(space);
+            self.broadcast_windows_changed(space);
+        }
+    }
+
     pub fn move_window_to_space(
         &mut self,
         source_space: SpaceId,
@@ -2044,6 +3910,49 @@ impl LayoutEngine {
         self.floating.is_floating(window_id)
     }
 
+    pub fn focused_window(&self) -> Option<WindowId> { self.focused_window }
+
+    /// The space and workspace of a managed window.
+    pub fn window_location(&self, window_id: WindowId) -> Option<(SpaceId, VirtualWorkspaceId)> {
+        self.virtual_workspace_manager.locate_window(window_id)
+    }
+
+    /// Makes `window_id` the window that focus-relative commands act on,
+    /// without touching focus history. Used to run a command against windows
+    /// picked by criteria. Returns the selection it replaced in the window's
+    /// layout, to be put back with [`Self::restore_selection`].
+    pub fn target_window(&mut self, window_id: WindowId) -> Option<TargetedSelection> {
+        let (space, workspace_id) = self.window_location(window_id)?;
+        let focused = self.focused_window.replace(window_id);
+        let layout = self.workspace_layouts.active(space, workspace_id);
+        let previous = layout.and_then(|layout| self.tree.selected_window(layout));
+        if !self.floating.is_floating(window_id)
+            && let Some(layout) = layout
+        {
+            let _ = self.tree.select_window(layout, window_id);
+        }
+        Some(TargetedSelection { focused, layout, window: previous })
+    }
+
+    /// Puts back the focus and selection replaced by [`Self::target_window`],
+    /// as far as those windows are still around.
+    pub fn restore_selection(&mut self, saved: TargetedSelection) {
+        self.focused_window = saved.focused.filter(|wid| self.window_location(*wid).is_some());
+        if let TargetedSelection { layout: Some(layout), window: Some(wid), .. } = saved
+            && self.tree.contains_window(layout, wid)
+        {
+            let _ = self.tree.select_window(layout, wid);
+        }
+    }
+
+    pub fn window_marks(&self, window_id: WindowId) -> Vec<String> {
+        self.marks
+            .iter()
+            .filter(|(_, marked)| **marked == window_id)
+            .map(|(mark, _)| mark.clone())
+            .collect()
+    }
+
     fn update_active_floating_windows(&mut self, space: SpaceId) {
         let windows_in_workspace =
             self.virtual_workspace_manager.windows_in_active_workspace(space);
@@ -2155,7 +4064,11 @@ mod tests {
 
     use super::*;
     use crate::common::collections::HashMap;
-    use crate::common::config::{LayoutSettings, VirtualWorkspaceSettings};
+    use crate::common::config::{
+        AppWorkspaceRule, GapSettings, HorizontalPlacement, LayoutSettings, TemplateNode,
+        VerticalPlacement, VirtualWorkspaceSettings, WindowCriteria,
+    };
+    use crate::layout_engine::LayoutKind;
 
     fn test_engine() -> LayoutEngine {
         LayoutEngine::new(
@@ -2202,4 +4115,611 @@ mod tests {
             None
         );
     }
+
+    #[test]
+    fn swap_workspaces_finds_names_in_the_current_list() {
+        let mut engine = test_engine();
+        let space = SpaceId::new(1);
+        let _ = engine.handle_event(LayoutEvent::SpaceExposed(space, CGSize::new(1000.0, 800.0)));
+        let editor = WindowId::new(1, 1);
+        let _ = engine.handle_event(LayoutEvent::WindowsOnScreenUpdated(
+            space,
+            1,
+            vec![(editor, None, None, None)],
+            None,
+        ));
+        let run = |engine: &mut LayoutEngine, command: LayoutCommand| {
+            let _ = engine.handle_virtual_workspace_command(space, &command);
+        };
+        run(&mut engine, LayoutCommand::RenameWorkspace {
+            workspace: Some(2),
+            name: "web".into(),
+        });
+        run(&mut engine, LayoutCommand::MoveWorkspaceToIndex { workspace: Some(2), index: 1 });
+        let web = engine.virtual_workspace_manager.workspace_order(space)[1].0;
+
+        run(&mut engine, LayoutCommand::SwapWorkspaces {
+            workspace: None,
+            other: WorkspaceSelector::Name("web".into()),
+        });
+        let manager = &engine.virtual_workspace_manager;
+        assert_eq!(manager.workspace_for_window(space, editor), Some(web));
+    }
+
+    #[test]
+    fn workspaces_move_and_swap_between_displays() {
+        let mut engine = test_engine();
+        let (left, right) = (SpaceId::new(1), SpaceId::new(2));
+        let left_frame = CGRect::new(CGPoint::new(0.0, 0.0), CGSize::new(1000.0, 800.0));
+        let right_frame = CGRect::new(CGPoint::new(1000.0, 0.0), CGSize::new(2000.0, 1200.0));
+        let (editor, term) = (WindowId::new(1, 1), WindowId::new(2, 1));
+        for (space, frame, wid) in [(left, left_frame, editor), (right, right_frame, term)] {
+            let _ = engine.handle_event(LayoutEvent::SpaceExposed(space, frame.size));
+            let _ = engine.handle_event(LayoutEvent::WindowsOnScreenUpdated(
+                space,
+                wid.pid,
+                vec![(wid, None, None, None)],
+                None,
+            ));
+        }
+        let (editor_ws, term_ws) = (
+            engine.active_workspace(left).unwrap(),
+            engine.active_workspace(right).unwrap(),
+        );
+        let tiled = |engine: &LayoutEngine, space, workspace| {
+            let layout = engine.workspace_layouts.active(space, workspace).unwrap();
+            engine.tree.visible_windows_in_layout(layout)
+        };
+        let located = |engine: &LayoutEngine, space, wid| {
+            engine.virtual_workspace_manager.workspace_for_window(space, wid)
+        };
+
+        let response = engine.move_workspace_to_space(left, left_frame, right, right_frame);
+        assert_eq!(response.focus_window, Some(editor));
+        assert_eq!(located(&engine, right, editor), Some(editor_ws));
+        assert_eq!(engine.active_workspace(right), Some(editor_ws));
+        assert_eq!(tiled(&engine, right, editor_ws), vec![editor]);
+        // The left display keeps a workspace of its own to show.
+        let left_ws = engine.active_workspace(left).unwrap();
+        assert!(![editor_ws, term_ws].contains(&left_ws));
+        assert_eq!(located(&engine, right, term), Some(term_ws));
+
+        let _ = engine.swap_workspace_between_spaces(right, right_frame, left, left_frame);
+        assert_eq!(located(&engine, left, editor), Some(editor_ws));
+        assert_eq!(engine.active_workspace(left), Some(editor_ws));
+        assert_eq!(engine.active_workspace(right), Some(left_ws));
+        assert_eq!(tiled(&engine, left, editor_ws), vec![editor]);
+        assert_eq!(engine.windows_in_active_workspace(right), Vec::<WindowId>::new());
+    }
+
+    #[test]
+    fn moved_windows_follow_to_existing_and_new_workspaces() {
+        let mut engine = test_engine();
+        let space = SpaceId::new(1);
+        let _ = engine.handle_event(LayoutEvent::SpaceExposed(space, CGSize::new(1000.0, 800.0)));
+        let (editor, notes) = (WindowId::new(1, 1), WindowId::new(1, 2));
+        let _ = engine.handle_event(LayoutEvent::WindowsOnScreenUpdated(
+            space,
+            1,
+            vec![(editor, None, None, None), (notes, None, None, None)],
+            None,
+        ));
+        let _ = engine.handle_event(LayoutEvent::WindowFocused(space, editor));
+        let first = engine.active_workspace(space).unwrap();
+        let second = engine.virtual_workspace_manager.workspace_order(space)[1].0;
+
+        let response = engine.handle_virtual_workspace_command(
+            space,
+            &LayoutCommand::MoveWindowToWorkspace {
+                workspace: 1,
+                window_id: None,
+                follow: true,
+            },
+        );
+        assert_eq!(response.focus_window, Some(editor));
+        assert_eq!(engine.active_workspace(space), Some(second));
+        let located = |engine: &LayoutEngine, wid| {
+            engine.virtual_workspace_manager.workspace_for_window(space, wid).unwrap()
+        };
+        assert_eq!(located(&engine, editor), second);
+
+        // Without follow, the window leaves and the display stays put.
+        let count = engine.virtual_workspace_manager.workspace_order(space).len();
+        let _ = engine.handle_virtual_workspace_command(
+            space,
+            &LayoutCommand::MoveWindowToNewWorkspace { window_id: None, follow: false },
+        );
+        let order = engine.virtual_workspace_manager.workspace_order(space);
+        assert_eq!(order.len(), count + 1);
+        let created = located(&engine, editor);
+        assert!(![first, second].contains(&created));
+        assert_eq!(engine.active_workspace(space), Some(second));
+
+        // A window picked by index is followed to its new workspace.
+        let response = engine.handle_virtual_workspace_command(
+            space,
+            &LayoutCommand::MoveWindowToNewWorkspace {
+                window_id: Some(notes.idx.get()),
+                follow: true,
+            },
+        );
+        let notes_ws = located(&engine, notes);
+        assert!(![first, second, created].contains(&notes_ws));
+        assert_eq!(engine.active_workspace(space), Some(notes_ws));
+        assert_eq!(response.focus_window, Some(notes));
+    }
+
+    #[test]
+    fn focus_history_switches_workspace_only_for_hidden_targets() {
+        let mut engine = test_engine();
+        let space = SpaceId::new(1);
+        let _ = engine.handle_event(LayoutEvent::SpaceExposed(space, CGSize::new(1000.0, 800.0)));
+        let (editor, notes, term) = (WindowId::new(1, 1), WindowId::new(1, 2), WindowId::new(2, 1));
+        let _ = engine.handle_event(LayoutEvent::WindowsOnScreenUpdated(
+            space,
+            1,
+            vec![(editor, None, None, None), (notes, None, None, None)],
+            None,
+        ));
+        let _ = engine.handle_event(LayoutEvent::WindowFocused(space, editor));
+        let _ = engine.handle_event(LayoutEvent::WindowFocused(space, notes));
+        assert!(!engine.focus_history_switches_workspace(&LayoutCommand::FocusBack));
+        assert!(!engine.focus_history_switches_workspace(&LayoutCommand::FocusMruNext));
+
+        let switch = LayoutCommand::SwitchToWorkspace(1);
+        let _ = engine.handle_virtual_workspace_command(space, &switch);
+        let _ = engine.handle_event(LayoutEvent::WindowsOnScreenUpdated(
+            space,
+            2,
+            vec![(term, None, None, None)],
+            None,
+        ));
+        let _ = engine.handle_event(LayoutEvent::WindowFocused(space, term));
+        assert!(engine.focus_history_switches_workspace(&LayoutCommand::FocusBack));
+        assert!(engine.focus_history_switches_workspace(&LayoutCommand::FocusMruPrev));
+        assert!(!engine.focus_history_switches_workspace(&LayoutCommand::NextWindow));
+    }
+
+    #[test]
+    fn marks_focus_and_swap_windows_across_workspaces() {
+        let mut engine = test_engine();
+        let space = SpaceId::new(1);
+        let _ = engine.handle_event(LayoutEvent::SpaceExposed(space, CGSize::new(1000.0, 800.0)));
+        let (editor, notes, term) = (WindowId::new(1, 1), WindowId::new(1, 2), WindowId::new(2, 1));
+        let _ = engine.handle_event(LayoutEvent::WindowsOnScreenUpdated(
+            space,
+            1,
+            vec![(editor, None, None, None), (notes, None, None, None)],
+            None,
+        ));
+        let first = engine.active_workspace(space).unwrap();
+        let mark = |engine: &mut LayoutEngine, wid, command| {
+            let _ = engine.handle_event(LayoutEvent::WindowFocused(space, wid));
+            let _ = engine.handle_command(Some(space), &[space], &HashMap::default(), command);
+        };
+        mark(&mut engine, editor, LayoutCommand::Mark("e".into()));
+        mark(&mut engine, notes, LayoutCommand::Mark("n".into()));
+        mark(&mut engine, notes, LayoutCommand::Unmark);
+        assert_eq!(engine.window_marks(editor), vec!["e".to_string()]);
+        assert!(engine.window_marks(notes).is_empty());
+
+        let switch = LayoutCommand::SwitchToWorkspace;
+        let _ = engine.handle_virtual_workspace_command(space, &switch(1));
+        let _ = engine.handle_event(LayoutEvent::WindowsOnScreenUpdated(
+            space,
+            2,
+            vec![(term, None, None, None)],
+            None,
+        ));
+        let second = engine.active_workspace(space).unwrap();
+        let _ = engine.handle_event(LayoutEvent::WindowFocused(space, term));
+
+        // Swapping trades workspaces along with places in the tree.
+        let swap = LayoutCommand::SwapWithMark("e".into());
+        let _ = engine.handle_virtual_workspace_command(space, &swap);
+        let manager = &engine.virtual_workspace_manager;
+        assert_eq!(manager.workspace_for_window(space, editor), Some(second));
+        assert_eq!(manager.workspace_for_window(space, term), Some(first));
+
+        // Focusing a mark shows the workspace the marked window is on.
+        let _ = engine.handle_virtual_workspace_command(space, &switch(0));
+        let focus = LayoutCommand::FocusMark("e".into());
+        let response = engine.handle_virtual_workspace_command(space, &focus);
+        assert_eq!(response.focus_window, Some(editor));
+        assert_eq!(engine.active_workspace(space), Some(second));
+        let missing = LayoutCommand::FocusMark("n".into());
+        assert_eq!(engine.handle_virtual_workspace_command(space, &missing).focus_window, None);
+    }
+
+    #[test]
+    fn commands_for_a_window_run_on_its_own_workspace() {
+        let mut engine = test_engine();
+        let space = SpaceId::new(1);
+        let _ = engine.handle_event(LayoutEvent::SpaceExposed(space, CGSize::new(1000.0, 800.0)));
+        let hidden: Vec<WindowId> = (1..=2).map(|idx| WindowId::new(1, idx)).collect();
+        let _ = engine.handle_event(LayoutEvent::WindowsOnScreenUpdated(
+            space,
+            1,
+            hidden.iter().map(|&wid| (wid, None, None, None)).collect(),
+            None,
+        ));
+        let hidden_workspace = engine.active_workspace(space).unwrap();
+        let switch = LayoutCommand::SwitchToWorkspace(1);
+        let _ = engine.handle_virtual_workspace_command(space, &switch);
+        let shown: Vec<WindowId> = (1..=2).map(|idx| WindowId::new(2, idx)).collect();
+        let _ = engine.handle_event(LayoutEvent::WindowsOnScreenUpdated(
+            space,
+            2,
+            shown.iter().map(|&wid| (wid, None, None, None)).collect(),
+            None,
+        ));
+        let shown_workspace = engine.active_workspace(space).unwrap();
+        assert_ne!(hidden_workspace, shown_workspace);
+        let draw = |engine: &LayoutEngine, workspace| {
+            let layout = engine.workspace_layouts.active(space, workspace).unwrap();
+            engine.tree.draw_tree(layout)
+        };
+        let (hidden_tree, shown_tree) =
+            (draw(&engine, hidden_workspace), draw(&engine, shown_workspace));
+        let layout = engine.workspace_layouts.active(space, hidden_workspace).unwrap();
+        let _ = engine.tree.select_window(layout, hidden[1]);
+        engine.focused_window = None;
+
+        let saved = engine.target_window(hidden[0]).unwrap();
+        let _ = engine.handle_command_for_window(
+            hidden[0],
+            &[space],
+            &HashMap::default(),
+            LayoutCommand::ToggleOrientation,
+        );
+        engine.restore_selection(saved);
+
+        assert_ne!(draw(&engine, hidden_workspace), hidden_tree);
+        assert_eq!(draw(&engine, shown_workspace), shown_tree);
+        assert_eq!(engine.tree.selected_window(layout), Some(hidden[1]));
+        assert_eq!(engine.focused_window(), None);
+    }
+
+    #[test]
+    fn app_rule_actions_stack_fullscreen_hold_focus_and_route_displays() {
+        let rule = |app_id: &str| AppWorkspaceRule {
+            app_id: Some(app_id.into()),
+            ..Default::default()
+        };
+        let settings = VirtualWorkspaceSettings {
+            app_rules: vec![
+                AppWorkspaceRule {
+                    stack_with: Some("com.example.editor".into()),
+                    ..rule("com.example.term")
+                },
+                AppWorkspaceRule {
+                    fullscreen: true,
+                    ..rule("com.example.video")
+                },
+                AppWorkspaceRule {
+                    focus: false,
+                    display: Some(DisplaySelector::Index(1)),
+                    ..rule("com.example.chat")
+                },
+            ],
+            ..Default::default()
+        };
+        let mut engine = LayoutEngine::new(&settings, &LayoutSettings::default(), None);
+        let space = SpaceId::new(1);
+        let _ = engine.handle_event(LayoutEvent::SpaceExposed(space, CGSize::new(1000.0, 800.0)));
+        let open = |engine: &mut LayoutEngine, pid, app_id: &str| {
+            let wid = WindowId::new(pid, 1);
+            let _ = engine.handle_event(LayoutEvent::WindowsOnScreenUpdated(
+                space,
+                pid,
+                vec![(wid, Some("window".into()), None, None)],
+                Some(AppInfo {
+                    bundle_id: Some(app_id.into()),
+                    localized_name: None,
+                }),
+            ));
+            wid
+        };
+        let editor = open(&mut engine, 1, "com.example.editor");
+        let term = open(&mut engine, 2, "com.example.term");
+        let workspace = engine.active_workspace(space).unwrap();
+        let layout = engine.workspace_layouts.active(space, workspace).unwrap();
+        assert!(engine.tree.select_window(layout, term));
+        assert!(engine.tree.parent_of_selection_is_stacked(layout));
+        assert!(engine.tree.select_window(layout, editor));
+        assert!(engine.tree.parent_of_selection_is_stacked(layout));
+
+        let video = open(&mut engine, 3, "com.example.video");
+        let screen = CGRect::new(CGPoint::new(0.0, 0.0), CGSize::new(1000.0, 800.0));
+        let frames = engine.tree.calculate_layout(
+            layout,
+            screen,
+            0.0,
+            &GapSettings::default(),
+            0.0,
+            HorizontalPlacement::default(),
+            VerticalPlacement::default(),
+        );
+        assert!(frames.contains(&(video, screen)));
+
+        let _ = engine.handle_event(LayoutEvent::WindowFocused(space, editor));
+        let chat = open(&mut engine, 4, "com.example.chat");
+        let response = engine.handle_event(LayoutEvent::WindowFocused(space, chat));
+        assert_eq!(response.focus_window, Some(editor));
+        assert_eq!(engine.focused_window(), Some(editor));
+        assert_eq!(engine.take_rule_display_moves(), vec![(chat, DisplaySelector::Index(1))]);
+        assert!(engine.take_rule_display_moves().is_empty());
+    }
+
+    #[test]
+    fn restored_marks_wait_for_their_window_to_be_matched() {
+        let (editor, term) = (WindowId::new(1, 1), WindowId::new(2, 1));
+        let mut engine = test_engine();
+        engine.marks.insert("e".into(), editor);
+        engine.marks.insert("t".into(), term);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("layout.ron");
+        engine.save(path.clone()).unwrap();
+
+        let mut engine = LayoutEngine::load(path.clone()).unwrap();
+        assert!(engine.window_marks(editor).is_empty());
+        assert!(engine.window_marks(term).is_empty());
+
+        // Saving before the windows are matched keeps their marks.
+        engine.save(path.clone()).unwrap();
+        let mut engine = LayoutEngine::load(path).unwrap();
+
+        let relaunched = WindowId::new(7, 1);
+        engine.replace_window(editor, relaunched);
+        engine.replace_window(term, term);
+        assert_eq!(engine.window_marks(relaunched), vec!["e".to_string()]);
+        assert_eq!(engine.window_marks(term), vec!["t".to_string()]);
+    }
+
+    #[test]
+    fn placeholders_are_not_raised_focused_framed_or_moved() {
+        let leaf = |app_id: &str| SnapshotNode::Window {
+            window: Slot::Window(SnapshotWindow {
+                app_id: Some(app_id.into()),
+                title: "window".into(),
+            }),
+            weight: 1.0,
+        };
+        let mut snapshots = LayoutSnapshots::default();
+        snapshots.insert("review".into(), LayoutSnapshot {
+            root: SnapshotNode::Container {
+                kind: LayoutKind::Horizontal,
+                weight: 1.0,
+                children: vec![leaf("com.example.editor"), leaf("com.example.term")],
+            },
+        });
+        let mut engine = test_engine();
+        engine.set_layout_snapshots(snapshots);
+        let space = SpaceId::new(1);
+        let screen = CGRect::new(CGPoint::new(0.0, 0.0), CGSize::new(1000.0, 800.0));
+        let _ = engine.handle_event(LayoutEvent::SpaceExposed(space, screen.size));
+        let editor = WindowId::new(1, 1);
+        let _ = engine.handle_event(LayoutEvent::WindowsOnScreenUpdated(
+            space,
+            editor.pid,
+            vec![(editor, Some("window".into()), None, None)],
+            Some(AppInfo {
+                bundle_id: Some("com.example.editor".into()),
+                localized_name: None,
+            }),
+        ));
+        let window = AppRuleQuery {
+            app_id: Some("com.example.editor".into()),
+            title: Some("window".into()),
+            ..Default::default()
+        };
+        let windows = BTreeMap::from_iter([(editor, window)]);
+        let _ = engine.load_layout_snapshot(space, "review", &windows).unwrap();
+        assert!(engine.has_placeholders());
+        let workspace = engine.active_workspace(space).unwrap();
+        let layout = engine.workspace_layouts.active(space, workspace).unwrap();
+        let _ = engine.tree.select_window(layout, editor);
+
+        let real = |response: &EventResponse| {
+            response.raise_windows.iter().chain(&response.focus_window).all(|w| !is_placeholder(*w))
+        };
+        for command in [LayoutCommand::NextWindow, LayoutCommand::MoveFocus(Direction::Right)] {
+            let response =
+                engine.handle_command(Some(space), &[space], &HashMap::default(), command);
+            assert!(real(&response));
+            assert_eq!(engine.tree.selected_window(layout), Some(editor));
+        }
+        for index in [1, 0] {
+            let switch = LayoutCommand::SwitchToWorkspace(index);
+            let response = engine.handle_virtual_workspace_command(space, &switch);
+            assert!(real(&response));
+        }
+
+        let frames = engine.calculate_layout(
+            space,
+            screen,
+            &GapSettings::default(),
+            0.0,
+            HorizontalPlacement::default(),
+            VerticalPlacement::default(),
+        );
+        assert_eq!(frames.iter().map(|(wid, _)| *wid).collect::<Vec<_>>(), vec![editor]);
+
+        let windows = |engine: &LayoutEngine| {
+            let tree = engine.tree.snapshot_layout(layout).unwrap();
+            tree.windows().into_iter().copied().collect::<Vec<_>>()
+        };
+        let placeholder = windows(&engine)[1];
+        for command in [
+            LayoutCommand::MoveNode(Direction::Right),
+            LayoutCommand::SwapWindows(editor, placeholder),
+        ] {
+            let _ = engine.handle_command(Some(space), &[space], &HashMap::default(), command);
+            assert_eq!(windows(&engine), vec![editor, placeholder]);
+        }
+        let stack = LayoutCommand::ToggleStack;
+        let _ = engine.handle_command(Some(space), &[space], &HashMap::default(), stack);
+        let groups = engine.collect_group_containers_in_selection_path(
+            space,
+            screen,
+            &GapSettings::default(),
+            0.0,
+            HorizontalPlacement::default(),
+            VerticalPlacement::default(),
+        );
+        assert_eq!(groups.len(), 1);
+        assert_eq!((groups[0].total_count, &groups[0].window_ids[..]), (1, &[editor][..]));
+
+        // Placeholders do not outlive the session they were loaded in.
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("layout.ron");
+        engine.save(path.clone()).unwrap();
+        let engine = LayoutEngine::load(path).unwrap();
+        assert!(!engine.has_placeholders());
+        assert_eq!(windows(&engine), vec![editor]);
+    }
+
+    #[test]
+    fn workspace_templates_place_windows_in_their_slots() {
+        let slot = |app_id: &str| TemplateNode::Window {
+            criteria: WindowCriteria {
+                app_id: Some(app_id.into()),
+                ..Default::default()
+            },
+            weight: 1.0,
+        };
+        let mut engine = test_engine();
+        engine.set_workspace_templates(vec![WorkspaceTemplate {
+            name: "review".into(),
+            workspaces: vec!["Main".into()],
+            layout: TemplateNode::Container {
+                kind: LayoutKind::Horizontal,
+                weight: 1.0,
+                children: vec![slot("com.example.editor"), slot("com.example.term")],
+            },
+        }]);
+        let space = SpaceId::new(1);
+        let _ = engine.handle_event(LayoutEvent::SpaceExposed(space, CGSize::new(1000.0, 800.0)));
+        assert!(engine.has_placeholders());
+
+        // The terminal opens first but still lands in the second slot.
+        let editor = WindowId::new(1, 1);
+        let term = WindowId::new(2, 1);
+        for (wid, app_id) in [(term, "com.example.term"), (editor, "com.example.editor")] {
+            let _ = engine.handle_event(LayoutEvent::WindowsOnScreenUpdated(
+                space,
+                wid.pid,
+                vec![(wid, Some("window".into()), None, None)],
+                Some(AppInfo {
+                    bundle_id: Some(app_id.into()),
+                    localized_name: None,
+                }),
+            ));
+        }
+
+        let workspace = engine.active_workspace(space).unwrap();
+        let layout = engine.workspace_layouts.active(space, workspace).unwrap();
+        let tree = engine.tree.snapshot_layout(layout).unwrap();
+        assert_eq!(tree.windows(), vec![&editor, &term]);
+        assert!(!engine.has_placeholders());
+    }
+
+    #[test]
+    fn undo_layout_reverts_structural_commands() {
+        let mut engine = test_engine();
+        let space = SpaceId::new(1);
+        let _ = engine.handle_event(LayoutEvent::SpaceExposed(space, CGSize::new(1000.0, 800.0)));
+        let windows: Vec<WindowId> = (1..=3).map(|idx| WindowId::new(1, idx)).collect();
+        let _ = engine.handle_event(LayoutEvent::WindowsOnScreenUpdated(
+            space,
+            1,
+            windows.iter().map(|&wid| (wid, None, None, None)).collect(),
+            None,
+        ));
+        let run = |engine: &mut LayoutEngine, command: LayoutCommand| {
+            let _ = engine.handle_command(Some(space), &[space], &HashMap::default(), command);
+        };
+        // Weights are rebuilt up to rounding, so compare container kind and
+        // window order.
+        let shape = |engine: &LayoutEngine| {
+            let workspace = engine.active_workspace(space).unwrap();
+            let layout = engine.workspace_layouts.active(space, workspace).unwrap();
+            let tree = engine.tree.snapshot_layout(layout).unwrap();
+            let kind = match &tree {
+                SnapshotNode::Container { kind, .. } => Some(*kind),
+                SnapshotNode::Window { .. } => None,
+            };
+            (kind, tree.windows().into_iter().copied().collect::<Vec<_>>())
+        };
+
+        let original = shape(&engine);
+        run(&mut engine, LayoutCommand::ToggleOrientation);
+        let toggled = shape(&engine);
+        assert_ne!(toggled, original);
+        // Selection changes leave nothing to undo.
+        run(&mut engine, LayoutCommand::Ascend);
+        run(&mut engine, LayoutCommand::UndoLayout);
+        assert_eq!(shape(&engine), original);
+        run(&mut engine, LayoutCommand::RedoLayout);
+        assert_eq!(shape(&engine), toggled);
+
+        // Windows closed since the change are left out when undoing it.
+        let _ = engine.handle_event(LayoutEvent::WindowRemoved(windows[1]));
+        run(&mut engine, LayoutCommand::UndoLayout);
+        assert_eq!(shape(&engine), (original.0, vec![windows[0], windows[2]]));
+        run(&mut engine, LayoutCommand::UndoLayout);
+        assert_eq!(shape(&engine), (original.0, vec![windows[0], windows[2]]));
+    }
+
+    #[test]
+    fn unversioned_state_loads_as_format_one() {
+        let state = test_engine().serialize_to_string();
+        let (version, body) = migration::split_header(&state);
+        assert_eq!(version, 1);
+        let engine = LayoutEngine::deserialize_versioned(version, body.to_string()).unwrap();
+        assert_eq!(engine.serialize_to_string(), state);
+
+        let newer = migration::LAYOUT_FORMAT_VERSION + 1;
+        assert!(LayoutEngine::deserialize_versioned(newer, state).is_err());
+    }
+
+    #[test]
+    fn undo_layout_restores_centered_master_state() {
+        let settings = LayoutSettings {
+            mode: crate::common::config::LayoutMode::CenteredMaster,
+            ..Default::default()
+        };
+        let mut engine = LayoutEngine::new(&VirtualWorkspaceSettings::default(), &settings, None);
+        let space = SpaceId::new(1);
+        let _ = engine.handle_event(LayoutEvent::SpaceExposed(space, CGSize::new(1000.0, 800.0)));
+        let windows: Vec<WindowId> = (1..=3).map(|idx| WindowId::new(1, idx)).collect();
+        let _ = engine.handle_event(LayoutEvent::WindowsOnScreenUpdated(
+            space,
+            1,
+            windows.iter().map(|&wid| (wid, None, None, None)).collect(),
+            None,
+        ));
+        let run = |engine: &mut LayoutEngine, command: LayoutCommand| {
+            let _ = engine.handle_command(Some(space), &[space], &HashMap::default(), command);
+        };
+        let extras = |engine: &LayoutEngine| {
+            let workspace = engine.active_workspace(space).unwrap();
+            let layout = engine.workspace_layouts.active(space, workspace).unwrap();
+            engine.tree.layout_extras(layout)
+        };
+
+        let original = extras(&engine);
+        run(&mut engine, LayoutCommand::ResizeWindowGrow);
+        let resized = extras(&engine);
+        assert_ne!(resized.master_ratio, original.master_ratio);
+        run(&mut engine, LayoutCommand::ToggleOrientation);
+        assert_ne!(extras(&engine).mirrored, resized.mirrored);
+
+        run(&mut engine, LayoutCommand::UndoLayout);
+        assert_eq!(extras(&engine), resized);
+        run(&mut engine, LayoutCommand::UndoLayout);
+        assert_eq!(extras(&engine), original);
+        run(&mut engine, LayoutCommand::RedoLayout);
+        assert_eq!(extras(&engine), resized);
+    }
 }
@@ -61,6 +61,16 @@ impl FloatingManager {
         self.remove_active_entries(window_id);
     }
 
+    pub(crate) fn replace_window(&mut self, old: WindowId, new: WindowId) {
+        if self.floating_windows.remove(&old) {
+            self.floating_windows.insert(new);
+        }
+        self.remove_active_entries(old);
+        if self.last_floating_focus == Some(old) {
+            self.last_floating_focus = Some(new);
+        }
+    }
+
     pub(crate) fn active_flat(&self, space: SpaceId) -> Vec<WindowId> {
         self.active_floating_windows
             .get(&space)
@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+
+use crate::actor::app::{WindowId, pid_t};
+
+const FOCUS_HISTORY_LIMIT: usize = 64;
+
+/// Most-recently-used focus order across every workspace and display.
+#[derive(Debug, Default)]
+pub(crate) struct FocusHistory {
+    windows: VecDeque<WindowId>,
+    cycle: Option<MruCycle>,
+}
+
+/// An in-progress `focus_mru_next`/`focus_mru_prev` cycle. The order is frozen
+/// when the cycle starts so stepping through it does not reshuffle it.
+#[derive(Debug)]
+struct MruCycle {
+    candidates: Vec<WindowId>,
+    index: usize,
+}
+
+impl FocusHistory {
+    /// Moves a window to the front of the history. Focus changes made while
+    /// cycling are previews and are only recorded on commit. Focusing any
+    /// other window commits the cycle first, since a cycle run over IPC never
+    /// sees the hotkey's modifiers released.
+    pub(crate) fn record(&mut self, wid: WindowId) {
+        if let Some(cycle) = &self.cycle {
+            if cycle.candidates.get(cycle.index) == Some(&wid) {
+                return;
+            }
+            self.commit_cycle();
+        }
+        self.push_front(wid);
+    }
+
+    pub(crate) fn remove(&mut self, wid: WindowId) { self.retain(|w| w != wid); }
+
+    pub(crate) fn remove_app(&mut self, pid: pid_t) { self.retain(|w| w.pid != pid); }
+
+    /// The most recently focused window other than `current`.
+    pub(crate) fn previous(&self, current: Option<WindowId>) -> Option<WindowId> {
+        self.windows.iter().copied().find(|&w| Some(w) != current)
+    }
+
+    /// Steps the MRU cycle, starting one from `current` if none is running.
+    pub(crate) fn cycle(&mut self, current: Option<WindowId>, forward: bool) -> Option<WindowId> {
+        let cycle = self.cycle.get_or_insert_with(|| MruCycle {
+            candidates: cycle_candidates(&self.windows, current),
+            index: 0,
+        });
+        cycle.index = cycle_step(cycle.index, cycle.candidates.len(), forward)?;
+        Some(cycle.candidates[cycle.index])
+    }
+
+    /// The window [`Self::cycle`] would step to, without stepping.
+    pub(crate) fn peek_cycle(&self, current: Option<WindowId>, forward: bool) -> Option<WindowId> {
+        let fresh;
+        let (candidates, index) = match &self.cycle {
+            Some(cycle) => (&cycle.candidates, cycle.index),
+            None => {
+                fresh = cycle_candidates(&self.windows, current);
+                (&fresh, 0)
+            }
+        };
+        Some(candidates[cycle_step(index, candidates.len(), forward)?])
+    }
+
+    /// Ends the MRU cycle, recording the window it landed on.
+    pub(crate) fn commit_cycle(&mut self) -> Option<WindowId> {
+        let cycle = self.cycle.take()?;
+        let wid = cycle.candidates.get(cycle.index).copied()?;
+        self.push_front(wid);
+        Some(wid)
+    }
+
+    fn push_front(&mut self, wid: WindowId) {
+        self.windows.retain(|&w| w != wid);
+        self.windows.push_front(wid);
+        self.windows.truncate(FOCUS_HISTORY_LIMIT);
+    }
+
+    fn retain(&mut self, keep: impl Fn(WindowId) -> bool) {
+        self.windows.retain(|&w| keep(w));
+        if let Some(cycle) = &mut self.cycle {
+            let selected = cycle.candidates.get(cycle.index).copied();
+            cycle.candidates.retain(|&w| keep(w));
+            cycle.index = selected
+                .and_then(|sel| cycle.candidates.iter().position(|&w| w == sel))
+                .unwrap_or(0);
+        }
+    }
+}
+
+/// The order a new MRU cycle steps through, starting from `current`.
+fn cycle_candidates(windows: &VecDeque<WindowId>, current: Option<WindowId>) -> Vec<WindowId> {
+    let mut candidates: Vec<WindowId> = current.into_iter().collect();
+    candidates.extend(windows.iter().copied().filter(|&w| Some(w) != current));
+    candidates
+}
+
+/// The index one step on from `index` in a cycle of `len` windows, or `None`
+/// if there is nothing to step to.
+fn cycle_step(index: usize, len: usize, forward: bool) -> Option<usize> {
+    if len < 2 {
+        return None;
+    }
+    Some(if forward { (index + 1) % len } else { (index + len - 1) % len })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn previous_skips_the_current_window() {
+        let mut history = FocusHistory::default();
+        let (a, b, c) = (WindowId::new(1, 1), WindowId::new(1, 2), WindowId::new(2, 1));
+        history.record(a);
+        history.record(b);
+        history.record(c);
+        assert_eq!(history.previous(Some(c)), Some(b));
+
+        history.remove(b);
+        assert_eq!(history.previous(Some(c)), Some(a));
+        history.remove_app(1);
+        assert_eq!(history.previous(Some(c)), None);
+    }
+
+    #[test]
+    fn cycle_keeps_order_until_committed() {
+        let mut history = FocusHistory::default();
+        let (a, b, c) = (WindowId::new(1, 1), WindowId::new(1, 2), WindowId::new(2, 1));
+        history.record(a);
+        history.record(b);
+        history.record(c);
+
+        assert_eq!(history.peek_cycle(Some(c), false), Some(a));
+        assert_eq!(history.cycle(Some(c), true), Some(b));
+        history.record(b);
+        assert_eq!(history.peek_cycle(Some(b), true), Some(a));
+        assert_eq!(history.cycle(Some(b), true), Some(a));
+        assert_eq!(history.cycle(Some(a), true), Some(c));
+        assert_eq!(history.cycle(Some(c), false), Some(a));
+
+        assert_eq!(history.commit_cycle(), Some(a));
+        assert_eq!(history.previous(Some(a)), Some(c));
+        assert_eq!(history.commit_cycle(), None);
+    }
+
+    #[test]
+    fn focusing_another_window_ends_an_uncommitted_cycle() {
+        let mut history = FocusHistory::default();
+        let (a, b, c) = (WindowId::new(1, 1), WindowId::new(1, 2), WindowId::new(2, 1));
+        history.record(a);
+        history.record(b);
+
+        // focus_mru_next over IPC: no modifier release ever commits it.
+        assert_eq!(history.cycle(Some(b), true), Some(a));
+        history.record(a);
+        history.record(c);
+
+        assert_eq!(history.commit_cycle(), None);
+        assert_eq!(history.previous(Some(c)), Some(a));
+        assert_eq!(history.previous(None), Some(c));
+    }
+}
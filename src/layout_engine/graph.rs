@@ -65,6 +65,8 @@ pub enum LayoutKind {
     Vertical,
     HorizontalStack,
     VerticalStack,
+    HorizontalAccordion,
+    VerticalAccordion,
 }
 
 impl LayoutKind {
@@ -82,8 +84,27 @@ impl LayoutKind {
         }
     }
 
+    pub fn accordion(orientation: Orientation) -> Self {
+        match orientation {
+            Orientation::Horizontal => LayoutKind::HorizontalAccordion,
+            Orientation::Vertical => LayoutKind::VerticalAccordion,
+        }
+    }
+
+    /// Whether children overlap and are navigated one at a time. This covers
+    /// both stacks and accordions.
     pub fn is_stacked(self) -> bool {
-        matches!(self, LayoutKind::HorizontalStack | LayoutKind::VerticalStack)
+        matches!(
+            self,
+            LayoutKind::HorizontalStack
+                | LayoutKind::VerticalStack
+                | LayoutKind::HorizontalAccordion
+                | LayoutKind::VerticalAccordion
+        )
+    }
+
+    pub fn is_accordion(self) -> bool {
+        matches!(self, LayoutKind::HorizontalAccordion | LayoutKind::VerticalAccordion)
     }
 
     pub fn orientation(self) -> Orientation {
@@ -93,10 +114,10 @@ impl LayoutKind {
             Vertical => Orientation::Vertical,
             HorizontalStack => Orientation::Horizontal,
             VerticalStack => Orientation::Vertical,
+            HorizontalAccordion => Orientation::Horizontal,
+            VerticalAccordion => Orientation::Vertical,
         }
     }
 
-    pub fn is_group(self) -> bool {
-        matches!(self, LayoutKind::HorizontalStack | LayoutKind::VerticalStack)
-    }
+    pub fn is_group(self) -> bool { self.is_stacked() }
 }
@@ -0,0 +1,104 @@
+use std::collections::VecDeque;
+
+use crate::actor::app::WindowId;
+use crate::layout_engine::LayoutExtras;
+use crate::layout_engine::snapshot::SnapshotNode;
+use crate::model::VirtualWorkspaceId;
+
+const LAYOUT_HISTORY_LIMIT: usize = 32;
+
+/// The tiled tree of one workspace, or `None` if it had no tiled windows,
+/// along with the layout state the tree does not describe.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct WorkspaceTree {
+    pub(crate) workspace: VirtualWorkspaceId,
+    pub(crate) tree: Option<SnapshotNode<WindowId>>,
+    pub(crate) extras: LayoutExtras,
+}
+
+/// Undo and redo stacks for commands that change the shape of tiled trees.
+/// Each step holds the trees of the workspaces a command changed, as they were
+/// on the other side of it.
+#[derive(Debug, Default)]
+pub(crate) struct LayoutHistory {
+    undo: VecDeque<Vec<WorkspaceTree>>,
+    redo: Vec<Vec<WorkspaceTree>>,
+}
+
+impl LayoutHistory {
+    /// Records the trees from before a command. Anything undone can no longer
+    /// be redone.
+    pub(crate) fn record(&mut self, before: Vec<WorkspaceTree>) {
+        self.redo.clear();
+        self.push_undo(before);
+    }
+
+    pub(crate) fn take_undo(&mut self) -> Option<Vec<WorkspaceTree>> { self.undo.pop_back() }
+
+    pub(crate) fn take_redo(&mut self) -> Option<Vec<WorkspaceTree>> { self.redo.pop() }
+
+    pub(crate) fn push_undo(&mut self, step: Vec<WorkspaceTree>) {
+        self.undo.push_back(step);
+        if self.undo.len() > LAYOUT_HISTORY_LIMIT {
+            self.undo.pop_front();
+        }
+    }
+
+    pub(crate) fn push_redo(&mut self, step: Vec<WorkspaceTree>) { self.redo.push(step); }
+
+    /// Gives `new` the place of `old` in every recorded tree.
+    pub(crate) fn replace_window(&mut self, old: WindowId, new: WindowId) {
+        for step in self.undo.iter_mut().chain(self.redo.iter_mut()) {
+            for entry in step {
+                entry.tree = entry.tree.take().and_then(|tree| {
+                    tree.filter_map_windows(&mut |wid| Some(if wid == old { new } else { wid }))
+                });
+                entry.extras.replace_window(old, new);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(n: u32) -> Vec<WorkspaceTree> {
+        vec![WorkspaceTree {
+            workspace: VirtualWorkspaceId::default(),
+            tree: Some(SnapshotNode::Window {
+                window: WindowId::new(1, n),
+                weight: 1.0,
+            }),
+            extras: LayoutExtras::default(),
+        }]
+    }
+
+    #[test]
+    fn history_is_bounded_and_recording_forgets_redo() {
+        let mut history = LayoutHistory::default();
+        for n in 1..=LAYOUT_HISTORY_LIMIT as u32 + 5 {
+            history.record(step(n));
+        }
+        assert_eq!(history.undo.len(), LAYOUT_HISTORY_LIMIT);
+        assert_eq!(history.undo.front(), Some(&step(6)));
+
+        let undone = history.take_undo().unwrap();
+        history.push_redo(undone);
+        assert!(history.take_redo().is_some());
+        history.push_redo(step(1));
+        history.record(step(2));
+        assert!(history.take_redo().is_none());
+    }
+
+    #[test]
+    fn replaced_windows_keep_their_place() {
+        let mut history = LayoutHistory::default();
+        history.record(step(1));
+        history.replace_window(WindowId::new(1, 1), WindowId::new(2, 7));
+        assert_eq!(
+            history.take_undo().unwrap()[0].tree.as_ref().unwrap().windows(),
+            vec![&WindowId::new(2, 7)]
+        );
+    }
+}
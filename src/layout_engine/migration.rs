@@ -0,0 +1,87 @@
+//! Versioning of the serialized layout state in the restore file and in
+//! recordings, so that state written by an older build is upgraded instead of
+//! failing to load.
+//!
+//! The state starts with a header line giving its format version. Files
+//! written before the format was versioned have no header and are version 1.
+//! Each migration rewrites the RON text of one version into the next, so old
+//! shapes never need to be kept around as Rust types.
+
+use anyhow::{Context, bail};
+
+/// Format version written by this build. Bump it whenever a change to the
+/// serialized shape of the layout engine cannot be read from older files, and
+/// add a migration from the previous version to [`MIGRATIONS`].
+pub const LAYOUT_FORMAT_VERSION: u32 = 2;
+
+const HEADER_PREFIX: &str = "// rift layout format ";
+
+type Migration = fn(String) -> anyhow::Result<String>;
+
+/// `MIGRATIONS[i]` upgrades version `i + 1` to version `i + 2`.
+const MIGRATIONS: [Migration; LAYOUT_FORMAT_VERSION as usize - 1] = [migrate_v1_to_v2];
+
+/// The header line for the current format version. Being a RON comment, it is
+/// skipped by builds that predate it.
+pub fn header() -> String { format!("{HEADER_PREFIX}{LAYOUT_FORMAT_VERSION}") }
+
+/// The format version named by a header line, if `line` is one.
+pub fn parse_header(line: &str) -> Option<u32> {
+    line.trim().strip_prefix(HEADER_PREFIX)?.trim().parse().ok()
+}
+
+/// Splits serialized state into its format version and body.
+pub fn split_header(text: &str) -> (u32, &str) {
+    let (first, rest) = text.split_once('\n').unwrap_or((text, ""));
+    match parse_header(first) {
+        Some(version) => (version, rest),
+        None => (1, text),
+    }
+}
+
+/// Whether state of format `version` can be read by this build.
+pub fn can_migrate(version: u32) -> bool { (1..=LAYOUT_FORMAT_VERSION).contains(&version) }
+
+/// Upgrades a body of format `version` to the current format.
+pub fn migrate(version: u32, mut body: String) -> anyhow::Result<String> {
+    if version > LAYOUT_FORMAT_VERSION {
+        bail!(
+            "layout format {version} was written by a newer rift; this build reads up to format {LAYOUT_FORMAT_VERSION}"
+        );
+    }
+    if version == 0 {
+        bail!("unknown layout format 0");
+    }
+    for from in version..LAYOUT_FORMAT_VERSION {
+        body = MIGRATIONS[from as usize - 1](body)
+            .with_context(|| format!("migrating layout format {from} to {}", from + 1))?;
+    }
+    Ok(body)
+}
+
+/// Format 2 introduced the header; the body is unchanged.
+fn migrate_v1_to_v2(body: String) -> anyhow::Result<String> { Ok(body) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers_name_the_format_version() {
+        let text = format!("{}\n(a: 1)", header());
+        assert_eq!(split_header(&text), (LAYOUT_FORMAT_VERSION, "(a: 1)"));
+        assert_eq!(split_header("(a: 1)"), (1, "(a: 1)"));
+        assert_eq!(parse_header("// rift layout format 7"), Some(7));
+        assert_eq!(parse_header("// another comment"), None);
+    }
+
+    #[test]
+    fn migrate_upgrades_old_formats_and_rejects_newer_ones() {
+        assert_eq!(migrate(1, "(a: 1)".into()).unwrap(), "(a: 1)");
+        assert_eq!(migrate(LAYOUT_FORMAT_VERSION, "(a: 1)".into()).unwrap(), "(a: 1)");
+        assert!(migrate(LAYOUT_FORMAT_VERSION + 1, "(a: 1)".into()).is_err());
+        assert!(migrate(0, "(a: 1)".into()).is_err());
+        assert!(can_migrate(1));
+        assert!(!can_migrate(LAYOUT_FORMAT_VERSION + 1));
+    }
+}
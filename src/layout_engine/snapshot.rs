@@ -0,0 +1,423 @@
+//! Named layout snapshots: the tiled tree of a workspace saved under a name,
+//! so that it can be rebuilt later with the windows it held, like i3's
+//! `append_layout`. Workspace templates from the config are rebuilt the same
+//! way, with window criteria in place of saved windows.
+//!
+//! Snapshots do not depend on the layout system they were taken in. Each
+//! system describes its trees as [`SnapshotNode`]s and rebuilds them as
+//! closely as it can.
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::actor::app::{WindowId, pid_t};
+use crate::common::collections::BTreeMap;
+use crate::common::config::{AppWorkspaceRule, TemplateNode, WindowCriteria};
+use crate::common::util::write_atomically;
+use crate::layout_engine::LayoutKind;
+use crate::model::VirtualWorkspaceId;
+use crate::model::virtual_workspace::{AppRuleQuery, app_rule_matches, compile_title_regex};
+use crate::model::window_identity::{WindowIdentity, title_similarity};
+
+/// Process id of placeholder windows, which no app can have.
+const PLACEHOLDER_PID: pid_t = -1;
+
+/// Id for the `n`th placeholder, which holds a leaf of a loaded snapshot
+/// until a window fills it.
+pub fn placeholder_id(n: u32) -> WindowId {
+    WindowId {
+        pid: PLACEHOLDER_PID,
+        idx: NonZeroU32::new(n.max(1)).unwrap(),
+    }
+}
+
+pub fn is_placeholder(wid: WindowId) -> bool { wid.pid == PLACEHOLDER_PID }
+
+fn default_weight() -> f64 { 1.0 }
+
+/// A node of a tiled tree. Leaves hold a window id while the tree is in use,
+/// and the [`SnapshotWindow`] identifying that window once it is saved.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotNode<W> {
+    Window {
+        window: W,
+        /// Share of the parent container, relative to the siblings.
+        #[serde(default = "default_weight")]
+        weight: f64,
+    },
+    Container {
+        kind: LayoutKind,
+        #[serde(default = "default_weight")]
+        weight: f64,
+        children: Vec<SnapshotNode<W>>,
+    },
+}
+
+impl<W> SnapshotNode<W> {
+    pub fn weight(&self) -> f64 {
+        match self {
+            SnapshotNode::Window { weight, .. } | SnapshotNode::Container { weight, .. } => {
+                *weight
+            }
+        }
+    }
+
+    /// The windows of the tree, in order.
+    pub fn windows(&self) -> Vec<&W> {
+        fn collect<'a, W>(node: &'a SnapshotNode<W>, out: &mut Vec<&'a W>) {
+            match node {
+                SnapshotNode::Window { window, .. } => out.push(window),
+                SnapshotNode::Container { children, .. } => {
+                    for child in children {
+                        collect(child, out);
+                    }
+                }
+            }
+        }
+        let mut out = Vec::new();
+        collect(self, &mut out);
+        out
+    }
+
+    /// Converts every leaf with `f`, dropping leaves it returns `None` for and
+    /// containers left empty.
+    pub fn filter_map_windows<V>(
+        self,
+        f: &mut impl FnMut(W) -> Option<V>,
+    ) -> Option<SnapshotNode<V>> {
+        match self {
+            SnapshotNode::Window { window, weight } => {
+                Some(SnapshotNode::Window { window: f(window)?, weight })
+            }
+            SnapshotNode::Container { kind, weight, children } => {
+                let children: Vec<_> =
+                    children.into_iter().filter_map(|c| c.filter_map_windows(f)).collect();
+                if children.is_empty() {
+                    return None;
+                }
+                Some(SnapshotNode::Container { kind, weight, children })
+            }
+        }
+    }
+}
+
+/// Identifies the window filling a leaf of a saved layout.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SnapshotWindow {
+    pub app_id: Option<String>,
+    /// Title of the window when the layout was saved. Any window of the app
+    /// can fill the leaf; the title only decides between several of them.
+    pub title: String,
+}
+
+impl SnapshotWindow {
+    pub fn from_identity(identity: &WindowIdentity) -> Self {
+        SnapshotWindow {
+            app_id: identity.bundle_id.clone(),
+            title: identity.title.clone(),
+        }
+    }
+
+    /// How well `window` fits this leaf, or `None` if it cannot fill it.
+    pub fn match_score(&self, window: &AppRuleQuery) -> Option<u32> {
+        let score = title_similarity(&self.title, window.title.as_deref().unwrap_or_default());
+        match &self.app_id {
+            Some(app_id) => (window.app_id.as_ref() == Some(app_id)).then_some(score),
+            // Without an app to go by, only the exact title will do.
+            None => (score == 3).then_some(score),
+        }
+    }
+}
+
+/// What a leaf of a saved layout or workspace template accepts.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Slot {
+    /// The window that filled the leaf when the layout was saved.
+    Window(SnapshotWindow),
+    /// Any window matching the criteria of a template leaf.
+    Criteria(SlotCriteria),
+}
+
+/// The criteria of a template leaf, kept as the app rule they are checked as
+/// with its title pattern compiled once rather than for every window.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(from = "WindowCriteria", into = "WindowCriteria")]
+pub struct SlotCriteria {
+    criteria: WindowCriteria,
+    rule: AppWorkspaceRule,
+    /// `Err` if the pattern does not compile, in which case nothing matches.
+    title_regex: Result<Option<regex::Regex>, ()>,
+}
+
+impl SlotCriteria {
+    pub fn criteria(&self) -> &WindowCriteria { &self.criteria }
+}
+
+impl From<WindowCriteria> for SlotCriteria {
+    fn from(criteria: WindowCriteria) -> Self {
+        let title_regex = criteria.title_regex.as_deref().map(compile_title_regex).transpose();
+        SlotCriteria {
+            rule: criteria.to_app_rule(),
+            title_regex: title_regex.map_err(|_| ()),
+            criteria,
+        }
+    }
+}
+
+impl From<SlotCriteria> for WindowCriteria {
+    fn from(slot: SlotCriteria) -> Self { slot.criteria }
+}
+
+impl PartialEq for SlotCriteria {
+    fn eq(&self, other: &Self) -> bool { self.criteria == other.criteria }
+}
+
+impl Slot {
+    pub fn app_id(&self) -> Option<&str> {
+        match self {
+            Slot::Window(window) => window.app_id.as_deref(),
+            Slot::Criteria(slot) => slot.criteria.app_id.as_deref(),
+        }
+    }
+
+    /// How well `window` fits this slot, or `None` if it cannot fill it.
+    pub fn match_score(&self, window: &AppRuleQuery) -> Option<u32> {
+        match self {
+            Slot::Window(saved) => saved.match_score(window),
+            Slot::Criteria(slot) => {
+                let Ok(title_regex) = &slot.title_regex else {
+                    return None;
+                };
+                app_rule_matches(
+                    &slot.rule,
+                    title_regex.as_ref(),
+                    window.app_id.as_deref(),
+                    window.app_name.as_deref(),
+                    window.title.as_deref(),
+                    window.ax_role.as_deref(),
+                    window.ax_subrole.as_deref(),
+                )
+                .then_some(0)
+            }
+        }
+    }
+}
+
+impl From<&TemplateNode> for SnapshotNode<Slot> {
+    fn from(node: &TemplateNode) -> Self {
+        match node {
+            TemplateNode::Container { kind, weight, children } => SnapshotNode::Container {
+                kind: *kind,
+                weight: *weight,
+                children: children.iter().map(SnapshotNode::from).collect(),
+            },
+            TemplateNode::Window { criteria, weight } => SnapshotNode::Window {
+                window: Slot::Criteria(criteria.clone().into()),
+                weight: *weight,
+            },
+        }
+    }
+}
+
+/// A leaf of a loaded snapshot or template that no window has filled yet.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Placeholder {
+    pub slot: Slot,
+    pub workspace: VirtualWorkspaceId,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LayoutSnapshot {
+    pub root: SnapshotNode<Slot>,
+}
+
+/// Summary of a saved snapshot, for listing.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LayoutSnapshotInfo {
+    pub name: String,
+    pub window_count: usize,
+    pub apps: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct LayoutSnapshots {
+    snapshots: BTreeMap<String, LayoutSnapshot>,
+    /// File the snapshots were loaded from and are saved to. Snapshots that
+    /// were not loaded from a file are never saved.
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl LayoutSnapshots {
+    /// Loads the snapshots stored at `path`, starting empty if there are none.
+    /// A file that cannot be read is moved aside to `<path>.bak` rather than
+    /// overwritten by the next save; if that fails, nothing is ever saved.
+    pub fn load(path: PathBuf) -> Self {
+        match Self::read(&path) {
+            Ok(snapshots) => LayoutSnapshots { path: Some(path), ..snapshots },
+            Err(_) if !path.exists() => LayoutSnapshots {
+                path: Some(path),
+                ..Default::default()
+            },
+            Err(e) => {
+                let mut backup = path.clone().into_os_string();
+                backup.push(".bak");
+                let backup = PathBuf::from(backup);
+                warn!(
+                    "Could not read layout snapshots from {}, moving it to {}: {e}",
+                    path.display(),
+                    backup.display()
+                );
+                let path = match fs::rename(&path, &backup) {
+                    Ok(()) => Some(path),
+                    Err(e) => {
+                        warn!("Could not move {} aside, not saving layouts: {e}", path.display());
+                        None
+                    }
+                };
+                LayoutSnapshots { path, ..Default::default() }
+            }
+        }
+    }
+
+    fn read(path: &Path) -> anyhow::Result<Self> {
+        let mut buf = String::new();
+        File::open(path)?.read_to_string(&mut buf)?;
+        Ok(ron::from_str(&buf)?)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let pretty = ron::ser::PrettyConfig::default();
+        write_atomically(path, ron::ser::to_string_pretty(self, pretty)?.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&LayoutSnapshot> { self.snapshots.get(name) }
+
+    pub fn insert(&mut self, name: String, snapshot: LayoutSnapshot) {
+        self.snapshots.insert(name, snapshot);
+    }
+
+    pub fn list(&self) -> Vec<LayoutSnapshotInfo> {
+        self.snapshots
+            .iter()
+            .map(|(name, snapshot)| {
+                let windows = snapshot.root.windows();
+                let mut apps: Vec<String> =
+                    windows.iter().filter_map(|w| w.app_id().map(str::to_string)).collect();
+                apps.sort();
+                apps.dedup();
+                LayoutSnapshotInfo {
+                    name: name.clone(),
+                    window_count: windows.len(),
+                    apps,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(app_id: &str, title: &str) -> SnapshotNode<SnapshotWindow> {
+        SnapshotNode::Window {
+            window: SnapshotWindow {
+                app_id: Some(app_id.into()),
+                title: title.into(),
+            },
+            weight: 1.0,
+        }
+    }
+
+    fn live(app_id: &str, title: &str) -> AppRuleQuery {
+        AppRuleQuery {
+            app_id: Some(app_id.into()),
+            title: Some(title.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn snapshot_windows_match_by_app_and_prefer_title() {
+        let editor = SnapshotWindow {
+            app_id: Some("com.example.editor".into()),
+            title: "main.rs - review".into(),
+        };
+        let exact = editor.match_score(&live("com.example.editor", "main.rs - review"));
+        let other = editor.match_score(&live("com.example.editor", "notes.txt"));
+        assert!(exact > other);
+        assert!(other.is_some());
+        assert_eq!(editor.match_score(&live("com.example.term", "main.rs - review")), None);
+    }
+
+    #[test]
+    fn criteria_slots_match_like_app_rules() {
+        let slot = Slot::Criteria(
+            WindowCriteria {
+                app_id: Some("com.example.browser".into()),
+                title_regex: Some("^Pull Request".into()),
+                ..Default::default()
+            }
+            .into(),
+        );
+        assert!(slot.match_score(&live("com.example.browser", "Pull Request #12")).is_some());
+        assert_eq!(slot.match_score(&live("com.example.browser", "Inbox")), None);
+        assert_eq!(slot.match_score(&live("com.example.term", "Pull Request #12")), None);
+        let text = ron::to_string(&slot).unwrap();
+        assert_eq!(ron::from_str::<Slot>(&text).unwrap(), slot);
+    }
+
+    #[test]
+    fn filter_map_drops_empty_containers() {
+        let tree = SnapshotNode::Container {
+            kind: LayoutKind::Horizontal,
+            weight: 1.0,
+            children: vec![
+                leaf("com.example.editor", "a"),
+                SnapshotNode::Container {
+                    kind: LayoutKind::VerticalStack,
+                    weight: 2.0,
+                    children: vec![leaf("com.example.term", "b")],
+                },
+            ],
+        };
+        let mut next = 0;
+        let mapped = tree
+            .clone()
+            .filter_map_windows(&mut |w: SnapshotWindow| {
+                next += 1;
+                (w.app_id.as_deref() == Some("com.example.editor")).then(|| placeholder_id(next))
+            })
+            .unwrap();
+        assert_eq!(mapped.windows(), vec![&placeholder_id(1)]);
+        assert!(is_placeholder(placeholder_id(1)));
+        assert!(tree.filter_map_windows(&mut |_| None::<WindowId>).is_none());
+    }
+
+    #[test]
+    fn unreadable_file_is_backed_up_not_overwritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("layouts.ron");
+        std::fs::write(&path, "not ron").unwrap();
+        let snapshots = LayoutSnapshots::load(path.clone());
+        assert!(snapshots.list().is_empty());
+        assert_eq!(std::fs::read_to_string(path.with_extension("ron.bak")).unwrap(), "not ron");
+        snapshots.save().unwrap();
+        assert!(LayoutSnapshots::load(path).list().is_empty());
+    }
+}
@@ -3,6 +3,7 @@ use objc2_core_foundation::CGRect;
 use serde::{Deserialize, Serialize};
 
 use crate::actor::app::{WindowId, pid_t};
+use crate::layout_engine::snapshot::SnapshotNode;
 use crate::layout_engine::{Direction, LayoutKind};
 
 slotmap::new_key_type! { pub struct LayoutId; }
@@ -12,6 +13,39 @@ pub struct WindowDetails {
     pub title: String,
     pub bundle_id: Option<String>,
     pub frame: CGRect,
+    pub marks: Vec<String>,
+}
+
+impl WindowDetails {
+    /// Suffix listing the window's marks for tree dumps, empty when unmarked.
+    pub fn marks_label(&self) -> String {
+        if self.marks.is_empty() {
+            String::new()
+        } else {
+            format!(" marks=[{}]", self.marks.join(","))
+        }
+    }
+}
+
+/// State of a layout that its snapshot does not describe: fullscreen windows,
+/// and the master column of centered-master layouts.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LayoutExtras {
+    pub fullscreen: Vec<WindowId>,
+    pub fullscreen_within_gaps: Vec<WindowId>,
+    pub master_ratio: Option<f64>,
+    pub mirrored: bool,
+}
+
+impl LayoutExtras {
+    /// Gives `new` the state of `old`.
+    pub fn replace_window(&mut self, old: WindowId, new: WindowId) {
+        for wid in self.fullscreen.iter_mut().chain(&mut self.fullscreen_within_gaps) {
+            if *wid == old {
+                *wid = new;
+            }
+        }
+    }
 }
 
 #[enum_dispatch]
@@ -19,6 +53,18 @@ pub trait LayoutSystem: Serialize + for<'de> Deserialize<'de> {
     fn create_layout(&mut self) -> LayoutId;
     fn clone_layout(&mut self, layout: LayoutId) -> LayoutId;
     fn remove_layout(&mut self, layout: LayoutId);
+    /// Describes the tree of `layout` independently of the layout system, or
+    /// `None` if it holds no windows.
+    fn snapshot_layout(&self, layout: LayoutId) -> Option<SnapshotNode<WindowId>>;
+    /// Creates a layout holding the tree described by `snapshot`, as closely
+    /// as this layout system can represent it.
+    fn create_layout_from_snapshot(&mut self, snapshot: &SnapshotNode<WindowId>) -> LayoutId;
+    /// State of `layout` that [`Self::snapshot_layout`] leaves out, with
+    /// windows in sorted order.
+    fn layout_extras(&self, layout: LayoutId) -> LayoutExtras;
+    /// Replaces that state of `layout` with `extras`, ignoring windows the
+    /// layout does not hold.
+    fn set_layout_extras(&mut self, layout: LayoutId, extras: &LayoutExtras);
 
     fn draw_tree(&self, layout: LayoutId) -> String;
 
@@ -67,6 +113,9 @@ pub trait LayoutSystem: Serialize + for<'de> Deserialize<'de> {
     fn add_window_after_selection(&mut self, layout: LayoutId, wid: WindowId);
     fn remove_window(&mut self, wid: WindowId);
     fn remove_windows_for_app(&mut self, pid: pid_t);
+    /// Gives `new` the place of `old` in every layout, for re-associating a
+    /// restored window with the live window it turned out to be.
+    fn replace_window(&mut self, old: WindowId, new: WindowId);
     fn set_windows_for_app(&mut self, layout: LayoutId, pid: pid_t, desired: Vec<WindowId>);
     fn has_windows_for_app(&self, layout: LayoutId, pid: pid_t) -> bool;
     fn contains_window(&self, layout: LayoutId, wid: WindowId) -> bool;
@@ -82,6 +131,15 @@ pub trait LayoutSystem: Serialize + for<'de> Deserialize<'de> {
     );
 
     fn swap_windows(&mut self, layout: LayoutId, a: WindowId, b: WindowId) -> bool;
+    /// Exchanges `a`, tiled in `layout_a`, with `b`, tiled in `layout_b`, each
+    /// taking the other's place.
+    fn swap_windows_between_layouts(
+        &mut self,
+        layout_a: LayoutId,
+        a: WindowId,
+        layout_b: LayoutId,
+        b: WindowId,
+    ) -> bool;
 
     fn move_selection(&mut self, layout: LayoutId, direction: Direction) -> bool;
     fn move_selection_level_restricted(&mut self, layout: LayoutId, direction: Direction) -> bool;
@@ -118,6 +176,8 @@ mod traditional;
 pub use traditional::TraditionalLayoutSystem;
 mod bsp;
 pub use bsp::BspLayoutSystem;
+mod centered;
+pub use centered::CenteredMasterLayoutSystem;
 
 #[derive(Serialize, Deserialize)]
 #[serde(tag = "kind", rename_all = "snake_case")]
@@ -125,4 +185,5 @@ pub use bsp::BspLayoutSystem;
 pub enum LayoutSystemKind {
     Traditional(TraditionalLayoutSystem),
     Bsp(BspLayoutSystem),
+    CenteredMaster(CenteredMasterLayoutSystem),
 }
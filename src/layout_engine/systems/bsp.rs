@@ -3,7 +3,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::actor::app::{WindowId, pid_t};
 use crate::common::collections::{HashMap, HashSet};
-use crate::layout_engine::systems::LayoutSystem;
+use crate::layout_engine::snapshot::SnapshotNode;
+use crate::layout_engine::systems::{LayoutExtras, LayoutSystem};
 use crate::layout_engine::utils::compute_tiling_area;
 use crate::layout_engine::{Direction, LayoutId, LayoutKind, Orientation};
 use crate::model::selection::*;
@@ -233,6 +234,67 @@ impl BspLayoutSystem {
         }
     }
 
+    fn snapshot_node(&self, node: NodeId, weight: f64) -> Option<SnapshotNode<WindowId>> {
+        match self.kind.get(node)? {
+            NodeKind::Leaf { window, .. } => {
+                Some(SnapshotNode::Window { window: (*window)?, weight })
+            }
+            NodeKind::Split { orientation, ratio } => {
+                let ratio = f64::from(*ratio);
+                let children: Vec<_> = node
+                    .children(&self.tree.map)
+                    .zip([ratio, 1.0 - ratio])
+                    .filter_map(|(child, share)| self.snapshot_node(child, share))
+                    .collect();
+                if children.is_empty() {
+                    return None;
+                }
+                Some(SnapshotNode::Container {
+                    kind: LayoutKind::from(*orientation),
+                    weight,
+                    children,
+                })
+            }
+        }
+    }
+
+    /// Builds the subtree described by `snapshot`, returning its detached root.
+    fn build_snapshot_node(&mut self, snapshot: &SnapshotNode<WindowId>) -> NodeId {
+        match snapshot {
+            SnapshotNode::Window { window, .. } => self.make_leaf(Some(*window)),
+            SnapshotNode::Container { kind, children, .. } => {
+                self.build_snapshot_split(kind.orientation(), children)
+            }
+        }
+    }
+
+    /// Splits are binary, so containers with more children become a chain of
+    /// splits, each taking the first child's share off the rest.
+    fn build_snapshot_split(
+        &mut self,
+        orientation: Orientation,
+        children: &[SnapshotNode<WindowId>],
+    ) -> NodeId {
+        match children {
+            [] => self.make_leaf(None),
+            [only] => self.build_snapshot_node(only),
+            [first, rest @ ..] => {
+                let total: f64 = children.iter().map(|child| child.weight().max(0.0)).sum();
+                let ratio = if total > 0.0 { first.weight().max(0.0) / total } else { 0.5 };
+                let first_node = self.build_snapshot_node(first);
+                let rest_node = self.build_snapshot_split(orientation, rest);
+                let split = self.tree.mk_node().into_id();
+                self.kind.insert(split, NodeKind::Split {
+                    orientation,
+                    ratio: (ratio as f32).clamp(0.05, 0.95),
+                });
+                first_node.detach(&mut self.tree).push_back(split);
+                rest_node.detach(&mut self.tree).push_back(split);
+                split
+            }
+        }
+    }
+
     fn find_layout_root(&self, mut node: NodeId) -> NodeId {
         while let Some(p) = node.parent(&self.tree.map) {
             node = p;
@@ -549,6 +611,58 @@ impl LayoutSystem for BspLayoutSystem {
         new_layout
     }
 
+    fn snapshot_layout(&self, layout: LayoutId) -> Option<SnapshotNode<WindowId>> {
+        let state = self.layouts.get(layout)?;
+        self.snapshot_node(state.root, 1.0)
+    }
+
+    fn create_layout_from_snapshot(&mut self, snapshot: &SnapshotNode<WindowId>) -> LayoutId {
+        let root = self.build_snapshot_node(snapshot);
+        let first = self.descend_to_leaf(root);
+        self.tree.data.selection.select(&self.tree.map, first);
+        self.layouts.insert(LayoutState { root })
+    }
+
+    fn layout_extras(&self, layout: LayoutId) -> LayoutExtras {
+        let mut windows = Vec::new();
+        if let Some(state) = self.layouts.get(layout) {
+            self.collect_windows_under(state.root, &mut windows);
+        }
+        windows.sort_unstable();
+        let mut extras = LayoutExtras::default();
+        for wid in windows {
+            if let Some(NodeKind::Leaf { fullscreen, fullscreen_within_gaps, .. }) =
+                self.window_to_node.get(&wid).and_then(|&node| self.kind.get(node))
+            {
+                if *fullscreen {
+                    extras.fullscreen.push(wid);
+                }
+                if *fullscreen_within_gaps {
+                    extras.fullscreen_within_gaps.push(wid);
+                }
+            }
+        }
+        extras
+    }
+
+    fn set_layout_extras(&mut self, layout: LayoutId, extras: &LayoutExtras) {
+        let mut windows = Vec::new();
+        if let Some(state) = self.layouts.get(layout) {
+            self.collect_windows_under(state.root, &mut windows);
+        }
+        for wid in windows {
+            if let Some(NodeKind::Leaf { fullscreen, fullscreen_within_gaps, .. }) = self
+                .window_to_node
+                .get(&wid)
+                .copied()
+                .and_then(|node| self.kind.get_mut(node))
+            {
+                *fullscreen = extras.fullscreen.contains(&wid);
+                *fullscreen_within_gaps = extras.fullscreen_within_gaps.contains(&wid);
+            }
+        }
+    }
+
     fn remove_layout(&mut self, layout: LayoutId) {
         if let Some(state) = self.layouts.remove(layout) {
             let mut windows = Vec::new();
@@ -622,9 +736,10 @@ impl LayoutSystem for BspLayoutSystem {
                                 details.title.clone()
                             };
                             out.push_str(&format!(
-                                "Leaf {:?} | \"{}\" ({}) [{:.0}x{:.0}]\n",
+                                "Leaf {:?} | \"{}\" ({}) [{:.0}x{:.0}]{}\n",
                                 wid, title, bundle,
-                                details.frame.size.width, details.frame.size.height
+                                details.frame.size.width, details.frame.size.height,
+                                details.marks_label()
                             ));
                         } else {
                             out.push_str(&format!("Leaf {:?}\n", wid));
@@ -1066,6 +1181,19 @@ impl LayoutSystem for BspLayoutSystem {
         }
     }
 
+    fn replace_window(&mut self, old: WindowId, new: WindowId) {
+        if self.window_to_node.contains_key(&new) {
+            return;
+        }
+        let Some(node) = self.window_to_node.remove(&old) else {
+            return;
+        };
+        if let Some(NodeKind::Leaf { window, .. }) = self.kind.get_mut(node) {
+            *window = Some(new);
+        }
+        self.window_to_node.insert(new, node);
+    }
+
     fn set_windows_for_app(&mut self, layout: LayoutId, pid: pid_t, desired: Vec<WindowId>) {
         let desired_set: HashSet<WindowId> = desired.iter().copied().collect();
         let mut current_set: HashSet<WindowId> = HashSet::default();
@@ -1266,6 +1394,16 @@ impl LayoutSystem for BspLayoutSystem {
     }
 
     fn swap_windows(&mut self, layout: LayoutId, a: WindowId, b: WindowId) -> bool {
+        self.swap_windows_between_layouts(layout, a, layout, b)
+    }
+
+    fn swap_windows_between_layouts(
+        &mut self,
+        layout_a: LayoutId,
+        a: WindowId,
+        layout_b: LayoutId,
+        b: WindowId,
+    ) -> bool {
         let Some(&node_a) = self.window_to_node.get(&a) else {
             return false;
         };
@@ -1276,12 +1414,15 @@ impl LayoutSystem for BspLayoutSystem {
             return false;
         }
 
-        if let Some(state) = self.layouts.get(layout).copied() {
-            if !self.belongs_to_layout(state, node_a) || !self.belongs_to_layout(state, node_b) {
-                return false;
+        match (self.layouts.get(layout_a).copied(), self.layouts.get(layout_b).copied()) {
+            (Some(state_a), Some(state_b)) => {
+                if !self.belongs_to_layout(state_a, node_a)
+                    || !self.belongs_to_layout(state_b, node_b)
+                {
+                    return false;
+                }
             }
-        } else {
-            return false;
+            _ => return false,
         }
 
         let mut a_window = None;
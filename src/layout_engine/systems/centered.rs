@@ -0,0 +1,972 @@
+use objc2_core_foundation::{CGPoint, CGRect, CGSize};
+use serde::{Deserialize, Serialize};
+
+use crate::actor::app::{WindowId, pid_t};
+use crate::common::collections::HashSet;
+use crate::common::config::{CenteredMasterSettings, GapSettings};
+use crate::layout_engine::snapshot::SnapshotNode;
+use crate::layout_engine::systems::{LayoutExtras, LayoutSystem};
+use crate::layout_engine::utils::compute_tiling_area;
+use crate::layout_engine::{Direction, LayoutId, LayoutKind};
+
+/// Which column of the centered-master layout a window lives in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Left,
+    Master,
+    Right,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct CenteredLayout {
+    /// Windows in layout order. The first window is the master; the remaining
+    /// windows alternate between the right and left side stacks.
+    windows: Vec<WindowId>,
+    selected: Option<WindowId>,
+    /// Master ratio adjusted by resizing. `None` uses the configured ratio.
+    master_ratio: Option<f64>,
+    /// Swap which side stack receives the first stack window.
+    #[serde(default)]
+    mirrored: bool,
+    #[serde(default)]
+    fullscreen: HashSet<WindowId>,
+    #[serde(default)]
+    fullscreen_within_gaps: HashSet<WindowId>,
+}
+
+impl CenteredLayout {
+    fn index_of(&self, wid: WindowId) -> Option<usize> {
+        self.windows.iter().position(|w| *w == wid)
+    }
+
+    fn selected_index(&self) -> Option<usize> { self.selected.and_then(|w| self.index_of(w)) }
+
+    fn column_of(&self, idx: usize) -> Column {
+        if idx == 0 {
+            return Column::Master;
+        }
+        let first_side = (idx - 1) % 2 == 0;
+        match (first_side, self.mirrored) {
+            (true, false) | (false, true) => Column::Right,
+            _ => Column::Left,
+        }
+    }
+
+    /// Indices of the windows in the given column, top to bottom.
+    fn column(&self, column: Column) -> Vec<usize> {
+        (0..self.windows.len()).filter(|&i| self.column_of(i) == column).collect()
+    }
+
+    /// The side stacks, the one taking the first stack window first.
+    fn stacks(&self) -> (Vec<WindowId>, Vec<WindowId>) {
+        let ids = |column| -> Vec<WindowId> {
+            self.column(column).into_iter().map(|i| self.windows[i]).collect()
+        };
+        let first_column = self.column_of(1);
+        let second_column =
+            if first_column == Column::Right { Column::Left } else { Column::Right };
+        (ids(first_column), ids(second_column))
+    }
+
+    /// Lays the windows out again as `master` followed by the stacks, taking
+    /// turns between them.
+    fn interleave(&mut self, master: WindowId, first: Vec<WindowId>, second: Vec<WindowId>) {
+        self.windows = std::iter::once(master)
+            .chain((0..first.len()).flat_map(|i| [first.get(i), second.get(i)]).flatten().copied())
+            .collect();
+    }
+
+    fn remove(&mut self, wid: WindowId) -> bool {
+        let Some(idx) = self.index_of(wid) else {
+            return false;
+        };
+        // Columns follow from positions, so the stacks are interleaved again
+        // with every other window keeping its column. Only when the stack that
+        // lost a window would end up too short does it take the bottom window
+        // of the other one.
+        let (mut first, mut second) = self.stacks();
+        let master = if idx == 0 {
+            (!first.is_empty()).then(|| first.remove(0))
+        } else {
+            first.retain(|&w| w != wid);
+            second.retain(|&w| w != wid);
+            Some(self.windows[0])
+        };
+        if second.len() > first.len() {
+            first.extend(second.pop());
+        } else if first.len() > second.len() + 1 {
+            second.extend(first.pop());
+        }
+        match master {
+            Some(master) => self.interleave(master, first, second),
+            None => self.windows.clear(),
+        }
+        self.fullscreen.remove(&wid);
+        self.fullscreen_within_gaps.remove(&wid);
+        if self.selected == Some(wid) {
+            self.selected = if self.windows.is_empty() {
+                None
+            } else {
+                Some(self.windows[idx.min(self.windows.len() - 1)])
+            };
+        }
+        true
+    }
+
+    fn insert_after_selection(&mut self, wid: WindowId) {
+        if self.index_of(wid).is_some() {
+            self.selected = Some(wid);
+            return;
+        }
+        if self.windows.is_empty() {
+            self.windows.push(wid);
+            self.selected = Some(wid);
+            return;
+        }
+        // Columns follow from positions, so the new window joins the column
+        // the next position falls in, after the selection if it is there, and
+        // the stacks are interleaved again with every other window keeping its
+        // column.
+        let (mut first, mut second) = self.stacks();
+        let selected = self.selected;
+        let stack = if self.column_of(self.windows.len()) == self.column_of(1) {
+            &mut first
+        } else {
+            &mut second
+        };
+        let at = selected
+            .and_then(|s| stack.iter().position(|&w| w == s))
+            .map(|p| p + 1)
+            .unwrap_or(stack.len());
+        stack.insert(at, wid);
+
+        let master = self.windows[0];
+        self.interleave(master, first, second);
+        self.selected = Some(wid);
+    }
+
+    /// Index of the window reached by moving from `idx` in `direction`.
+    fn neighbor(&self, idx: usize, direction: Direction) -> Option<usize> {
+        let column = self.column_of(idx);
+        match direction {
+            Direction::Up | Direction::Down => {
+                let members = self.column(column);
+                let pos = members.iter().position(|&i| i == idx)?;
+                match direction {
+                    Direction::Up => pos.checked_sub(1).map(|p| members[p]),
+                    _ => members.get(pos + 1).copied(),
+                }
+            }
+            Direction::Left | Direction::Right => {
+                let target = match (column, direction) {
+                    (Column::Master, Direction::Left) => Column::Left,
+                    (Column::Master, _) => Column::Right,
+                    (Column::Left, Direction::Right) | (Column::Right, Direction::Left) => {
+                        Column::Master
+                    }
+                    _ => return None,
+                };
+                self.column(target).first().copied()
+            }
+        }
+    }
+}
+
+/// Centered-master tiling: the master window sits in a centered column and
+/// the remaining windows alternate into stacks on either side of it.
+#[derive(Serialize, Deserialize, Default)]
+pub struct CenteredMasterLayoutSystem {
+    layouts: slotmap::SlotMap<LayoutId, CenteredLayout>,
+    #[serde(skip)]
+    settings: CenteredMasterSettings,
+}
+
+impl CenteredMasterLayoutSystem {
+    pub fn set_settings(&mut self, settings: &CenteredMasterSettings) {
+        self.settings = settings.clone();
+    }
+
+    fn ratio_for(layout: &CenteredLayout, settings: &CenteredMasterSettings) -> f64 {
+        layout.master_ratio.unwrap_or(settings.master_ratio).clamp(0.1, 0.9)
+    }
+
+    fn stack_frames(
+        area: CGRect,
+        x: f64,
+        width: f64,
+        indices: &[usize],
+        gap: f64,
+        out: &mut Vec<(usize, CGRect)>,
+    ) {
+        if indices.is_empty() {
+            return;
+        }
+        let count = indices.len() as f64;
+        let height = ((area.size.height - gap * (count - 1.0)) / count).max(0.0);
+        for (pos, &idx) in indices.iter().enumerate() {
+            let y = area.origin.y + pos as f64 * (height + gap);
+            out.push((idx, CGRect::new(CGPoint::new(x, y), CGSize::new(width, height))));
+        }
+    }
+
+    /// Calculates frames using the given (already display-resolved) settings.
+    pub fn calculate_layout_with_settings(
+        &self,
+        layout: LayoutId,
+        screen: CGRect,
+        gaps: &GapSettings,
+        settings: &CenteredMasterSettings,
+    ) -> Vec<(WindowId, CGRect)> {
+        let Some(state) = self.layouts.get(layout) else {
+            return Vec::new();
+        };
+        let area = compute_tiling_area(screen, gaps);
+        let gap = gaps.inner.horizontal;
+        let vgap = gaps.inner.vertical;
+        let mut frames: Vec<(usize, CGRect)> = Vec::new();
+
+        match state.windows.len() {
+            0 => {}
+            1 => {
+                let width = match settings.single_window_max_width {
+                    Some(max) => area.size.width.min(max),
+                    None => area.size.width,
+                };
+                let x = area.origin.x + (area.size.width - width) / 2.0;
+                frames.push((
+                    0,
+                    CGRect::new(
+                        CGPoint::new(x, area.origin.y),
+                        CGSize::new(width, area.size.height),
+                    ),
+                ));
+            }
+            _ => {
+                let ratio = Self::ratio_for(state, settings);
+                let left = state.column(Column::Left);
+                let right = state.column(Column::Right);
+                if left.is_empty() || right.is_empty() {
+                    // With a single side stack, the master takes its share on
+                    // the side opposite to the stack.
+                    let side = if left.is_empty() { &right } else { &left };
+                    let available = (area.size.width - gap).max(0.0);
+                    let master_w = available * ratio;
+                    let side_w = available - master_w;
+                    let (master_x, side_x) = if left.is_empty() {
+                        (area.origin.x, area.origin.x + master_w + gap)
+                    } else {
+                        (area.origin.x + side_w + gap, area.origin.x)
+                    };
+                    Self::stack_frames(area, master_x, master_w, &[0], vgap, &mut frames);
+                    Self::stack_frames(area, side_x, side_w, side, vgap, &mut frames);
+                } else {
+                    let available = (area.size.width - 2.0 * gap).max(0.0);
+                    let master_w = available * ratio;
+                    let side_w = (available - master_w) / 2.0;
+                    let master_x = area.origin.x + side_w + gap;
+                    let right_x = master_x + master_w + gap;
+                    Self::stack_frames(area, area.origin.x, side_w, &left, vgap, &mut frames);
+                    Self::stack_frames(area, master_x, master_w, &[0], vgap, &mut frames);
+                    Self::stack_frames(area, right_x, side_w, &right, vgap, &mut frames);
+                }
+            }
+        }
+
+        frames
+            .into_iter()
+            .map(|(idx, rect)| {
+                let wid = state.windows[idx];
+                let rect = if state.fullscreen.contains(&wid) {
+                    screen
+                } else if state.fullscreen_within_gaps.contains(&wid) {
+                    area
+                } else {
+                    rect
+                };
+                (wid, rect)
+            })
+            .collect()
+    }
+
+    /// Resizes the selection starting from the ratio the given (already
+    /// display-resolved) settings lay it out with.
+    pub fn resize_selection_with_settings(
+        &mut self,
+        layout: LayoutId,
+        amount: f64,
+        settings: &CenteredMasterSettings,
+    ) {
+        let Some(state) = self.layouts.get_mut(layout) else {
+            return;
+        };
+        let Some(idx) = state.selected_index() else {
+            return;
+        };
+        if state.windows.len() < 2 {
+            return;
+        }
+        let current = Self::ratio_for(state, settings);
+        // Growing a side window shrinks the master column.
+        let delta = if idx == 0 { amount } else { -amount };
+        state.master_ratio = Some((current + delta).clamp(0.1, 0.9));
+    }
+
+    fn focus_index(&mut self, layout: LayoutId, idx: usize) -> (Option<WindowId>, Vec<WindowId>) {
+        let Some(state) = self.layouts.get_mut(layout) else {
+            return (None, vec![]);
+        };
+        let wid = state.windows[idx];
+        state.selected = Some(wid);
+        (Some(wid), state.windows.clone())
+    }
+
+    fn cycle_selection(&mut self, layout: LayoutId, forward: bool) -> (Option<WindowId>, Vec<WindowId>) {
+        let Some(state) = self.layouts.get(layout) else {
+            return (None, vec![]);
+        };
+        let len = state.windows.len();
+        let Some(idx) = state.selected_index() else {
+            return (None, vec![]);
+        };
+        if len < 2 {
+            return (None, vec![]);
+        }
+        let next = if forward { (idx + 1) % len } else { (idx + len - 1) % len };
+        self.focus_index(layout, next)
+    }
+}
+
+impl LayoutSystem for CenteredMasterLayoutSystem {
+    fn create_layout(&mut self) -> LayoutId { self.layouts.insert(CenteredLayout::default()) }
+
+    fn clone_layout(&mut self, layout: LayoutId) -> LayoutId {
+        let cloned = self.layouts.get(layout).cloned().unwrap_or_default();
+        self.layouts.insert(cloned)
+    }
+
+    fn remove_layout(&mut self, layout: LayoutId) { self.layouts.remove(layout); }
+
+    /// Centered-master layouts are a sequence of windows, saved as a single
+    /// horizontal container in layout order.
+    fn snapshot_layout(&self, layout: LayoutId) -> Option<SnapshotNode<WindowId>> {
+        let state = self.layouts.get(layout)?;
+        if state.windows.is_empty() {
+            return None;
+        }
+        Some(SnapshotNode::Container {
+            kind: LayoutKind::Horizontal,
+            weight: 1.0,
+            children: state
+                .windows
+                .iter()
+                .map(|&window| SnapshotNode::Window { window, weight: 1.0 })
+                .collect(),
+        })
+    }
+
+    fn create_layout_from_snapshot(&mut self, snapshot: &SnapshotNode<WindowId>) -> LayoutId {
+        let windows: Vec<WindowId> = snapshot.windows().into_iter().copied().collect();
+        self.layouts.insert(CenteredLayout {
+            selected: windows.first().copied(),
+            windows,
+            ..Default::default()
+        })
+    }
+
+    fn layout_extras(&self, layout: LayoutId) -> LayoutExtras {
+        let Some(state) = self.layouts.get(layout) else {
+            return LayoutExtras::default();
+        };
+        let sorted = |set: &HashSet<WindowId>| {
+            let mut windows: Vec<WindowId> = set.iter().copied().collect();
+            windows.sort_unstable();
+            windows
+        };
+        LayoutExtras {
+            fullscreen: sorted(&state.fullscreen),
+            fullscreen_within_gaps: sorted(&state.fullscreen_within_gaps),
+            master_ratio: state.master_ratio,
+            mirrored: state.mirrored,
+        }
+    }
+
+    fn set_layout_extras(&mut self, layout: LayoutId, extras: &LayoutExtras) {
+        let Some(state) = self.layouts.get_mut(layout) else {
+            return;
+        };
+        let held = |windows: &[WindowId], state: &CenteredLayout| -> HashSet<WindowId> {
+            windows.iter().copied().filter(|&w| state.index_of(w).is_some()).collect()
+        };
+        state.fullscreen = held(&extras.fullscreen, state);
+        state.fullscreen_within_gaps = held(&extras.fullscreen_within_gaps, state);
+        state.master_ratio = extras.master_ratio;
+        state.mirrored = extras.mirrored;
+    }
+
+    fn draw_tree(&self, layout: LayoutId) -> String {
+        self.draw_tree_with_details(layout, |_| None)
+    }
+
+    fn draw_tree_with_details<F>(&self, layout: LayoutId, window_info_fn: F) -> String
+    where
+        F: Fn(WindowId) -> Option<crate::layout_engine::systems::WindowDetails>,
+    {
+        let Some(state) = self.layouts.get(layout) else {
+            return "<empty centered_master>".to_string();
+        };
+        let mut out = format!(
+            "CenteredMaster ratio={}\n",
+            state.master_ratio.map(|r| format!("{r:.2}")).unwrap_or_else(|| "default".into())
+        );
+        for column in [Column::Left, Column::Master, Column::Right] {
+            let members = state.column(column);
+            if members.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("  {:?}\n", column));
+            for idx in members {
+                let wid = state.windows[idx];
+                let marker = if state.selected == Some(wid) { " *" } else { "" };
+                match window_info_fn(wid) {
+                    Some(details) => {
+                        let bundle = details.bundle_id.as_deref().unwrap_or("unknown");
+                        let title = if details.title.chars().count() > 40 {
+                            format!("{}...", details.title.chars().take(37).collect::<String>())
+                        } else {
+                            details.title.clone()
+                        };
+                        out.push_str(&format!(
+                            "    {:?}{} | \"{}\" ({}) [{:.0}x{:.0}]{}\n",
+                            wid,
+                            marker,
+                            title,
+                            bundle,
+                            details.frame.size.width,
+                            details.frame.size.height,
+                            details.marks_label()
+                        ));
+                    }
+                    None => out.push_str(&format!("    {:?}{}\n", wid, marker)),
+                }
+            }
+        }
+        out
+    }
+
+    fn calculate_layout(
+        &self,
+        layout: LayoutId,
+        screen: CGRect,
+        _stack_offset: f64,
+        gaps: &GapSettings,
+        _stack_line_thickness: f64,
+        _stack_line_horiz: crate::common::config::HorizontalPlacement,
+        _stack_line_vert: crate::common::config::VerticalPlacement,
+    ) -> Vec<(WindowId, CGRect)> {
+        self.calculate_layout_with_settings(layout, screen, gaps, &self.settings)
+    }
+
+    fn selected_window(&self, layout: LayoutId) -> Option<WindowId> {
+        self.layouts.get(layout).and_then(|s| s.selected)
+    }
+
+    fn visible_windows_in_layout(&self, layout: LayoutId) -> Vec<WindowId> {
+        self.layouts.get(layout).map(|s| s.windows.clone()).unwrap_or_default()
+    }
+
+    fn visible_windows_under_selection(&self, layout: LayoutId) -> Vec<WindowId> {
+        self.selected_window(layout).into_iter().collect()
+    }
+
+    fn ascend_selection(&mut self, _layout: LayoutId) -> bool { false }
+
+    fn descend_selection(&mut self, _layout: LayoutId) -> bool { false }
+
+    fn ungroup_selection(&mut self, _layout: LayoutId) -> bool { false }
+
+    fn ungroup_siblings(&mut self, _layout: LayoutId) -> bool { false }
+
+    fn group_selection(
+        &mut self,
+        _layout: LayoutId,
+        _auto_stack: bool,
+        _stack_orientation: crate::common::config::StackDefaultOrientation,
+    ) -> Vec<WindowId> {
+        vec![]
+    }
+
+    fn increase_selection_left(&mut self, _layout: LayoutId) -> bool { false }
+
+    fn increase_selection_right(&mut self, _layout: LayoutId) -> bool { false }
+
+    fn decrease_selection_left(&mut self, _layout: LayoutId) -> bool { false }
+
+    fn decrease_selection_right(&mut self, _layout: LayoutId) -> bool { false }
+
+    fn move_selection_to_sibling_next(&mut self, _layout: LayoutId) -> bool { false }
+
+    fn move_selection_to_sibling_prev(&mut self, _layout: LayoutId) -> bool { false }
+
+    fn move_focus(
+        &mut self,
+        layout: LayoutId,
+        direction: Direction,
+    ) -> (Option<WindowId>, Vec<WindowId>) {
+        let Some(state) = self.layouts.get(layout) else {
+            return (None, vec![]);
+        };
+        let Some(next) = state.selected_index().and_then(|idx| state.neighbor(idx, direction))
+        else {
+            return (None, vec![]);
+        };
+        self.focus_index(layout, next)
+    }
+
+    fn move_focus_level_restricted(
+        &mut self,
+        layout: LayoutId,
+        direction: Direction,
+    ) -> (Option<WindowId>, Vec<WindowId>) {
+        self.move_focus(layout, direction)
+    }
+
+    fn next_sibling_window(&mut self, layout: LayoutId) -> (Option<WindowId>, Vec<WindowId>) {
+        self.cycle_selection(layout, true)
+    }
+
+    fn prev_sibling_window(&mut self, layout: LayoutId) -> (Option<WindowId>, Vec<WindowId>) {
+        self.cycle_selection(layout, false)
+    }
+
+    fn window_in_direction(&self, layout: LayoutId, direction: Direction) -> Option<WindowId> {
+        let state = self.layouts.get(layout)?;
+        let idx = match direction {
+            Direction::Right => state.column(Column::Left).first().copied(),
+            Direction::Left => state.column(Column::Right).first().copied(),
+            Direction::Up | Direction::Down => None,
+        }
+        .or_else(|| (!state.windows.is_empty()).then_some(0))?;
+        Some(state.windows[idx])
+    }
+
+    fn add_window_after_selection(&mut self, layout: LayoutId, wid: WindowId) {
+        if let Some(state) = self.layouts.get_mut(layout) {
+            state.insert_after_selection(wid);
+        }
+    }
+
+    fn remove_window(&mut self, wid: WindowId) {
+        for (_, state) in self.layouts.iter_mut() {
+            state.remove(wid);
+        }
+    }
+
+    fn remove_windows_for_app(&mut self, pid: pid_t) {
+        for (_, state) in self.layouts.iter_mut() {
+            let windows: Vec<_> = state.windows.iter().copied().filter(|w| w.pid == pid).collect();
+            for w in windows {
+                state.remove(w);
+            }
+        }
+    }
+
+    fn replace_window(&mut self, old: WindowId, new: WindowId) {
+        for (_, state) in self.layouts.iter_mut() {
+            if state.index_of(new).is_some() {
+                continue;
+            }
+            let Some(idx) = state.index_of(old) else {
+                continue;
+            };
+            state.windows[idx] = new;
+            if state.selected == Some(old) {
+                state.selected = Some(new);
+            }
+            if state.fullscreen.remove(&old) {
+                state.fullscreen.insert(new);
+            }
+            if state.fullscreen_within_gaps.remove(&old) {
+                state.fullscreen_within_gaps.insert(new);
+            }
+        }
+    }
+
+    fn set_windows_for_app(&mut self, layout: LayoutId, pid: pid_t, desired: Vec<WindowId>) {
+        let Some(state) = self.layouts.get_mut(layout) else {
+            return;
+        };
+        let desired_set: HashSet<WindowId> = desired.iter().copied().collect();
+        let current: Vec<WindowId> =
+            state.windows.iter().copied().filter(|w| w.pid == pid).collect();
+        for w in &current {
+            if !desired_set.contains(w)
+                && !state.fullscreen.contains(w)
+                && !state.fullscreen_within_gaps.contains(w)
+            {
+                state.remove(*w);
+            }
+        }
+        for w in desired {
+            if !current.contains(&w) {
+                state.insert_after_selection(w);
+            }
+        }
+    }
+
+    fn has_windows_for_app(&self, layout: LayoutId, pid: pid_t) -> bool {
+        self.layouts
+            .get(layout)
+            .map(|s| s.windows.iter().any(|w| w.pid == pid))
+            .unwrap_or(false)
+    }
+
+    fn contains_window(&self, layout: LayoutId, wid: WindowId) -> bool {
+        self.layouts.get(layout).map(|s| s.index_of(wid).is_some()).unwrap_or(false)
+    }
+
+    fn select_window(&mut self, layout: LayoutId, wid: WindowId) -> bool {
+        match self.layouts.get_mut(layout) {
+            Some(state) if state.index_of(wid).is_some() => {
+                state.selected = Some(wid);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn on_window_resized(
+        &mut self,
+        layout: LayoutId,
+        wid: WindowId,
+        old_frame: CGRect,
+        new_frame: CGRect,
+        screen: CGRect,
+        gaps: &GapSettings,
+    ) {
+        let Some(state) = self.layouts.get_mut(layout) else {
+            return;
+        };
+        let Some(idx) = state.index_of(wid) else {
+            return;
+        };
+        let tiling = compute_tiling_area(screen, gaps);
+        if new_frame == screen {
+            state.fullscreen.insert(wid);
+            state.fullscreen_within_gaps.remove(&wid);
+        } else if old_frame == screen {
+            state.fullscreen.remove(&wid);
+        } else if new_frame == tiling {
+            state.fullscreen_within_gaps.insert(wid);
+            state.fullscreen.remove(&wid);
+        } else if old_frame == tiling {
+            state.fullscreen_within_gaps.remove(&wid);
+        } else if idx == 0
+            && state.windows.len() > 1
+            && old_frame.size.width != new_frame.size.width
+        {
+            let three_columns = !state.column(Column::Left).is_empty()
+                && !state.column(Column::Right).is_empty();
+            let column_gaps = if three_columns { 2.0 } else { 1.0 };
+            let available = tiling.size.width - column_gaps * gaps.inner.horizontal;
+            if available > 0.0 {
+                state.master_ratio = Some((new_frame.size.width / available).clamp(0.1, 0.9));
+            }
+        }
+    }
+
+    fn swap_windows(&mut self, layout: LayoutId, a: WindowId, b: WindowId) -> bool {
+        let Some(state) = self.layouts.get_mut(layout) else {
+            return false;
+        };
+        match (state.index_of(a), state.index_of(b)) {
+            (Some(ia), Some(ib)) if ia != ib => {
+                state.windows.swap(ia, ib);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn swap_windows_between_layouts(
+        &mut self,
+        layout_a: LayoutId,
+        a: WindowId,
+        layout_b: LayoutId,
+        b: WindowId,
+    ) -> bool {
+        if layout_a == layout_b {
+            return self.swap_windows(layout_a, a, b);
+        }
+        let Some([state_a, state_b]) = self.layouts.get_disjoint_mut([layout_a, layout_b]) else {
+            return false;
+        };
+        let (Some(ia), Some(ib)) = (state_a.index_of(a), state_b.index_of(b)) else {
+            return false;
+        };
+        state_a.windows[ia] = b;
+        state_b.windows[ib] = a;
+        if state_a.selected == Some(a) {
+            state_a.selected = Some(b);
+        }
+        if state_b.selected == Some(b) {
+            state_b.selected = Some(a);
+        }
+        true
+    }
+
+    fn move_selection(&mut self, layout: LayoutId, direction: Direction) -> bool {
+        let Some(state) = self.layouts.get_mut(layout) else {
+            return false;
+        };
+        let Some(idx) = state.selected_index() else {
+            return false;
+        };
+        let Some(target) = state.neighbor(idx, direction) else {
+            return false;
+        };
+        state.windows.swap(idx, target);
+        true
+    }
+
+    fn move_selection_level_restricted(&mut self, layout: LayoutId, direction: Direction) -> bool {
+        self.move_selection(layout, direction)
+    }
+
+    fn move_selection_to_layout_after_selection(
+        &mut self,
+        from_layout: LayoutId,
+        to_layout: LayoutId,
+    ) {
+        let Some(wid) = self.selected_window(from_layout) else {
+            return;
+        };
+        if let Some(state) = self.layouts.get_mut(from_layout) {
+            state.remove(wid);
+        }
+        self.add_window_after_selection(to_layout, wid);
+    }
+
+    fn split_selection(&mut self, _layout: LayoutId, _kind: LayoutKind) {}
+
+    fn toggle_fullscreen_of_selection(&mut self, layout: LayoutId) -> Vec<WindowId> {
+        let Some(state) = self.layouts.get_mut(layout) else {
+            return vec![];
+        };
+        let Some(wid) = state.selected else {
+            return vec![];
+        };
+        if !state.fullscreen.remove(&wid) {
+            state.fullscreen.insert(wid);
+            state.fullscreen_within_gaps.remove(&wid);
+        }
+        vec![wid]
+    }
+
+    fn toggle_fullscreen_within_gaps_of_selection(&mut self, layout: LayoutId) -> Vec<WindowId> {
+        let Some(state) = self.layouts.get_mut(layout) else {
+            return vec![];
+        };
+        let Some(wid) = state.selected else {
+            return vec![];
+        };
+        if !state.fullscreen_within_gaps.remove(&wid) {
+            state.fullscreen_within_gaps.insert(wid);
+            state.fullscreen.remove(&wid);
+        }
+        vec![wid]
+    }
+
+    fn join_selection_with_direction(&mut self, _layout: LayoutId, _direction: Direction) {}
+
+    fn join_selection_with_direction_level_restricted(
+        &mut self,
+        _layout: LayoutId,
+        _direction: Direction,
+    ) {
+    }
+
+    fn apply_stacking_to_parent_of_selection(
+        &mut self,
+        _: LayoutId,
+        _: crate::common::config::StackDefaultOrientation,
+    ) -> Vec<WindowId> {
+        vec![]
+    }
+
+    fn unstack_parent_of_selection(
+        &mut self,
+        _: LayoutId,
+        _: crate::common::config::StackDefaultOrientation,
+    ) -> Vec<WindowId> {
+        vec![]
+    }
+
+    fn parent_of_selection_is_stacked(&self, _layout: LayoutId) -> bool { false }
+
+    fn unjoin_selection(&mut self, _layout: LayoutId) {}
+
+    fn resize_selection_by(&mut self, layout: LayoutId, amount: f64) {
+        let settings = self.settings.clone();
+        self.resize_selection_with_settings(layout, amount, &settings);
+    }
+
+    fn rebalance(&mut self, layout: LayoutId) {
+        if let Some(state) = self.layouts.get_mut(layout) {
+            state.master_ratio = None;
+        }
+    }
+
+    fn toggle_tile_orientation(&mut self, layout: LayoutId) {
+        if let Some(state) = self.layouts.get_mut(layout) {
+            state.mirrored = !state.mirrored;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn w(idx: u32) -> WindowId { WindowId::new(1, idx) }
+
+    fn screen() -> CGRect { CGRect::new(CGPoint::new(0.0, 0.0), CGSize::new(5120.0, 1440.0)) }
+
+    fn frame_of(frames: &[(WindowId, CGRect)], wid: WindowId) -> CGRect {
+        frames.iter().find(|(w, _)| *w == wid).map(|(_, r)| *r).unwrap()
+    }
+
+    #[test]
+    fn single_window_is_centered_with_max_width() {
+        let mut system = CenteredMasterLayoutSystem::default();
+        let layout = system.create_layout();
+        system.add_window_after_selection(layout, w(1));
+
+        let settings = CenteredMasterSettings {
+            single_window_max_width: Some(2000.0),
+            ..Default::default()
+        };
+        let frames = system.calculate_layout_with_settings(
+            layout,
+            screen(),
+            &GapSettings::default(),
+            &settings,
+        );
+        let rect = frame_of(&frames, w(1));
+        assert_eq!(rect.size.width, 2000.0);
+        assert_eq!(rect.origin.x, 1560.0);
+        assert_eq!(rect.size.height, 1440.0);
+    }
+
+    #[test]
+    fn stack_windows_alternate_around_centered_master() {
+        let mut system = CenteredMasterLayoutSystem::default();
+        let layout = system.create_layout();
+        for i in 1..=5 {
+            system.add_window_after_selection(layout, w(i));
+        }
+
+        let frames = system.calculate_layout_with_settings(
+            layout,
+            screen(),
+            &GapSettings::default(),
+            &CenteredMasterSettings::default(),
+        );
+        let master = frame_of(&frames, w(1));
+        assert_eq!(master.origin.x, 1280.0);
+        assert_eq!(master.size.width, 2560.0);
+
+        // w2 and w4 go right, w3 and w5 go left.
+        assert_eq!(frame_of(&frames, w(2)).origin.x, 3840.0);
+        assert_eq!(frame_of(&frames, w(4)).origin.x, 3840.0);
+        assert_eq!(frame_of(&frames, w(3)).origin.x, 0.0);
+        assert_eq!(frame_of(&frames, w(5)).origin.x, 0.0);
+        assert_eq!(frame_of(&frames, w(4)).origin.y, 720.0);
+    }
+
+    #[test]
+    fn inserting_a_window_keeps_existing_columns() {
+        let mut system = CenteredMasterLayoutSystem::default();
+        let layout = system.create_layout();
+        for i in 1..=5 {
+            system.add_window_after_selection(layout, w(i));
+        }
+        let columns = |system: &CenteredMasterLayoutSystem| {
+            let state = &system.layouts[layout];
+            (1..=6)
+                .filter_map(|i| state.index_of(w(i)).map(|idx| (i, state.column_of(idx))))
+                .collect::<Vec<_>>()
+        };
+        let before = columns(&system);
+
+        system.select_window(layout, w(2));
+        system.add_window_after_selection(layout, w(6));
+        let after = columns(&system);
+        assert_eq!(after[..5], before[..]);
+        // w6 joins the right stack, whose turn it is, below the selected w2.
+        assert_eq!(after[5], (6, Column::Right));
+        let state = &system.layouts[layout];
+        let right: Vec<_> =
+            state.column(Column::Right).into_iter().map(|i| state.windows[i]).collect();
+        assert_eq!(right, vec![w(2), w(6), w(4)]);
+    }
+
+    #[test]
+    fn removing_a_window_keeps_other_columns() {
+        let mut system = CenteredMasterLayoutSystem::default();
+        let layout = system.create_layout();
+        for i in 1..=5 {
+            system.add_window_after_selection(layout, w(i));
+        }
+        let column = |system: &CenteredMasterLayoutSystem, i| {
+            let state = &system.layouts[layout];
+            state.column_of(state.index_of(w(i)).unwrap())
+        };
+
+        // w3 leaves the left stack, which may be one shorter than the right.
+        system.remove_window(w(3));
+        assert_eq!(column(&system, 2), Column::Right);
+        assert_eq!(column(&system, 4), Column::Right);
+        assert_eq!(column(&system, 5), Column::Left);
+
+        // w2 leaves the right stack, which must not be the shorter one, so it
+        // takes the bottom of the left stack rather than every window moving.
+        system.add_window_after_selection(layout, w(6));
+        assert_eq!(column(&system, 6), Column::Left);
+        system.remove_window(w(2));
+        assert_eq!(column(&system, 4), Column::Right);
+        assert_eq!(column(&system, 5), Column::Left);
+        assert_eq!(column(&system, 6), Column::Right);
+    }
+
+    #[test]
+    fn resizing_starts_from_the_display_ratio() {
+        let mut system = CenteredMasterLayoutSystem::default();
+        let layout = system.create_layout();
+        for i in 1..=3 {
+            system.add_window_after_selection(layout, w(i));
+        }
+        system.select_window(layout, w(1));
+        let display = CenteredMasterSettings {
+            master_ratio: 0.7,
+            ..Default::default()
+        };
+        system.resize_selection_with_settings(layout, 0.05, &display);
+        let ratio = system.layouts[layout].master_ratio.unwrap();
+        assert!((ratio - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn focus_moves_between_columns() {
+        let mut system = CenteredMasterLayoutSystem::default();
+        let layout = system.create_layout();
+        for i in 1..=3 {
+            system.add_window_after_selection(layout, w(i));
+        }
+        system.select_window(layout, w(1));
+
+        assert_eq!(system.move_focus(layout, Direction::Right).0, Some(w(2)));
+        assert_eq!(system.move_focus(layout, Direction::Left).0, Some(w(1)));
+        assert_eq!(system.move_focus(layout, Direction::Left).0, Some(w(3)));
+        assert_eq!(system.move_focus(layout, Direction::Left).0, None);
+    }
+}
@@ -3,7 +3,10 @@ use serde::{Deserialize, Serialize};
 use tracing::warn;
 
 use crate::actor::app::{WindowId, pid_t};
-use crate::layout_engine::systems::LayoutSystem;
+use crate::common::collections::HashMap;
+use crate::common::config::NormalizationSettings;
+use crate::layout_engine::snapshot::SnapshotNode;
+use crate::layout_engine::systems::{LayoutExtras, LayoutSystem};
 use crate::layout_engine::utils::compute_tiling_area;
 use crate::layout_engine::{Direction, LayoutId, LayoutKind, Orientation};
 use crate::model::selection::*;
@@ -14,6 +17,18 @@ use crate::sys::geometry::Round;
 pub struct TraditionalLayoutSystem {
     tree: Tree<Components>,
     layout_roots: slotmap::SlotMap<LayoutId, OwnedNode>,
+    #[serde(skip, default = "default_accordion_padding")]
+    accordion_padding: f64,
+    #[serde(skip)]
+    normalization: NormalizationSettings,
+    #[serde(skip)]
+    autotile: bool,
+    #[serde(skip)]
+    last_frames: HashMap<WindowId, CGRect>,
+}
+
+fn default_accordion_padding() -> f64 {
+    crate::common::config::StackSettings::default().accordion_padding
 }
 
 impl Default for TraditionalLayoutSystem {
@@ -21,11 +36,204 @@ impl Default for TraditionalLayoutSystem {
         Self {
             tree: Tree::with_observer(Components::default()),
             layout_roots: Default::default(),
+            accordion_padding: default_accordion_padding(),
+            normalization: NormalizationSettings::default(),
+            autotile: false,
+            last_frames: HashMap::default(),
         }
     }
 }
 
 impl TraditionalLayoutSystem {
+    /// Sets how much of each collapsed accordion child stays visible.
+    pub fn set_accordion_padding(&mut self, padding: f64) { self.accordion_padding = padding; }
+
+    /// Enables splitting the selected window along its longer side when a
+    /// window is added after it.
+    pub fn set_autotile(&mut self, autotile: bool) { self.autotile = autotile; }
+
+    /// Records the most recently calculated frames, used by autotiling to
+    /// decide which way to split the selected window.
+    pub fn set_last_frames(&mut self, frames: &[(WindowId, CGRect)]) {
+        self.last_frames.extend(frames.iter().copied());
+    }
+
+    /// Returns the orientation the selected window should be split along, or
+    /// `None` if autotiling is off or the window's frame is not known yet.
+    fn autotile_orientation(&self, selection: NodeId) -> Option<Orientation> {
+        if !self.autotile {
+            return None;
+        }
+        let frame = self.last_frames.get(&self.window_at(selection)?)?;
+        Some(if frame.size.width >= frame.size.height {
+            Orientation::Horizontal
+        } else {
+            Orientation::Vertical
+        })
+    }
+
+    /// Sets the normalization applied after structural changes and brings
+    /// existing layouts in line with it.
+    pub fn set_normalization(&mut self, settings: NormalizationSettings) {
+        self.normalization = settings;
+        let layouts: Vec<_> = self.layout_roots.keys().collect();
+        for layout in layouts {
+            self.normalize(layout);
+        }
+    }
+
+    /// Removes degenerate containers left behind by tree edits, according to
+    /// the configured normalization settings. Does nothing when disabled.
+    fn normalize(&mut self, layout: LayoutId) {
+        if !self.normalization.is_enabled() {
+            return;
+        }
+        let root = self.root(layout);
+        let mut selection = self.selection(layout);
+        let mut changed = false;
+        while let Some(next_selection) = self.normalize_step(root, selection) {
+            selection = next_selection;
+            changed = true;
+        }
+        if changed {
+            self.select(selection);
+        }
+    }
+
+    /// Applies a single normalization fix under `root`. Returns the node that
+    /// should hold the selection afterwards, or `None` if nothing changed.
+    fn normalize_step(&mut self, root: NodeId, selection: NodeId) -> Option<NodeId> {
+        let nodes: Vec<_> = root.traverse_postorder(self.map()).collect();
+        for node in nodes {
+            if self.window_at(node).is_some() {
+                continue;
+            }
+            let map = self.map();
+            let Some(first) = node.first_child(map) else { continue };
+            let parent = node.parent(map);
+
+            if self.normalization.flatten_containers && node.last_child(map) == Some(first) {
+                match parent {
+                    Some(_) => {
+                        first
+                            .detach(&mut self.tree)
+                            .insert_after(node)
+                            .with(|child, tree| {
+                                tree.data.layout.assume_size_of(child, node, &tree.map)
+                            })
+                            .finish();
+                        return Some(if selection == node { first } else { selection });
+                    }
+                    None if self.window_at(first).is_none() => {
+                        // The root wraps a lone container; take over its kind and children.
+                        let kind = self.layout(first);
+                        let replacement = self.local_selection_or_first_child(first);
+                        self.splice_children_into_parent(first);
+                        self.set_layout(node, kind);
+                        return Some(if selection == first { replacement } else { selection });
+                    }
+                    None => {}
+                }
+            }
+
+            let Some(parent) = parent else { continue };
+            let kind = self.layout(node);
+            let parent_kind = self.layout(parent);
+            if kind.is_group()
+                || parent_kind.is_group()
+                || kind.orientation() != parent_kind.orientation()
+            {
+                continue;
+            }
+            if self.normalization.opposite_orientation_for_nested_containers {
+                let opposite = match kind.orientation() {
+                    Orientation::Horizontal => Orientation::Vertical,
+                    Orientation::Vertical => Orientation::Horizontal,
+                };
+                self.set_layout(node, LayoutKind::from(opposite));
+                return Some(selection);
+            }
+            if self.normalization.flatten_containers {
+                let replacement = self.local_selection_or_first_child(node);
+                self.splice_children_into_parent(node);
+                return Some(if selection == node { replacement } else { selection });
+            }
+        }
+        None
+    }
+
+    fn local_selection_or_first_child(&self, container: NodeId) -> NodeId {
+        let map = self.map();
+        self.tree
+            .data
+            .selection
+            .local_selection(map, container)
+            .or_else(|| container.first_child(map))
+            .unwrap_or(container)
+    }
+
+    /// Moves the children of `container` into its parent in its place, keeping
+    /// the share of space each child had. The emptied container is removed.
+    fn splice_children_into_parent(&mut self, container: NodeId) {
+        let map = &self.tree.map;
+        let Some(parent) = container.parent(map) else { return };
+        let info = &self.tree.data.layout.info;
+        let container_size = info[container].size;
+        let total = info[container].total;
+        let count = container.children(map).count() as f32;
+        let children: Vec<(NodeId, f32)> = container
+            .children(map)
+            .map(|child| {
+                let share = if total > 0.0 { info[child].size / total } else { 1.0 / count };
+                (child, share * container_size)
+            })
+            .collect();
+
+        for &(child, _) in &children {
+            // Once a single child remains, the tree observer promotes it itself.
+            if child.parent(&self.tree.map) == Some(container) {
+                child.detach(&mut self.tree).insert_before(container).finish();
+            }
+        }
+
+        for (child, size) in children {
+            self.tree.data.layout.info[child].size = size;
+        }
+        let info = &self.tree.data.layout.info;
+        let total: f32 = parent.children(&self.tree.map).map(|child| info[child].size).sum();
+        self.tree.data.layout.info[parent].total = total;
+    }
+
+    /// Converts the stack containing the selection into an accordion of the
+    /// same orientation. Returns the windows that need raising, or an empty
+    /// list if the selection is not inside a stack.
+    pub fn convert_stack_of_selection_to_accordion(&mut self, layout: LayoutId) -> Vec<WindowId> {
+        let Some(container) = self.stacked_container_of_selection(layout) else {
+            return vec![];
+        };
+        let kind = self.layout(container);
+        if kind.is_accordion() {
+            return vec![];
+        }
+        self.set_layout(container, LayoutKind::accordion(kind.orientation()));
+        self.visible_windows_under_internal(container)
+    }
+
+    fn stacked_container_of_selection(&self, layout: LayoutId) -> Option<NodeId> {
+        let selection = self.selection(layout);
+        let map = self.map();
+        if self.tree.data.window.at(selection).is_some() {
+            selection
+                .ancestors(map)
+                .skip(1)
+                .find(|&ancestor| self.layout(ancestor).is_stacked())
+        } else if self.layout(selection).is_stacked() {
+            Some(selection)
+        } else {
+            selection.children(map).find(|&child| self.layout(child).is_stacked())
+        }
+    }
+
     fn find_best_focus_target(&self, node: NodeId) -> Option<(NodeId, WindowId)> {
         if let Some(wid) = self.tree.data.window.at(node) {
             return Some((node, wid));
@@ -400,6 +608,61 @@ impl LayoutSystem for TraditionalLayoutSystem {
         self.layout_roots.remove(layout).unwrap().remove(&mut self.tree)
     }
 
+    fn snapshot_layout(&self, layout: LayoutId) -> Option<SnapshotNode<WindowId>> {
+        self.snapshot_node(self.root(layout))
+    }
+
+    fn create_layout_from_snapshot(&mut self, snapshot: &SnapshotNode<WindowId>) -> LayoutId {
+        let layout = self.create_layout();
+        let root = self.root(layout);
+        match snapshot {
+            SnapshotNode::Window { window, .. } => {
+                self.add_window_under(layout, root, *window);
+            }
+            SnapshotNode::Container { kind, children, .. } => {
+                self.set_layout(root, *kind);
+                self.build_snapshot_children(layout, root, children);
+            }
+        }
+        if let Some(&first) = snapshot.windows().first() {
+            self.select_window(layout, *first);
+        }
+        layout
+    }
+
+    fn layout_extras(&self, layout: LayoutId) -> LayoutExtras {
+        let mut extras = LayoutExtras::default();
+        for node in self.root(layout).traverse_preorder(self.map()) {
+            let Some(wid) = self.window_at(node) else {
+                continue;
+            };
+            let info = &self.tree.data.layout.info[node];
+            if info.is_fullscreen {
+                extras.fullscreen.push(wid);
+            }
+            if info.is_fullscreen_within_gaps {
+                extras.fullscreen_within_gaps.push(wid);
+            }
+        }
+        extras.fullscreen.sort_unstable();
+        extras.fullscreen_within_gaps.sort_unstable();
+        extras
+    }
+
+    fn set_layout_extras(&mut self, layout: LayoutId, extras: &LayoutExtras) {
+        let windows: Vec<(NodeId, WindowId)> = self
+            .root(layout)
+            .traverse_preorder(self.map())
+            .filter_map(|node| Some((node, self.window_at(node)?)))
+            .collect();
+        for (node, wid) in windows {
+            let fullscreen = extras.fullscreen.contains(&wid);
+            let within_gaps = !fullscreen && extras.fullscreen_within_gaps.contains(&wid);
+            self.tree.data.layout.set_fullscreen(node, fullscreen);
+            self.tree.data.layout.set_fullscreen_within_gaps(node, within_gaps);
+        }
+    }
+
     fn draw_tree(&self, layout: LayoutId) -> String {
         let tree = self.get_ascii_tree(self.root(layout));
         let mut out = String::new();
@@ -433,11 +696,13 @@ impl LayoutSystem for TraditionalLayoutSystem {
         self.tree.data.layout.apply_with_gaps(
             &self.tree.map,
             &self.tree.data.window,
+            &self.tree.data.selection,
             self.root(layout),
             tiling_area,
             screen,
             &mut sizes,
             stack_offset,
+            self.accordion_padding,
             gaps,
             stack_line_thickness,
             stack_line_horiz,
@@ -668,28 +933,60 @@ impl LayoutSystem for TraditionalLayoutSystem {
 
     fn add_window_after_selection(&mut self, layout: LayoutId, wid: WindowId) {
         let selection = self.selection(layout);
-        let node = if selection.parent(self.map()).is_none() {
+        let parent = selection.parent(self.map());
+        let autotile = parent
+            .filter(|&parent| !self.layout(parent).is_group())
+            .and(self.autotile_orientation(selection));
+        let node = if parent.is_none() {
             self.add_window_under(layout, selection, wid)
+        } else if let Some(orientation) = autotile {
+            let parent = parent.unwrap();
+            if self.layout(parent).orientation() != orientation {
+                self.nest_in_container_internal(layout, selection, LayoutKind::from(orientation));
+            }
+            self.smart_window_insertion(layout, selection, wid)
         } else {
             let node = self.smart_window_insertion(layout, selection, wid);
             node
         };
         self.select(node);
+        self.normalize(layout);
     }
 
     fn remove_window(&mut self, wid: WindowId) {
-        let nodes: Vec<_> =
-            self.tree.data.window.take_nodes_for(wid).map(|(_, node)| node).collect();
-        for node in nodes {
+        self.last_frames.remove(&wid);
+        let nodes: Vec<_> = self.tree.data.window.take_nodes_for(wid).collect();
+        let mut layouts = Vec::new();
+        for (layout, node) in nodes {
             node.detach(&mut self.tree).remove();
+            if !layouts.contains(&layout) {
+                layouts.push(layout);
+            }
+        }
+        for layout in layouts {
+            self.normalize(layout);
         }
     }
 
+    fn replace_window(&mut self, old: WindowId, new: WindowId) {
+        if let Some(frame) = self.last_frames.remove(&old) {
+            self.last_frames.insert(new, frame);
+        }
+        self.tree.data.window.replace(old, new);
+    }
+
     fn remove_windows_for_app(&mut self, pid: pid_t) {
-        let nodes: Vec<_> =
-            self.tree.data.window.take_nodes_for_app(pid).map(|(_, _, node)| node).collect();
-        for node in nodes {
+        self.last_frames.retain(|wid, _| wid.pid != pid);
+        let nodes: Vec<_> = self.tree.data.window.take_nodes_for_app(pid).collect();
+        let mut layouts = Vec::new();
+        for (_, layout, node) in nodes {
             node.detach(&mut self.tree).remove();
+            if !layouts.contains(&layout) {
+                layouts.push(layout);
+            }
+        }
+        for layout in layouts {
+            self.normalize(layout);
         }
     }
 
@@ -730,6 +1027,7 @@ impl LayoutSystem for TraditionalLayoutSystem {
                 (None, None) => break,
             }
         }
+        self.normalize(layout);
     }
 
     fn has_windows_for_app(&self, layout: LayoutId, pid: pid_t) -> bool {
@@ -785,7 +1083,9 @@ impl LayoutSystem for TraditionalLayoutSystem {
         // Clear selection range when moving node
         self.tree.data.selection.clear_range(&self.tree.map, selection);
         
-        self.move_node(layout, selection, direction)
+        let moved = self.move_node(layout, selection, direction);
+        self.normalize(layout);
+        moved
     }
 
     fn move_selection_level_restricted(&mut self, layout: LayoutId, direction: Direction) -> bool {
@@ -828,6 +1128,12 @@ impl LayoutSystem for TraditionalLayoutSystem {
         } else {
             from_sel.detach(&mut self.tree).insert_after(to_sel);
         }
+        // The subtree keeps its nodes, so only the window index needs to learn
+        // which layout they belong to now.
+        let moved: Vec<_> = from_sel.traverse_preorder(&self.tree.map).collect();
+        for node in moved {
+            self.tree.data.window.move_to_layout(node, to_layout);
+        }
         if is_selection {
             for node in from_sel.ancestors(&self.tree.map) {
                 if node == old_parent {
@@ -836,6 +1142,8 @@ impl LayoutSystem for TraditionalLayoutSystem {
                 self.tree.data.selection.select_locally(&self.tree.map, node);
             }
         }
+        self.normalize(from_layout);
+        self.normalize(to_layout);
     }
 
     fn split_selection(&mut self, layout: LayoutId, kind: LayoutKind) {
@@ -867,6 +1175,7 @@ impl LayoutSystem for TraditionalLayoutSystem {
         // Only join with direct siblings at the current level
         if let Some(target) = self.move_over(selection, direction) {
             self.perform_natural_join(layout, selection, target, direction);
+            self.normalize(layout);
         }
     }
 
@@ -920,6 +1229,7 @@ impl LayoutSystem for TraditionalLayoutSystem {
                     }
                 }
                 self.select(selection);
+                self.normalize(layout);
                 return;
             }
 
@@ -929,6 +1239,7 @@ impl LayoutSystem for TraditionalLayoutSystem {
             } else {
                 let _ = self.descend_selection(layout);
             }
+            self.normalize(layout);
         }
     }
 
@@ -951,6 +1262,8 @@ impl LayoutSystem for TraditionalLayoutSystem {
             let new_layout = match current_layout {
                 LayoutKind::HorizontalStack => Some(LayoutKind::VerticalStack),
                 LayoutKind::VerticalStack => Some(LayoutKind::HorizontalStack),
+                LayoutKind::HorizontalAccordion => Some(LayoutKind::VerticalAccordion),
+                LayoutKind::VerticalAccordion => Some(LayoutKind::HorizontalAccordion),
                 LayoutKind::Horizontal => match default_orientation {
                     crate::common::config::StackDefaultOrientation::Perpendicular => {
                         Some(LayoutKind::VerticalStack)
@@ -1002,54 +1315,42 @@ impl LayoutSystem for TraditionalLayoutSystem {
         layout: LayoutId,
         default_orientation: crate::common::config::StackDefaultOrientation,
     ) -> Vec<WindowId> {
-        let selection = self.selection(layout);
-
-        let target_container = if self.tree.data.window.at(selection).is_some() {
-            let map = self.map();
-            selection
-                .ancestors(map)
-                .skip(1)
-                .find(|&ancestor| self.layout(ancestor).is_stacked())
-        } else {
-            let selection_layout = self.layout(selection);
-            if selection_layout.is_stacked() {
-                Some(selection)
-            } else {
-                let map = self.map();
-                selection.children(map).find(|&child| self.layout(child).is_stacked())
-            }
-        };
+        let target_container = self.stacked_container_of_selection(layout);
 
         if let Some(container) = target_container {
             let new_layout = match self.layout(container) {
-                LayoutKind::HorizontalStack => match default_orientation {
-                    crate::common::config::StackDefaultOrientation::Perpendicular => {
-                        Some(LayoutKind::Vertical)
-                    }
-                    crate::common::config::StackDefaultOrientation::Same => {
-                        Some(LayoutKind::Horizontal)
-                    }
-                    crate::common::config::StackDefaultOrientation::Horizontal => {
-                        Some(LayoutKind::Horizontal)
-                    }
-                    crate::common::config::StackDefaultOrientation::Vertical => {
-                        Some(LayoutKind::Vertical)
-                    }
-                },
-                LayoutKind::VerticalStack => match default_orientation {
-                    crate::common::config::StackDefaultOrientation::Perpendicular => {
-                        Some(LayoutKind::Horizontal)
-                    }
-                    crate::common::config::StackDefaultOrientation::Same => {
-                        Some(LayoutKind::Vertical)
-                    }
-                    crate::common::config::StackDefaultOrientation::Horizontal => {
-                        Some(LayoutKind::Horizontal)
+                LayoutKind::HorizontalStack | LayoutKind::HorizontalAccordion => {
+                    match default_orientation {
+                        crate::common::config::StackDefaultOrientation::Perpendicular => {
+                            Some(LayoutKind::Vertical)
+                        }
+                        crate::common::config::StackDefaultOrientation::Same => {
+                            Some(LayoutKind::Horizontal)
+                        }
+                        crate::common::config::StackDefaultOrientation::Horizontal => {
+                            Some(LayoutKind::Horizontal)
+                        }
+                        crate::common::config::StackDefaultOrientation::Vertical => {
+                            Some(LayoutKind::Vertical)
+                        }
                     }
-                    crate::common::config::StackDefaultOrientation::Vertical => {
-                        Some(LayoutKind::Vertical)
+                }
+                LayoutKind::VerticalStack | LayoutKind::VerticalAccordion => {
+                    match default_orientation {
+                        crate::common::config::StackDefaultOrientation::Perpendicular => {
+                            Some(LayoutKind::Horizontal)
+                        }
+                        crate::common::config::StackDefaultOrientation::Same => {
+                            Some(LayoutKind::Vertical)
+                        }
+                        crate::common::config::StackDefaultOrientation::Horizontal => {
+                            Some(LayoutKind::Horizontal)
+                        }
+                        crate::common::config::StackDefaultOrientation::Vertical => {
+                            Some(LayoutKind::Vertical)
+                        }
                     }
-                },
+                }
                 _ => None,
             };
 
@@ -1111,6 +1412,7 @@ impl LayoutSystem for TraditionalLayoutSystem {
                     self.remove_unnecessary_container_internal(parent);
                 }
             }
+            self.normalize(layout);
         }
     }
 
@@ -1145,6 +1447,7 @@ impl LayoutSystem for TraditionalLayoutSystem {
             self.select(selection);
         }
         
+        self.normalize(layout);
         true
     }
 
@@ -1207,6 +1510,7 @@ impl LayoutSystem for TraditionalLayoutSystem {
                 parent.detach(&mut self.tree).remove();
             }
             
+            self.normalize(layout);
             return true;
         }
         
@@ -1272,6 +1576,7 @@ impl LayoutSystem for TraditionalLayoutSystem {
                 parent.detach(&mut self.tree).remove();
             }
             
+            self.normalize(layout);
             return true;
         }
         
@@ -1295,8 +1600,17 @@ impl LayoutSystem for TraditionalLayoutSystem {
             return Vec::new();
         };
         
-        // Get the parent's layout kind to use for the new container
-        let parent_layout = self.layout(parent);
+        // Get the parent's layout kind to use for the new container. When the
+        // tree is normalized, a same-orientation group would be merged straight
+        // back into its parent, so use the perpendicular orientation instead.
+        let parent_layout = if self.normalization.is_enabled() {
+            match self.layout(parent).orientation() {
+                Orientation::Horizontal => LayoutKind::Vertical,
+                Orientation::Vertical => LayoutKind::Horizontal,
+            }
+        } else {
+            self.layout(parent)
+        };
         
         // Create a new container node and insert it where the first selection is
         let container = self.tree.mk_node().insert_before(first_node);
@@ -1464,6 +1778,7 @@ impl LayoutSystem for TraditionalLayoutSystem {
                 }
             }
             
+            self.normalize(layout);
             true
         } else {
             // Original behavior: ungroup all siblings from the parent
@@ -1504,6 +1819,7 @@ impl LayoutSystem for TraditionalLayoutSystem {
                 self.select(sel);
             }
             
+            self.normalize(layout);
             true
         }
     }
@@ -1544,11 +1860,21 @@ impl LayoutSystem for TraditionalLayoutSystem {
     }
 
     fn swap_windows(&mut self, layout: LayoutId, a: WindowId, b: WindowId) -> bool {
-        let node_a = match self.tree.data.window.node_for(layout, a) {
+        self.swap_windows_between_layouts(layout, a, layout, b)
+    }
+
+    fn swap_windows_between_layouts(
+        &mut self,
+        layout_a: LayoutId,
+        a: WindowId,
+        layout_b: LayoutId,
+        b: WindowId,
+    ) -> bool {
+        let node_a = match self.tree.data.window.node_for(layout_a, a) {
             Some(n) => n,
             None => return false,
         };
-        let node_b = match self.tree.data.window.node_for(layout, b) {
+        let node_b = match self.tree.data.window.node_for(layout_b, b) {
             Some(n) => n,
             None => return false,
         };
@@ -1578,14 +1904,16 @@ impl LayoutSystem for TraditionalLayoutSystem {
 
         if let Some(infos) = self.tree.data.window.window_nodes.get_mut(&a) {
             for info in &mut infos.0 {
-                if info.layout == layout {
+                if info.layout == layout_a && info.node == node_a {
+                    info.layout = layout_b;
                     info.node = node_b;
                 }
             }
         }
         if let Some(infos) = self.tree.data.window.window_nodes.get_mut(&b) {
             for info in &mut infos.0 {
-                if info.layout == layout {
+                if info.layout == layout_b && info.node == node_b {
+                    info.layout = layout_a;
                     info.node = node_a;
                 }
             }
@@ -1940,6 +2268,20 @@ impl TraditionalLayoutSystem {
             }
             crate::layout_engine::LayoutKind::HorizontalStack
             | crate::layout_engine::LayoutKind::VerticalStack => parent_rect,
+            crate::layout_engine::LayoutKind::HorizontalAccordion
+            | crate::layout_engine::LayoutKind::VerticalAccordion => {
+                let is_horizontal =
+                    parent_kind == crate::layout_engine::LayoutKind::HorizontalAccordion;
+                let selected_idx = self
+                    .tree
+                    .data
+                    .selection
+                    .local_selection(map, parent_node)
+                    .and_then(|sel| siblings.iter().position(|&n| n == sel))
+                    .unwrap_or(0);
+                StackLayoutResult::new_accordion(parent_rect, self.accordion_padding, is_horizontal)
+                    .get_accordion_frame_for_index(child_index, selected_idx, siblings.len())
+            }
         }
     }
 }
@@ -2077,9 +2419,10 @@ impl TraditionalLayoutSystem {
                     } else {
                         details.title.clone()
                     };
-                    format!("{desc} {:?} {} | \"{}\" ({}) [{:.0}x{:.0}]", 
+                    format!("{desc} {:?} {} | \"{}\" ({}) [{:.0}x{:.0}]{}", 
                         wid, layout_info, title, bundle,
-                        details.frame.size.width, details.frame.size.height)
+                        details.frame.size.width, details.frame.size.height,
+                        details.marks_label())
                 } else {
                     format!("{desc} {:?} {}", wid, layout_info)
                 }
@@ -2106,6 +2449,51 @@ impl TraditionalLayoutSystem {
 
     fn window_at(&self, node: NodeId) -> Option<WindowId> { self.tree.data.window.at(node) }
 
+    fn snapshot_node(&self, node: NodeId) -> Option<SnapshotNode<WindowId>> {
+        let weight = self.tree.data.layout.proportion(self.map(), node).unwrap_or(1.0);
+        if let Some(window) = self.window_at(node) {
+            return Some(SnapshotNode::Window { window, weight });
+        }
+        let children: Vec<_> =
+            node.children(self.map()).filter_map(|child| self.snapshot_node(child)).collect();
+        if children.is_empty() {
+            return None;
+        }
+        Some(SnapshotNode::Container {
+            kind: self.layout(node),
+            weight,
+            children,
+        })
+    }
+
+    /// Adds the nodes described by `children` under `parent`, sized so that
+    /// each sibling's share matches its weight.
+    fn build_snapshot_children(
+        &mut self,
+        layout: LayoutId,
+        parent: NodeId,
+        children: &[SnapshotNode<WindowId>],
+    ) {
+        let total: f64 = children.iter().map(|child| child.weight().max(0.0)).sum();
+        for child in children {
+            let node = match child {
+                SnapshotNode::Window { window, .. } => {
+                    self.add_window_under(layout, parent, *window)
+                }
+                SnapshotNode::Container { kind, children, .. } => {
+                    let node = self.tree.mk_node().push_back(parent);
+                    self.set_layout(node, *kind);
+                    self.build_snapshot_children(layout, node, children);
+                    node
+                }
+            };
+            if total > 0.0 {
+                let size = child.weight().max(0.0) / total * children.len() as f64;
+                self.tree.data.layout.set_size(&self.tree.map, node, size as f32);
+            }
+        }
+    }
+
     fn window_in_direction_from(&self, node: NodeId, direction: Direction) -> Option<WindowId> {
         if let Some(window) = self.window_at(node) {
             return Some(window);
@@ -2669,6 +3057,26 @@ impl Window {
             .push(WindowNodeInfo { layout, node });
     }
 
+    fn move_to_layout(&mut self, node: NodeId, layout: LayoutId) {
+        let Some(&wid) = self.windows.get(node) else { return };
+        if let Some(window_nodes) = self.window_nodes.get_mut(&wid) {
+            for info in window_nodes.0.iter_mut().filter(|info| info.node == node) {
+                info.layout = layout;
+            }
+        }
+    }
+
+    fn replace(&mut self, old: WindowId, new: WindowId) {
+        if self.window_nodes.contains_key(&new) {
+            return;
+        }
+        let Some(infos) = self.window_nodes.remove(&old) else { return };
+        for info in &infos.0 {
+            self.windows.insert(info.node, new);
+        }
+        self.window_nodes.insert(new, infos);
+    }
+
     fn take_nodes_for(&mut self, wid: WindowId) -> impl Iterator<Item = (LayoutId, NodeId)> {
         self.window_nodes
             .remove(&wid)
@@ -2758,6 +3166,50 @@ impl StackLayoutResult {
         }
     }
 
+    /// Accordion containers give every child the full container minus a
+    /// `padding` strip on each side, so collapsed siblings peek out from under
+    /// the selected child.
+    fn new_accordion(container_rect: CGRect, padding: f64, is_horizontal: bool) -> Self {
+        Self {
+            container_rect,
+            stack_offset: padding.max(0.0),
+            is_horizontal,
+            window_width: container_rect.size.width,
+            window_height: container_rect.size.height,
+        }
+    }
+
+    fn get_accordion_frame_for_index(
+        &self,
+        index: usize,
+        selected_idx: usize,
+        count: usize,
+    ) -> CGRect {
+        let padding = self.stack_offset;
+        let last = count.saturating_sub(1);
+        let (lead, trail) = if index < selected_idx {
+            (0.0, if selected_idx == last { padding } else { 2.0 * padding })
+        } else if index > selected_idx {
+            (if selected_idx == 0 { padding } else { 2.0 * padding }, 0.0)
+        } else {
+            (
+                if index > 0 { padding } else { 0.0 },
+                if index < last { padding } else { 0.0 },
+            )
+        };
+        let mut frame = self.container_rect;
+        if self.is_horizontal {
+            let width = (self.window_width - lead - trail).max(0.0);
+            frame.origin.x += lead;
+            frame.size.width = width;
+        } else {
+            let height = (self.window_height - lead - trail).max(0.0);
+            frame.origin.y += lead;
+            frame.size.height = height;
+        }
+        frame.round()
+    }
+
     fn get_frame_for_index(&self, index: usize) -> CGRect {
         use objc2_core_foundation::{CGPoint, CGSize};
         let offset_amount = index as f64 * self.stack_offset;
@@ -2881,6 +3333,12 @@ impl Layout {
 
     fn kind(&self, node: NodeId) -> LayoutKind { self.info[node].kind }
 
+    fn set_size(&mut self, map: &NodeMap, node: NodeId, size: f32) {
+        let Some(parent) = node.parent(map) else { return };
+        self.info[parent].total += size - self.info[node].size;
+        self.info[node].size = size;
+    }
+
     fn proportion(&self, map: &NodeMap, node: NodeId) -> Option<f64> {
         let Some(parent) = node.parent(map) else { return None };
         Some(f64::from(self.info[node].size) / f64::from(self.info[parent].total))
@@ -2956,11 +3414,13 @@ impl Layout {
         &self,
         map: &NodeMap,
         window: &Window,
+        selection: &Selection,
         node: NodeId,
         rect: CGRect,
         screen: CGRect,
         sizes: &mut Vec<(WindowId, CGRect)>,
         stack_offset: f64,
+        accordion_padding: f64,
         gaps: &crate::common::config::GapSettings,
         stack_line_thickness: f64,
         stack_line_horiz: crate::common::config::HorizontalPlacement,
@@ -3017,11 +3477,44 @@ impl Layout {
                     self.apply_with_gaps(
                         map,
                         window,
+                        selection,
                         child,
                         frame,
                         screen,
                         sizes,
                         stack_offset,
+                        accordion_padding,
+                        gaps,
+                        stack_line_thickness,
+                        stack_line_horiz,
+                        stack_line_vert,
+                    );
+                }
+            }
+            HorizontalAccordion | VerticalAccordion => {
+                let children: Vec<_> = node.children(map).collect();
+                if children.is_empty() {
+                    return;
+                }
+                let is_horizontal = matches!(info.kind, HorizontalAccordion);
+                let layout = StackLayoutResult::new_accordion(rect, accordion_padding, is_horizontal);
+                let selected_idx = selection
+                    .local_selection(map, node)
+                    .and_then(|sel| children.iter().position(|&c| c == sel))
+                    .unwrap_or(0);
+                for (idx, &child) in children.iter().enumerate() {
+                    let frame =
+                        layout.get_accordion_frame_for_index(idx, selected_idx, children.len());
+                    self.apply_with_gaps(
+                        map,
+                        window,
+                        selection,
+                        child,
+                        frame,
+                        screen,
+                        sizes,
+                        stack_offset,
+                        accordion_padding,
                         gaps,
                         stack_line_thickness,
                         stack_line_horiz,
@@ -3032,11 +3525,13 @@ impl Layout {
             Horizontal => self.layout_axis(
                 map,
                 window,
+                selection,
                 node,
                 rect,
                 screen,
                 sizes,
                 stack_offset,
+                accordion_padding,
                 gaps,
                 true,
                 stack_line_thickness,
@@ -3046,11 +3541,13 @@ impl Layout {
             Vertical => self.layout_axis(
                 map,
                 window,
+                selection,
                 node,
                 rect,
                 screen,
                 sizes,
                 stack_offset,
+                accordion_padding,
                 gaps,
                 false,
                 stack_line_thickness,
@@ -3064,11 +3561,13 @@ impl Layout {
         &self,
         map: &NodeMap,
         window: &Window,
+        selection: &Selection,
         node: NodeId,
         rect: CGRect,
         screen: CGRect,
         sizes: &mut Vec<(WindowId, CGRect)>,
         stack_offset: f64,
+        accordion_padding: f64,
         gaps: &crate::common::config::GapSettings,
         horizontal: bool,
         stack_line_thickness: f64,
@@ -3152,11 +3651,13 @@ impl Layout {
             self.apply_with_gaps(
                 map,
                 window,
+                selection,
                 child,
                 child_rect,
                 screen,
                 sizes,
                 stack_offset,
+                accordion_padding,
                 gaps,
                 stack_line_thickness,
                 stack_line_horiz,
@@ -3240,6 +3741,114 @@ mod tests {
         assert_eq!(system.window_in_direction(layout, Direction::Up), Some(w(2)));
     }
 
+    #[test]
+    fn move_selection_to_layout_keeps_container_structure() {
+        let mut system = TraditionalLayoutSystem::default();
+        let source = system.create_layout();
+        let target = system.create_layout();
+        let source_root = system.root(source);
+        system.tree.data.layout.set_kind(source_root, LayoutKind::Horizontal);
+        system.add_window_under(source, source_root, w(1));
+        let container = system.tree.mk_node().push_back(source_root);
+        system.tree.data.layout.set_kind(container, LayoutKind::VerticalStack);
+        system.add_window_under(source, container, w(2));
+        system.add_window_under(source, container, w(3));
+        system.add_window_after_selection(target, w(4));
+        system.select(container);
+
+        let moved_lines = |tree: &str| -> Vec<String> {
+            let (a, b) = (format!("{:?}", w(2)), format!("{:?}", w(3)));
+            tree.lines().filter(|l| l.contains(&a) || l.contains(&b)).map(str::to_owned).collect()
+        };
+        let before = moved_lines(&system.draw_tree(source));
+        assert_eq!(before.len(), 2);
+        assert!(moved_lines(&system.draw_tree(target)).is_empty());
+
+        system.move_selection_to_layout_after_selection(source, target);
+
+        let source_tree = system.draw_tree(source);
+        assert!(moved_lines(&source_tree).is_empty());
+        assert!(source_tree.contains(&format!("{:?}", w(1))));
+        assert_eq!(moved_lines(&system.draw_tree(target)), before);
+        assert_eq!(container.parent(system.map()), Some(system.root(target)));
+        assert_eq!(system.layout(container), LayoutKind::VerticalStack);
+        assert!(system.contains_window(target, w(2)));
+        assert!(system.contains_window(target, w(3)));
+        assert!(!system.contains_window(source, w(2)));
+    }
+
+    #[test]
+    fn swap_windows_between_layouts_trades_positions() {
+        let mut system = TraditionalLayoutSystem::default();
+        let first = system.create_layout();
+        let second = system.create_layout();
+        system.add_window_after_selection(first, w(1));
+        system.add_window_after_selection(first, w(2));
+        system.add_window_after_selection(second, w(3));
+
+        let before = system.draw_tree(first);
+        assert!(system.swap_windows_between_layouts(first, w(1), second, w(3)));
+        let after = system.draw_tree(first);
+        assert_eq!(after, before.replace(&format!("{:?}", w(1)), &format!("{:?}", w(3))));
+        assert_eq!(system.visible_windows_in_layout(second), vec![w(1)]);
+        assert!(!system.contains_window(first, w(1)));
+        assert!(!system.contains_window(second, w(3)));
+
+        // Windows must live in the layouts they are swapped from.
+        assert!(!system.swap_windows_between_layouts(first, w(1), second, w(2)));
+    }
+
+    #[test]
+    fn replace_window_keeps_position_in_every_layout() {
+        let mut system = TraditionalLayoutSystem::default();
+        let first = system.create_layout();
+        let second = system.create_layout();
+        system.add_window_after_selection(first, w(1));
+        system.add_window_after_selection(first, w(2));
+        system.add_window_after_selection(second, w(2));
+
+        let before = system.draw_tree(first);
+        system.replace_window(w(2), w(9));
+        let after = system.draw_tree(first);
+        assert_eq!(after, before.replace(&format!("{:?}", w(2)), &format!("{:?}", w(9))));
+        assert_eq!(system.visible_windows_in_layout(second), vec![w(9)]);
+        assert!(!system.contains_window(first, w(2)));
+        assert_eq!(system.selected_window(first), Some(w(9)));
+    }
+
+    #[test]
+    fn layout_snapshot_round_trips() {
+        let leaf = |window, weight| SnapshotNode::Window { window, weight };
+        let snapshot = SnapshotNode::Container {
+            kind: LayoutKind::Horizontal,
+            weight: 1.0,
+            children: vec![leaf(w(1), 3.0), SnapshotNode::Container {
+                kind: LayoutKind::VerticalStack,
+                weight: 1.0,
+                children: vec![leaf(w(2), 1.0), leaf(w(3), 1.0)],
+            }],
+        };
+
+        let mut system = TraditionalLayoutSystem::default();
+        let layout = system.create_layout_from_snapshot(&snapshot);
+        assert_eq!(system.selected_window(layout), Some(w(1)));
+
+        let Some(SnapshotNode::Container { kind, children, .. }) = system.snapshot_layout(layout)
+        else {
+            panic!("expected a container at the root");
+        };
+        assert_eq!(kind, LayoutKind::Horizontal);
+        assert!((children[0].weight() - 0.75).abs() < 1e-3);
+        assert!(matches!(
+            &children[1],
+            SnapshotNode::Container { kind: LayoutKind::VerticalStack, children, .. }
+                if children.len() == 2
+        ));
+        let windows: Vec<WindowId> =
+            children.iter().flat_map(|c| c.windows()).copied().collect();
+        assert_eq!(windows, vec![w(1), w(2), w(3)]);
+    }
+
     struct TestTraditionalLayoutSystem {
         system: TraditionalLayoutSystem,
         _root: OwnedNode,
@@ -3873,4 +4482,298 @@ mod tests {
         assert_eq!(window_node.parent(&system.tree.map), Some(sibling_container),
                    "Window should be moved to sibling container");
     }
+
+    #[test]
+    fn accordion_collapses_unselected_children_to_padding() {
+        let mut system = TraditionalLayoutSystem::default();
+        system.set_accordion_padding(30.0);
+        let layout = system.create_layout();
+        let root = system.root(layout);
+        system.tree.data.layout.set_kind(root, LayoutKind::HorizontalAccordion);
+
+        system.add_window_after_selection(layout, w(1));
+        system.add_window_after_selection(layout, w(2));
+        system.add_window_after_selection(layout, w(3));
+        system.select_window(layout, w(2));
+
+        let screen = CGRect::new(CGPoint::new(0.0, 0.0), CGSize::new(1000.0, 800.0));
+        let frames = system.calculate_layout(
+            layout,
+            screen,
+            0.0,
+            &crate::common::config::GapSettings::default(),
+            0.0,
+            Default::default(),
+            Default::default(),
+        );
+        let frame = |wid| frames.iter().find(|(w, _)| *w == wid).unwrap().1;
+
+        assert_eq!(frame(w(1)).origin.x, 0.0);
+        assert_eq!(frame(w(1)).size.width, 940.0);
+        assert_eq!(frame(w(2)).origin.x, 30.0);
+        assert_eq!(frame(w(2)).size.width, 940.0);
+        assert_eq!(frame(w(3)).origin.x, 60.0);
+        assert_eq!(frame(w(3)).size.height, 800.0);
+    }
+
+    #[test]
+    fn toggle_stack_cycles_through_accordion() {
+        use crate::common::config::StackDefaultOrientation;
+
+        let mut system = TraditionalLayoutSystem::default();
+        let layout = system.create_layout();
+        let root = system.root(layout);
+        system.tree.data.layout.set_kind(root, LayoutKind::Horizontal);
+        system.add_window_after_selection(layout, w(1));
+        system.add_window_after_selection(layout, w(2));
+
+        let _ = system.apply_stacking_to_parent_of_selection(layout, StackDefaultOrientation::Same);
+        assert_eq!(system.layout(root), LayoutKind::HorizontalStack);
+
+        assert!(!system.convert_stack_of_selection_to_accordion(layout).is_empty());
+        assert_eq!(system.layout(root), LayoutKind::HorizontalAccordion);
+        assert!(system.convert_stack_of_selection_to_accordion(layout).is_empty());
+
+        let _ = system.unstack_parent_of_selection(layout, StackDefaultOrientation::Same);
+        assert_eq!(system.layout(root), LayoutKind::Horizontal);
+    }
+
+    fn assert_normalized(system: &TraditionalLayoutSystem, layout: LayoutId) {
+        let map = system.map();
+        let root = system.root(layout);
+        for node in root.traverse_preorder(map) {
+            if system.window_at(node).is_some() {
+                continue;
+            }
+            let children: Vec<_> = node.children(map).collect();
+            if node != root {
+                assert!(children.len() > 1, "container {node:?} has {} children", children.len());
+            } else if let [only] = children[..] {
+                assert!(system.window_at(only).is_some(), "root wraps a lone container");
+            }
+            let total: f32 = children.iter().map(|&c| system.tree.data.layout.info[c].size).sum();
+            assert!((total - system.tree.data.layout.info[node].total).abs() < 1e-4);
+            if let Some(parent) = node.parent(map) {
+                let (kind, parent_kind) = (system.layout(node), system.layout(parent));
+                if !kind.is_group() && !parent_kind.is_group() {
+                    assert_ne!(
+                        kind.orientation(),
+                        parent_kind.orientation(),
+                        "{node:?} nests the same orientation as its parent"
+                    );
+                }
+            }
+        }
+    }
+
+    fn flatten_settings() -> NormalizationSettings {
+        NormalizationSettings {
+            flatten_containers: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn normalization_keeps_invariants_across_tree_edits() {
+        let mut system = TraditionalLayoutSystem::default();
+        system.set_normalization(flatten_settings());
+        let layout = system.create_layout();
+        let root = system.root(layout);
+        system.tree.data.layout.set_kind(root, LayoutKind::Horizontal);
+
+        for i in 1..=4 {
+            system.add_window_after_selection(layout, w(i));
+            assert_normalized(&system, layout);
+        }
+
+        system.select_window(layout, w(2));
+        system.join_selection_with_direction(layout, Direction::Down);
+        assert_normalized(&system, layout);
+
+        system.select_window(layout, w(3));
+        assert!(system.ungroup_selection(layout));
+        assert_normalized(&system, layout);
+
+        system.select_window(layout, w(1));
+        system.join_selection_with_direction(layout, Direction::Down);
+        assert_normalized(&system, layout);
+        assert!(system.ungroup_siblings(layout));
+        assert_normalized(&system, layout);
+
+        system.select_window(layout, w(4));
+        assert!(system.move_selection(layout, Direction::Up));
+        assert_normalized(&system, layout);
+        assert!(system.move_selection(layout, Direction::Left));
+        assert_normalized(&system, layout);
+
+        system.remove_window(w(2));
+        assert_normalized(&system, layout);
+
+        let mut windows = system.visible_windows_in_layout(layout);
+        windows.sort();
+        assert_eq!(windows, vec![w(1), w(3), w(4)]);
+    }
+
+    #[test]
+    fn normalization_merges_nested_same_orientation_containers() {
+        let mut system = TraditionalLayoutSystem::default();
+        let layout = system.create_layout();
+        let root = system.root(layout);
+        system.tree.data.layout.set_kind(root, LayoutKind::Horizontal);
+
+        // root(H) -> [w1, H -> [w2, V -> [w3]]]
+        system.add_window_under(layout, root, w(1));
+        let nested = system.tree.mk_node().push_back(root);
+        system.tree.data.layout.set_kind(nested, LayoutKind::Horizontal);
+        system.add_window_under(layout, nested, w(2));
+        let single = system.tree.mk_node().push_back(nested);
+        system.tree.data.layout.set_kind(single, LayoutKind::Vertical);
+        let w3 = system.add_window_under(layout, single, w(3));
+        system.select(w3);
+
+        system.set_normalization(flatten_settings());
+        assert_normalized(&system, layout);
+
+        let map = system.map();
+        assert_eq!(root.children(map).count(), 3);
+        assert_eq!(system.visible_windows_in_layout(layout), vec![w(1), w(2), w(3)]);
+        assert_eq!(system.selected_window(layout), Some(w(3)));
+        let info = &system.tree.data.layout.info;
+        let sizes: Vec<_> = root.children(map).map(|c| info[c].size / info[root].total).collect();
+        assert!((sizes[0] - 0.5).abs() < 1e-4);
+        assert!((sizes[1] - 0.25).abs() < 1e-4);
+        assert!((sizes[2] - 0.25).abs() < 1e-4);
+    }
+
+    #[test]
+    fn normalization_can_force_opposite_orientation_for_nested_containers() {
+        let mut system = TraditionalLayoutSystem::default();
+        let layout = system.create_layout();
+        let root = system.root(layout);
+        system.tree.data.layout.set_kind(root, LayoutKind::Horizontal);
+
+        system.add_window_under(layout, root, w(1));
+        let nested = system.tree.mk_node().push_back(root);
+        system.tree.data.layout.set_kind(nested, LayoutKind::Horizontal);
+        system.add_window_under(layout, nested, w(2));
+        let inner = system.tree.mk_node().push_back(nested);
+        system.tree.data.layout.set_kind(inner, LayoutKind::Horizontal);
+        system.add_window_under(layout, inner, w(3));
+        system.add_window_under(layout, inner, w(4));
+
+        system.set_normalization(NormalizationSettings {
+            flatten_containers: true,
+            opposite_orientation_for_nested_containers: true,
+        });
+        assert_normalized(&system, layout);
+        assert_eq!(system.layout(nested), LayoutKind::Vertical);
+        assert_eq!(system.layout(inner), LayoutKind::Horizontal);
+        assert_eq!(system.visible_windows_in_layout(layout), vec![w(1), w(2), w(3), w(4)]);
+    }
+
+    #[test]
+    fn normalization_is_off_by_default() {
+        let mut system = TraditionalLayoutSystem::default();
+        let layout = system.create_layout();
+        let root = system.root(layout);
+        system.tree.data.layout.set_kind(root, LayoutKind::Horizontal);
+        system.add_window_under(layout, root, w(1));
+        let single = system.tree.mk_node().push_back(root);
+        system.add_window_under(layout, single, w(2));
+        let w1 = system.tree.data.window.node_for(layout, w(1)).unwrap();
+        system.select(w1);
+
+        system.add_window_after_selection(layout, w(3));
+        assert_eq!(single.children(system.map()).count(), 1);
+    }
+
+    #[test]
+    fn autotile_splits_selected_window_along_longer_side() {
+        let mut system = TraditionalLayoutSystem::default();
+        system.set_autotile(true);
+        let layout = system.create_layout();
+        let root = system.root(layout);
+        system.tree.data.layout.set_kind(root, LayoutKind::Horizontal);
+        let screen = CGRect::new(CGPoint::new(0.0, 0.0), CGSize::new(1000.0, 800.0));
+
+        let mut kinds = Vec::new();
+        for i in 1..=5 {
+            system.add_window_after_selection(layout, w(i));
+            let node = system.tree.data.window.node_for(layout, w(i)).unwrap();
+            kinds.push(system.layout(node.parent(system.map()).unwrap()));
+            let frames = system.calculate_layout(
+                layout,
+                screen,
+                0.0,
+                &crate::common::config::GapSettings::default(),
+                0.0,
+                Default::default(),
+                Default::default(),
+            );
+            system.set_last_frames(&frames);
+        }
+
+        assert_eq!(kinds, vec![
+            LayoutKind::Horizontal,
+            LayoutKind::Horizontal,
+            LayoutKind::Vertical,
+            LayoutKind::Horizontal,
+            LayoutKind::Vertical,
+        ]);
+        let node = system.tree.data.window.node_for(layout, w(5)).unwrap();
+        let path: Vec<_> =
+            node.ancestors(system.map()).skip(1).map(|n| system.layout(n)).collect();
+        assert_eq!(path, vec![
+            LayoutKind::Vertical,
+            LayoutKind::Horizontal,
+            LayoutKind::Vertical,
+            LayoutKind::Horizontal,
+        ]);
+    }
+
+    #[test]
+    fn autotile_nests_windows_in_crowded_containers() {
+        let mut system = TraditionalLayoutSystem::default();
+        system.set_autotile(true);
+        let layout = system.create_layout();
+        let root = system.root(layout);
+        system.tree.data.layout.set_kind(root, LayoutKind::Horizontal);
+        // Wide enough that every window stays wider than it is tall.
+        let screen = CGRect::new(CGPoint::new(0.0, 0.0), CGSize::new(4000.0, 800.0));
+
+        for i in 1..=5 {
+            system.add_window_after_selection(layout, w(i));
+            let frames = system.calculate_layout(
+                layout,
+                screen,
+                0.0,
+                &crate::common::config::GapSettings::default(),
+                0.0,
+                Default::default(),
+                Default::default(),
+            );
+            system.set_last_frames(&frames);
+        }
+
+        assert_eq!(root.children(system.map()).count(), 4);
+        let node = system.tree.data.window.node_for(layout, w(5)).unwrap();
+        let parent = node.parent(system.map()).unwrap();
+        assert_ne!(parent, root);
+        assert_eq!(system.layout(parent), LayoutKind::Horizontal);
+    }
+
+    #[test]
+    fn autotile_without_frames_keeps_parent_orientation() {
+        let mut system = TraditionalLayoutSystem::default();
+        system.set_autotile(true);
+        let layout = system.create_layout();
+        let root = system.root(layout);
+        system.tree.data.layout.set_kind(root, LayoutKind::Horizontal);
+
+        for i in 1..=3 {
+            system.add_window_after_selection(layout, w(i));
+        }
+        assert_eq!(root.children(system.map()).count(), 3);
+        assert_eq!(system.layout(root), LayoutKind::Horizontal);
+    }
 }
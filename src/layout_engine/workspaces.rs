@@ -126,6 +126,86 @@ impl WorkspaceLayouts {
         }
     }
 
+    /// Forgets all layouts belonging to a workspace, returning them so the
+    /// caller can remove them from the layout system.
+    pub(crate) fn remove_workspace(
+        &mut self,
+        space: SpaceId,
+        workspace_id: crate::model::VirtualWorkspaceId,
+    ) -> Vec<LayoutId> {
+        let Some(info) = self.map.remove(&(space, workspace_id)) else {
+            return Vec::new();
+        };
+        let mut layouts: Vec<LayoutId> = info.configurations.into_values().collect();
+        layouts.sort_unstable();
+        layouts.dedup();
+        layouts
+    }
+
+    /// Creates layouts for workspaces added to a space after it was exposed,
+    /// using the size the space is currently laid out at.
+    pub(crate) fn ensure_active_for_new_workspaces(
+        &mut self,
+        space: SpaceId,
+        workspaces: impl IntoIterator<Item = crate::model::VirtualWorkspaceId>,
+        tree: &mut impl LayoutSystem,
+    ) {
+        let Some(size) =
+            self.map.iter().find(|((sp, _), _)| *sp == space).map(|(_, info)| info.active_size)
+        else {
+            return;
+        };
+        let size = CGSize::new(size.width as f64, size.height as f64);
+        let missing: Vec<_> =
+            workspaces.into_iter().filter(|ws| !self.map.contains_key(&(space, *ws))).collect();
+        self.ensure_active_for_space(space, size, missing, tree);
+    }
+
+    /// Moves layouts whose workspace now lives on a different space (global
+    /// workspaces) under that space, replacing any placeholder created there.
+    pub(crate) fn rehome(
+        &mut self,
+        owner: impl Fn(crate::model::VirtualWorkspaceId) -> Option<SpaceId>,
+        tree: &mut impl LayoutSystem,
+    ) {
+        let moves: Vec<_> = self
+            .map
+            .keys()
+            .filter_map(|&(space, ws)| match owner(ws) {
+                Some(target) if target != space => Some((space, ws, target)),
+                _ => None,
+            })
+            .collect();
+        for (space, ws, target) in moves {
+            let Some(info) = self.map.remove(&(space, ws)) else { continue };
+            if let Some(placeholder) = self.map.insert((target, ws), info) {
+                let mut layouts: Vec<LayoutId> =
+                    placeholder.configurations.into_values().collect();
+                layouts.sort_unstable();
+                layouts.dedup();
+                for layout in layouts {
+                    tree.remove_layout(layout);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn swap_workspaces(
+        &mut self,
+        space: SpaceId,
+        a: crate::model::VirtualWorkspaceId,
+        b: crate::model::VirtualWorkspaceId,
+    ) {
+        let info_a = self.map.remove(&(space, a));
+        let info_b = self.map.remove(&(space, b));
+        if let Some(info) = info_a {
+            self.map.insert((space, b), info);
+        }
+        if let Some(info) = info_b {
+            self.map.insert((space, a), info);
+        }
+    }
+
     pub(crate) fn active(
         &self,
         space: SpaceId,
@@ -134,6 +214,36 @@ impl WorkspaceLayouts {
         self.map.get(&(space, workspace_id)).and_then(|l| l.active())
     }
 
+    /// Every layout kept for a workspace, one per screen size it was shown at.
+    pub(crate) fn layouts(
+        &self,
+        space: SpaceId,
+        workspace_id: crate::model::VirtualWorkspaceId,
+    ) -> Vec<LayoutId> {
+        let Some(info) = self.map.get(&(space, workspace_id)) else {
+            return Vec::new();
+        };
+        let mut layouts: Vec<LayoutId> = info.configurations.values().copied().collect();
+        layouts.sort_unstable();
+        layouts.dedup();
+        layouts
+    }
+
+    /// Makes `layout` the workspace's layout at the screen size it is shown
+    /// at, leaving the layouts for other sizes alone. The caller removes the
+    /// layout it replaces from the layout system.
+    pub(crate) fn set_layout(
+        &mut self,
+        space: SpaceId,
+        workspace_id: crate::model::VirtualWorkspaceId,
+        layout: LayoutId,
+    ) {
+        if let Some(info) = self.map.get_mut(&(space, workspace_id)) {
+            info.configurations.insert(info.active_size, layout);
+            info.last_saved = Some(layout);
+        }
+    }
+
     pub(crate) fn mark_last_saved(
         &mut self,
         space: SpaceId,
@@ -0,0 +1,176 @@
+//! Remembers where each app's windows last lived, so that windows return there
+//! when the app is launched again even without an app rule.
+//!
+//! Window ids do not survive an app relaunch, so placements are keyed by the
+//! app's bundle id and a pattern derived from the window title.
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use objc2_core_foundation::CGRect;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use tracing::warn;
+
+use crate::common::collections::BTreeMap;
+use crate::common::util::write_atomically;
+use crate::sys::geometry::CGRectDef;
+
+/// Number of title patterns remembered per app; the least recently recorded
+/// ones are forgotten first.
+const MAX_PLACEMENTS_PER_APP: usize = 16;
+
+/// Where a window lived when it was last seen.
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RememberedPlacement {
+    /// See [`title_pattern`].
+    pub title_pattern: String,
+    pub workspace: String,
+    pub floating: bool,
+    /// Frame of a floating window.
+    #[serde_as(as = "Option<CGRectDef>")]
+    pub frame: Option<CGRect>,
+    pub display_uuid: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PlacementMemory {
+    /// Placements by bundle id, most recently recorded first.
+    apps: BTreeMap<String, Vec<RememberedPlacement>>,
+    /// File the memory was loaded from and is saved to. Memory that was not
+    /// loaded from a file is never saved.
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    /// Whether there are placements that have not been saved yet.
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl PlacementMemory {
+    /// Loads the memory stored at `path`, starting empty if there is none.
+    pub fn load(path: PathBuf) -> Self {
+        let mut memory = match Self::read(&path) {
+            Ok(memory) => memory,
+            Err(e) => {
+                if path.exists() {
+                    warn!("Could not read placement memory from {}: {e}", path.display());
+                }
+                PlacementMemory::default()
+            }
+        };
+        memory.path = Some(path);
+        memory
+    }
+
+    fn read(path: &Path) -> anyhow::Result<Self> {
+        let mut buf = String::new();
+        File::open(path)?.read_to_string(&mut buf)?;
+        Ok(ron::from_str(&buf)?)
+    }
+
+    /// Writes the memory to its file if placements changed since the last save.
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !self.dirty {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomically(path, ron::ser::to_string(self)?.as_bytes())?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// The placement recorded for a new window of the app with the same title
+    /// pattern.
+    pub fn lookup(&self, bundle_id: &str, title: Option<&str>) -> Option<&RememberedPlacement> {
+        let pattern = title_pattern(title?);
+        self.apps.get(bundle_id)?.iter().find(|p| p.title_pattern == pattern)
+    }
+
+    /// Number of placements remembered for the app.
+    pub fn placement_count(&self, bundle_id: &str) -> usize {
+        self.apps.get(bundle_id).map_or(0, Vec::len)
+    }
+
+    /// Records a placement, replacing any with the same title pattern. Returns
+    /// whether the memory changed.
+    pub fn remember(&mut self, bundle_id: &str, placement: RememberedPlacement) -> bool {
+        let placements = self.apps.entry(bundle_id.to_string()).or_default();
+        if placements.first() == Some(&placement) {
+            return false;
+        }
+        placements.retain(|p| p.title_pattern != placement.title_pattern);
+        placements.insert(0, placement);
+        placements.truncate(MAX_PLACEMENTS_PER_APP);
+        self.dirty = true;
+        true
+    }
+}
+
+/// Reduces a window title to the part that stays the same across relaunches:
+/// runs of digits (counters, dates, unread badges) become `#` and surrounding
+/// whitespace is dropped.
+pub fn title_pattern(title: &str) -> String {
+    let mut pattern = String::with_capacity(title.len());
+    let mut in_digits = false;
+    for ch in title.trim().chars() {
+        if ch.is_ascii_digit() {
+            if !in_digits {
+                pattern.push('#');
+            }
+            in_digits = true;
+        } else {
+            pattern.push(ch);
+            in_digits = false;
+        }
+    }
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placement(title: &str, workspace: &str) -> RememberedPlacement {
+        RememberedPlacement {
+            title_pattern: title_pattern(title),
+            workspace: workspace.into(),
+            floating: false,
+            frame: None,
+            display_uuid: None,
+        }
+    }
+
+    #[test]
+    fn title_patterns_ignore_numbers() {
+        assert_eq!(title_pattern(" Inbox (23) - Mail "), "Inbox (#) - Mail");
+        assert_eq!(title_pattern("Inbox (4) - Mail"), title_pattern("Inbox (150) - Mail"));
+    }
+
+    #[test]
+    fn lookup_matches_title_pattern() {
+        let mut memory = PlacementMemory::default();
+        assert!(memory.remember("com.example.x", placement("Inbox (3)", "mail")));
+        assert!(memory.remember("com.example.x", placement("Compose", "writing")));
+        assert!(!memory.remember("com.example.x", placement("Compose", "writing")));
+
+        let found = |memory: &PlacementMemory, title: Option<&str>| {
+            memory.lookup("com.example.x", title).map(|p| p.workspace.clone())
+        };
+        assert_eq!(found(&memory, Some("Inbox (12)")).as_deref(), Some("mail"));
+        assert_eq!(found(&memory, Some("Compose")).as_deref(), Some("writing"));
+        assert_eq!(found(&memory, Some("Untitled")), None);
+        assert_eq!(found(&memory, None), None);
+        assert!(memory.lookup("com.example.y", Some("Compose")).is_none());
+
+        assert!(memory.remember("com.example.x", placement("Inbox (5)", "web")));
+        assert_eq!(found(&memory, Some("Inbox (1)")).as_deref(), Some("web"));
+        assert_eq!(memory.apps["com.example.x"].len(), 2);
+    }
+}
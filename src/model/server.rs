@@ -26,6 +26,8 @@ pub struct WindowData {
     pub is_focused: bool,
     pub bundle_id: Option<String>,
     pub window_server_id: Option<u32>,
+    #[serde(default)]
+    pub marks: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -5,10 +5,14 @@ use slotmap::{SlotMap, new_key_type};
 use tracing::{error, warn};
 
 use crate::actor::app::WindowId;
+use crate::actor::reactor::DisplaySelector;
 use crate::common::collections::{HashMap, HashSet};
-use crate::common::config::{AppWorkspaceRule, VirtualWorkspaceSettings, WorkspaceSelector};
+use crate::common::config::{
+    AppWorkspaceRule, RuleFrame, VirtualWorkspaceSettings, WorkspaceModel, WorkspaceSelector,
+};
 use crate::common::log::trace_misc;
 use crate::layout_engine::Direction;
+use crate::model::placement_memory::{PlacementMemory, RememberedPlacement, title_pattern};
 use crate::sys::app::pid_t;
 use crate::sys::geometry::CGRectDef;
 use crate::sys::screen::SpaceId;
@@ -39,15 +43,99 @@ pub enum WorkspaceError {
 }
 
 /// Details about an app rule assignment when Rift will manage the window.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct AppRuleAssignment {
     pub workspace_id: VirtualWorkspaceId,
     pub floating: bool,
     pub prev_rule_decision: bool,
+    /// Placement actions of the matching rule, only set on the window's first
+    /// rule decision so they shape where it appears rather than pin it there.
+    pub actions: Option<AppRuleActions>,
+}
+
+/// Placement actions carried by an app rule besides workspace and floating.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppRuleActions {
+    pub frame: Option<RuleFrame>,
+    pub center: bool,
+    pub display: Option<DisplaySelector>,
+    pub fullscreen: bool,
+    pub focus: bool,
+    pub stack_with: Option<String>,
+}
+
+impl AppRuleActions {
+    fn from_rule(rule: &AppWorkspaceRule) -> Option<Self> {
+        rule.has_placement_actions().then(|| AppRuleActions {
+            frame: rule.frame,
+            center: rule.center,
+            display: rule.display.clone(),
+            fullscreen: rule.fullscreen,
+            focus: rule.focus,
+            stack_with: rule.stack_with.clone(),
+        })
+    }
+
+    /// Where a floating window of the given size should first appear on
+    /// `screen`, if the rule places it at all.
+    pub fn floating_frame(&self, screen: CGRect, size: CGSize) -> Option<CGRect> {
+        if self.fullscreen {
+            return Some(screen);
+        }
+        let mut frame = match self.frame {
+            Some(frame) => frame.resolve(screen),
+            None if self.center => CGRect::new(screen.origin, size),
+            None => return None,
+        };
+        if self.center {
+            let mid = screen.mid();
+            frame.origin =
+                CGPoint::new(mid.x - frame.size.width / 2.0, mid.y - frame.size.height / 2.0);
+        }
+        Some(frame)
+    }
+}
+
+/// A window description to run app-rule matching against.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AppRuleQuery {
+    pub app_id: Option<String>,
+    pub app_name: Option<String>,
+    pub title: Option<String>,
+    pub ax_role: Option<String>,
+    pub ax_subrole: Option<String>,
+}
+
+/// A rule that matched an [`AppRuleQuery`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppRuleMatch {
+    /// Position of the rule in `app_rules`.
+    pub index: usize,
+    /// Number of conditions the rule specifies.
+    pub specificity: usize,
+    pub rule: AppWorkspaceRule,
+}
+
+/// Outcome of [`VirtualWorkspaceManager::explain_app_rules`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppRuleExplanation {
+    pub query: AppRuleQuery,
+    pub matches: Vec<AppRuleMatch>,
+    /// Index of the rule that applies, if any matched.
+    pub winner: Option<usize>,
+    pub reason: String,
+}
+
+/// Why a rule won app-rule matching.
+enum RuleChoice<'a> {
+    OnlyMatch,
+    /// Several matches share this `app_id`, so only they competed.
+    SharedAppId(&'a str),
+    MostSpecific,
 }
 
 /// Result of evaluating app rules for a window.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum AppRuleResult {
     Managed(AppRuleAssignment),
     Unmanaged,
@@ -103,6 +191,37 @@ impl Default for HideCorner {
     fn default() -> Self { HideCorner::BottomRight }
 }
 
+/// Number of entries kept in each direction of a space's workspace history.
+const WORKSPACE_HISTORY_LIMIT: usize = 32;
+
+/// Back and forward stacks of previously active workspaces on one space,
+/// navigated like browser history.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct WorkspaceHistory {
+    back: Vec<VirtualWorkspaceId>,
+    forward: Vec<VirtualWorkspaceId>,
+}
+
+impl WorkspaceHistory {
+    fn push(stack: &mut Vec<VirtualWorkspaceId>, workspace_id: VirtualWorkspaceId) {
+        if stack.last() == Some(&workspace_id) {
+            return;
+        }
+        stack.push(workspace_id);
+        if stack.len() > WORKSPACE_HISTORY_LIMIT {
+            stack.remove(0);
+        }
+    }
+}
+
+/// What app rules last decided for a window on a space.
+#[derive(Debug, Clone, Copy, Default)]
+struct RuleDecision {
+    floating: bool,
+    /// Workspace the rule placed the window in, if it named one that exists.
+    workspace: Option<VirtualWorkspaceId>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VirtualWorkspaceManager {
     workspaces: SlotMap<VirtualWorkspaceId, VirtualWorkspace>,
@@ -113,7 +232,17 @@ pub struct VirtualWorkspaceManager {
     #[serde(skip)]
     window_rule_floating: HashMap<(SpaceId, WindowId), bool>,
     #[serde(skip)]
-    last_rule_decision: HashMap<(SpaceId, WindowId), bool>,
+    last_rule_decision: HashMap<(SpaceId, WindowId), RuleDecision>,
+    #[serde(skip)]
+    placement_memory: PlacementMemory,
+    /// Placements restored from `placement_memory` that the layout engine has
+    /// not applied yet.
+    #[serde(skip)]
+    restored_placements: HashMap<WindowId, RememberedPlacement>,
+    /// Apps launched while rift runs whose first windows may still return to
+    /// remembered placements, with how many have so far.
+    #[serde(skip)]
+    launched_apps: HashMap<pid_t, usize>,
     floating_positions: HashMap<(SpaceId, VirtualWorkspaceId), FloatingWindowPositions>,
     workspace_counter: usize,
     #[serde(skip)]
@@ -130,6 +259,29 @@ pub struct VirtualWorkspaceManager {
     default_workspace: usize,
     #[serde(skip)]
     workspace_auto_back_and_forth: bool,
+    #[serde(skip)]
+    dynamic: bool,
+    #[serde(skip)]
+    workspace_displays: HashMap<String, String>,
+    #[serde(skip)]
+    space_displays: HashMap<SpaceId, String>,
+    #[serde(skip)]
+    display_last_space: HashMap<String, SpaceId>,
+    #[serde(skip)]
+    main_display: Option<String>,
+    #[serde(skip)]
+    workspace_model: WorkspaceModel,
+    /// Shared workspace order when `workspace_model` is global.
+    #[serde(default)]
+    global_order: Vec<VirtualWorkspaceId>,
+    #[serde(default)]
+    workspace_history: HashMap<SpaceId, WorkspaceHistory>,
+    /// Workspaces a window is tagged with besides the one it belongs to.
+    #[serde(default)]
+    window_tags: HashMap<(SpaceId, WindowId), Vec<VirtualWorkspaceId>>,
+    /// Workspaces shown alongside the active one on each space.
+    #[serde(default)]
+    viewed_workspaces: HashMap<SpaceId, Vec<VirtualWorkspaceId>>,
 }
 
 impl Default for VirtualWorkspaceManager {
@@ -157,6 +309,9 @@ impl VirtualWorkspaceManager {
             window_to_workspace: HashMap::default(),
             window_rule_floating: HashMap::default(),
             last_rule_decision: HashMap::default(),
+            placement_memory: PlacementMemory::default(),
+            restored_placements: HashMap::default(),
+            launched_apps: HashMap::default(),
             floating_positions: HashMap::default(),
             workspace_counter: 1,
             app_rules: config.app_rules.clone(),
@@ -166,6 +321,16 @@ impl VirtualWorkspaceManager {
             default_workspace_names: config.workspace_names.clone(),
             default_workspace,
             workspace_auto_back_and_forth: config.workspace_auto_back_and_forth,
+            dynamic: config.dynamic,
+            workspace_displays: config.workspace_displays.clone(),
+            space_displays: HashMap::default(),
+            display_last_space: HashMap::default(),
+            main_display: None,
+            workspace_model: config.workspace_model,
+            global_order: Vec::new(),
+            workspace_history: HashMap::default(),
+            window_tags: HashMap::default(),
+            viewed_workspaces: HashMap::default(),
         };
 
         manager.rebuild_app_rule_regex_cache();
@@ -177,6 +342,9 @@ impl VirtualWorkspaceManager {
         self.default_workspace_count = config.default_workspace_count;
         self.default_workspace_names = config.workspace_names.clone();
         self.workspace_auto_back_and_forth = config.workspace_auto_back_and_forth;
+        self.dynamic = config.dynamic;
+        self.workspace_displays = config.workspace_displays.clone();
+        self.workspace_model = config.workspace_model;
         self.rebuild_app_rule_regex_cache();
 
         let target_count = self.default_workspace_count.max(1).min(self.max_workspaces);
@@ -208,7 +376,7 @@ impl VirtualWorkspaceManager {
                     if rule_re.is_empty() {
                         return None;
                     }
-                    match regex::RegexBuilder::new(rule_re).case_insensitive(true).build() {
+                    match compile_title_regex(rule_re) {
                         Ok(regex) => Some(regex),
                         Err(e) => {
                             warn!("Invalid title_regex '{}' in app rule: {}", rule_re, e);
@@ -224,15 +392,34 @@ impl VirtualWorkspaceManager {
         if self.workspaces_by_space.contains_key(&space) {
             return;
         }
+        if self.is_global() && !self.global_order.is_empty() {
+            self.initialize_global_space(space);
+            return;
+        }
 
-        let mut ids = Vec::new();
         let count = self.default_workspace_count.max(1).min(self.max_workspaces);
-        for i in 0..count {
-            let name = self
-                .default_workspace_names
-                .get(i)
-                .cloned()
-                .unwrap_or_else(|| format!("Workspace {}", i + 1));
+        let mut names: Vec<String> = (0..count)
+            .map(|i| {
+                self.default_workspace_names
+                    .get(i)
+                    .cloned()
+                    .unwrap_or_else(|| format!("Workspace {}", i + 1))
+            })
+            .filter(|name| self.workspace_allowed_on_space(name, space))
+            .collect();
+        let pinned: Vec<String> = self
+            .pinned_workspace_names_for_space(space)
+            .into_iter()
+            .filter(|name| !names.contains(name))
+            .collect();
+        names.extend(pinned);
+        names.truncate(self.max_workspaces);
+        if names.is_empty() {
+            names.push("Workspace 1".to_string());
+        }
+
+        let mut ids = Vec::new();
+        for name in names {
             let ws = VirtualWorkspace::new(name, space);
             let id = self.workspaces.insert(ws);
             ids.push(id);
@@ -243,6 +430,378 @@ impl VirtualWorkspaceManager {
         if let Some(&default_id) = ids.get(default_idx) {
             self.active_workspace_per_space.insert(space, (None, default_id));
         }
+        if self.is_global() {
+            self.global_order = ids;
+        }
+        self.reconcile_dynamic_workspaces(space);
+    }
+
+    /// Gives a newly seen space one of the shared workspaces: the first one not
+    /// shown on any display, or a new workspace if every one is visible.
+    fn initialize_global_space(&mut self, space: SpaceId) {
+        self.workspaces_by_space.insert(space, Vec::new());
+        let visible: HashSet<VirtualWorkspaceId> =
+            self.active_workspace_per_space.values().map(|(_, active)| *active).collect();
+        let hidden = self
+            .global_order
+            .iter()
+            .copied()
+            .find(|id| !visible.contains(id) && self.workspaces.contains_key(*id));
+
+        let workspace_id = match hidden {
+            Some(id) => {
+                self.transfer_workspace(id, space);
+                id
+            }
+            None => {
+                let name = format!("Workspace {}", self.global_order.len() + 1);
+                let id = self.workspaces.insert(VirtualWorkspace::new(name, space));
+                self.workspaces_by_space.entry(space).or_default().push(id);
+                self.global_order.push(id);
+                id
+            }
+        };
+        self.active_workspace_per_space.insert(space, (None, workspace_id));
+    }
+
+    pub fn is_global(&self) -> bool { self.workspace_model == WorkspaceModel::Global }
+
+    /// Moves a workspace, with its windows and floating positions, to another
+    /// space. If the workspace was active on its old space, another workspace
+    /// owned there becomes active.
+    pub fn transfer_workspace(&mut self, workspace_id: VirtualWorkspaceId, to: SpaceId) -> bool {
+        let Some(from) = self.workspace_space(workspace_id) else {
+            return false;
+        };
+        if from == to {
+            return true;
+        }
+
+        let windows: Vec<WindowId> = match self.workspaces.get_mut(workspace_id) {
+            Some(ws) => {
+                ws.space = to;
+                ws.windows().collect()
+            }
+            None => return false,
+        };
+        for window_id in windows {
+            if self.window_to_workspace.remove(&(from, window_id)).is_some() {
+                self.window_to_workspace.insert((to, window_id), workspace_id);
+            }
+            if let Some(floating) = self.window_rule_floating.remove(&(from, window_id)) {
+                self.window_rule_floating.insert((to, window_id), floating);
+            }
+            if let Some(decision) = self.last_rule_decision.remove(&(from, window_id)) {
+                self.last_rule_decision.insert((to, window_id), decision);
+            }
+            self.window_tags.remove(&(from, window_id));
+        }
+        self.forget_workspace_tags(workspace_id);
+        if let Some(positions) = self.floating_positions.remove(&(from, workspace_id)) {
+            self.floating_positions.insert((to, workspace_id), positions);
+        }
+
+        if let Some(ids) = self.workspaces_by_space.get_mut(&from) {
+            ids.retain(|&id| id != workspace_id);
+        }
+        let order = &self.global_order;
+        let ids = self.workspaces_by_space.entry(to).or_default();
+        ids.push(workspace_id);
+        ids.sort_by_key(|id| order.iter().position(|o| o == id).unwrap_or(usize::MAX));
+
+        if let Some((last, active)) = self.active_workspace_per_space.get(&from).copied() {
+            let remaining = self.workspaces_by_space.get(&from).cloned().unwrap_or_default();
+            let last = last.filter(|id| *id != workspace_id && remaining.contains(id));
+            if active == workspace_id {
+                match last.or_else(|| remaining.first().copied()) {
+                    Some(replacement) => {
+                        let last = last.filter(|id| *id != replacement);
+                        self.active_workspace_per_space.insert(from, (last, replacement));
+                    }
+                    None => {
+                        self.active_workspace_per_space.remove(&from);
+                    }
+                }
+            } else {
+                self.active_workspace_per_space.insert(from, (last, active));
+            }
+        }
+        true
+    }
+
+    /// Maps a workspace's stored floating positions from one screen frame to
+    /// another, keeping each window's relative position and size.
+    pub fn rescale_floating_positions(
+        &mut self,
+        space: SpaceId,
+        workspace_id: VirtualWorkspaceId,
+        from: CGRect,
+        to: CGRect,
+    ) {
+        if from.size.width <= 0.0 || from.size.height <= 0.0 {
+            return;
+        }
+        let Some(positions) = self.floating_positions.get_mut(&(space, workspace_id)) else {
+            return;
+        };
+        let sx = to.size.width / from.size.width;
+        let sy = to.size.height / from.size.height;
+        let windows: Vec<WindowId> = positions.windows().collect();
+        for window_id in windows {
+            if let Some(rect) = positions.get_position(window_id) {
+                let origin = CGPoint::new(
+                    to.origin.x + (rect.origin.x - from.origin.x) * sx,
+                    to.origin.y + (rect.origin.y - from.origin.y) * sy,
+                );
+                let size = CGSize::new(rect.size.width * sx, rect.size.height * sy);
+                positions.store_position(window_id, CGRect::new(origin, size));
+            }
+        }
+    }
+
+    /// Hands the workspaces of spaces that no longer exist to the first live
+    /// space so their windows stay tracked. Returns whether anything moved.
+    pub fn adopt_orphaned_workspaces(&mut self, live_spaces: &[SpaceId]) -> bool {
+        if !self.is_global() {
+            return false;
+        }
+        let Some(&target) = live_spaces.first() else {
+            return false;
+        };
+        let orphans: Vec<VirtualWorkspaceId> = self
+            .global_order
+            .iter()
+            .copied()
+            .filter(|id| self.workspace_space(*id).is_some_and(|sp| !live_spaces.contains(&sp)))
+            .collect();
+        for &id in &orphans {
+            if let Some(space) = self.workspace_space(id) {
+                self.active_workspace_per_space.remove(&space);
+            }
+            self.transfer_workspace(id, target);
+        }
+        self.workspaces_by_space.retain(|space, ids| live_spaces.contains(space) || !ids.is_empty());
+        !orphans.is_empty()
+    }
+
+    /// Workspaces in the order used for index-based commands: the shared order in
+    /// the global model, otherwise the space's own workspaces.
+    pub fn workspace_order(&mut self, space: SpaceId) -> Vec<(VirtualWorkspaceId, String)> {
+        if !self.is_global() {
+            return self.list_workspaces(space);
+        }
+        self.ensure_space_initialized(space);
+        self.global_order
+            .iter()
+            .filter_map(|id| self.workspaces.get(*id).map(|ws| (*id, ws.name.clone())))
+            .collect()
+    }
+
+    fn ordered_ids(&self, space: SpaceId) -> Vec<VirtualWorkspaceId> {
+        if self.is_global() {
+            self.global_order.clone()
+        } else {
+            self.workspaces_by_space.get(&space).cloned().unwrap_or_default()
+        }
+    }
+
+    pub fn is_dynamic(&self) -> bool { self.dynamic && !self.is_global() }
+
+    pub fn has_display_pins(&self) -> bool {
+        !self.workspace_displays.is_empty() && !self.is_global()
+    }
+
+    pub fn set_main_display(&mut self, display_uuid: Option<String>) -> bool {
+        let changed = self.main_display != display_uuid;
+        self.main_display = display_uuid;
+        changed
+    }
+
+    /// Records which display a space is shown on. Returns workspaces removed from
+    /// the space because they are pinned to a different display.
+    pub fn set_space_display(
+        &mut self,
+        space: SpaceId,
+        display_uuid: Option<String>,
+    ) -> Vec<VirtualWorkspaceId> {
+        let changed = self.space_displays.get(&space) != display_uuid.as_ref();
+        match display_uuid {
+            Some(uuid) => {
+                self.display_last_space.insert(uuid.clone(), space);
+                self.space_displays.insert(space, uuid);
+            }
+            None => {
+                self.space_displays.remove(&space);
+            }
+        }
+        if changed && self.workspaces_by_space.contains_key(&space) {
+            self.apply_display_pins(space)
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn pinned_display(&self, workspace_name: &str) -> Option<&str> {
+        let display = self.workspace_displays.get(workspace_name)?;
+        if display.eq_ignore_ascii_case("main") {
+            self.main_display.as_deref()
+        } else {
+            Some(display.as_str())
+        }
+    }
+
+    fn workspace_allowed_on_space(&self, workspace_name: &str, space: SpaceId) -> bool {
+        if self.is_global() || !self.workspace_displays.contains_key(workspace_name) {
+            return true;
+        }
+        match (self.pinned_display(workspace_name), self.space_displays.get(&space)) {
+            (Some(pinned), Some(display)) => pinned == display,
+            _ => false,
+        }
+    }
+
+    fn pinned_workspace_names_for_space(&self, space: SpaceId) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .workspace_displays
+            .keys()
+            .filter(|name| self.workspace_allowed_on_space(name, space))
+            .cloned()
+            .collect();
+        // Keep the configured workspace order, then any extra pinned names alphabetically.
+        names.sort_by_key(|name| {
+            let pos = self.default_workspace_names.iter().position(|n| n == name);
+            (pos.unwrap_or(usize::MAX), name.clone())
+        });
+        names
+    }
+
+    pub fn main_display_space(&self) -> Option<SpaceId> {
+        self.display_last_space.get(self.main_display.as_deref()?).copied()
+    }
+
+    /// Returns the space a pinned workspace lives on, if its display is known.
+    pub fn space_for_pinned_workspace(&self, workspace_name: &str) -> Option<SpaceId> {
+        let display = self.pinned_display(workspace_name)?;
+        self.display_last_space.get(display).copied()
+    }
+
+    /// Adds the workspaces pinned to a space's display and removes empty,
+    /// inactive workspaces pinned elsewhere. Returns the removed workspaces.
+    pub fn apply_display_pins(&mut self, space: SpaceId) -> Vec<VirtualWorkspaceId> {
+        if !self.has_display_pins() || !self.space_displays.contains_key(&space) {
+            return Vec::new();
+        }
+        let Some(ids) = self.workspaces_by_space.get(&space).cloned() else {
+            return Vec::new();
+        };
+
+        let mut removed = Vec::new();
+        for &id in &ids {
+            if ids.len() - removed.len() <= 1 {
+                break;
+            }
+            let misplaced = self
+                .workspaces
+                .get(id)
+                .is_some_and(|ws| !self.workspace_allowed_on_space(&ws.name, space));
+            if misplaced && self.is_vacant(space, id) {
+                removed.push(id);
+            }
+        }
+        for &id in &removed {
+            self.workspaces.remove(id);
+            self.floating_positions.remove(&(space, id));
+        }
+        if let Some(ids) = self.workspaces_by_space.get_mut(&space) {
+            ids.retain(|id| !removed.contains(id));
+        }
+        if let Some((last, _)) = self.active_workspace_per_space.get_mut(&space) {
+            if last.is_some_and(|id| removed.contains(&id)) {
+                *last = None;
+            }
+        }
+
+        for name in self.pinned_workspace_names_for_space(space) {
+            let exists = self
+                .workspaces_by_space
+                .get(&space)
+                .into_iter()
+                .flatten()
+                .any(|id| self.workspaces.get(*id).is_some_and(|ws| ws.name == name));
+            if !exists && self.create_workspace(space, Some(name)).is_err() {
+                break;
+            }
+        }
+
+        removed
+    }
+
+    /// In dynamic mode, keeps exactly one trailing workspace that is empty and
+    /// inactive: other empty, inactive workspaces beyond `default_workspace_count`
+    /// are removed, and a new trailing workspace is appended once the last one is
+    /// occupied or becomes active. Returns the removed workspaces so callers can
+    /// drop any state they keep for them.
+    pub fn reconcile_dynamic_workspaces(&mut self, space: SpaceId) -> Vec<VirtualWorkspaceId> {
+        if !self.is_dynamic() {
+            return Vec::new();
+        }
+        let Some(ids) = self.workspaces_by_space.get(&space).cloned() else {
+            return Vec::new();
+        };
+        let floor = self.default_workspace_count.max(1).min(self.max_workspaces);
+        let last_idx = ids.len().saturating_sub(1);
+        let removed: Vec<VirtualWorkspaceId> = ids
+            .iter()
+            .enumerate()
+            .filter(|&(idx, &id)| idx >= floor && idx != last_idx && self.is_vacant(space, id))
+            .map(|(_, &id)| id)
+            .collect();
+
+        for &id in &removed {
+            self.workspaces.remove(id);
+            self.floating_positions.remove(&(space, id));
+        }
+        if let Some(ids) = self.workspaces_by_space.get_mut(&space) {
+            ids.retain(|id| !removed.contains(id));
+        }
+        if let Some((last, _)) = self.active_workspace_per_space.get_mut(&space) {
+            if last.is_some_and(|id| removed.contains(&id)) {
+                *last = None;
+            }
+        }
+
+        let ids = self.workspaces_by_space.get(&space).cloned().unwrap_or_default();
+        let needs_trailing = ids.last().is_none_or(|&id| !self.is_vacant(space, id));
+        if needs_trailing && ids.len() < self.max_workspaces {
+            let name = self.next_dynamic_workspace_name(space);
+            let id = self.workspaces.insert(VirtualWorkspace::new(name, space));
+            self.workspaces_by_space.entry(space).or_default().push(id);
+        }
+
+        removed
+    }
+
+    fn is_vacant(&self, space: SpaceId, workspace_id: VirtualWorkspaceId) -> bool {
+        self.active_workspace(space) != Some(workspace_id)
+            && self.workspaces.get(workspace_id).is_some_and(|ws| ws.windows.is_empty())
+    }
+
+    fn next_dynamic_workspace_name(&self, space: SpaceId) -> String {
+        let ids = self.workspaces_by_space.get(&space).map(Vec::as_slice).unwrap_or_default();
+        let in_use: HashSet<&str> = ids
+            .iter()
+            .filter_map(|id| self.workspaces.get(*id))
+            .map(|ws| ws.name.as_str())
+            .collect();
+        if let Some(name) =
+            self.default_workspace_names.iter().find(|name| !in_use.contains(name.as_str()))
+        {
+            return name.clone();
+        }
+        (ids.len() + 1..)
+            .map(|n| format!("Workspace {}", n))
+            .find(|name| !in_use.contains(name.as_str()))
+            .unwrap_or_default()
     }
 
     pub fn remap_space(&mut self, old_space: SpaceId, new_space: SpaceId) {
@@ -276,6 +835,24 @@ impl VirtualWorkspaceManager {
         if let Some((last, active)) = self.active_workspace_per_space.remove(&old_space) {
             self.active_workspace_per_space.insert(new_space, (last, active));
         }
+        self.workspace_history.remove(&new_space);
+        if let Some(history) = self.workspace_history.remove(&old_space) {
+            self.workspace_history.insert(new_space, history);
+        }
+        self.viewed_workspaces.remove(&new_space);
+        if let Some(viewed) = self.viewed_workspaces.remove(&old_space) {
+            self.viewed_workspaces.insert(new_space, viewed);
+        }
+
+        let mut new_window_tags = HashMap::default();
+        for ((space, wid), tags) in std::mem::take(&mut self.window_tags) {
+            if space == new_space {
+                continue;
+            }
+            let target_space = if space == old_space { new_space } else { space };
+            new_window_tags.insert((target_space, wid), tags);
+        }
+        self.window_tags = new_window_tags;
 
         let mut new_window_to_workspace = HashMap::default();
         for ((space, wid), ws_id) in std::mem::take(&mut self.window_to_workspace) {
@@ -318,6 +895,15 @@ impl VirtualWorkspaceManager {
             new_positions.insert((target_space, ws_id), positions);
         }
         self.floating_positions = new_positions;
+
+        if let Some(display) = self.space_displays.remove(&old_space) {
+            self.space_displays.insert(new_space, display);
+        }
+        for space in self.display_last_space.values_mut() {
+            if *space == old_space {
+                *space = new_space;
+            }
+        }
     }
 
     pub fn create_workspace(
@@ -343,6 +929,9 @@ impl VirtualWorkspaceManager {
         let workspace = VirtualWorkspace::new(name, space);
         let workspace_id = self.workspaces.insert(workspace);
         self.workspaces_by_space.entry(space).or_default().push(workspace_id);
+        if self.is_global() {
+            self.global_order.push(workspace_id);
+        }
 
         Ok(workspace_id)
     }
@@ -367,18 +956,113 @@ impl VirtualWorkspaceManager {
 
     pub fn workspace_auto_back_and_forth(&self) -> bool { self.workspace_auto_back_and_forth }
 
+    /// Makes `workspace_id` active on `space`, recording the previously active
+    /// workspace in the space's history.
     pub fn set_active_workspace(
         &mut self,
         space: SpaceId,
         workspace_id: VirtualWorkspaceId,
     ) -> bool {
-        trace_misc("set_active_workspace", || {
-            let active = self.active_workspace_per_space.get(&space).map(|tuple| tuple.1);
+        let previous = self.active_workspace(space);
+        self.set_active_workspace_from(space, workspace_id, previous)
+    }
+
+    /// Like `set_active_workspace`, for callers that already changed which
+    /// workspaces the space owns and know what was shown before.
+    pub fn set_active_workspace_from(
+        &mut self,
+        space: SpaceId,
+        workspace_id: VirtualWorkspaceId,
+        previous: Option<VirtualWorkspaceId>,
+    ) -> bool {
+        if !self.activate_workspace(space, workspace_id, previous) {
+            return false;
+        }
+        if let Some(previous) = previous.filter(|&id| id != workspace_id) {
+            let history = self.workspace_history.entry(space).or_default();
+            WorkspaceHistory::push(&mut history.back, previous);
+            history.forward.clear();
+        }
+        true
+    }
+
+    /// Returns the workspace a step back (or forward) in the space's history
+    /// would switch to, dropping entries for workspaces that are gone.
+    pub fn workspace_history_target(
+        &mut self,
+        space: SpaceId,
+        forward: bool,
+    ) -> Option<VirtualWorkspaceId> {
+        let global = self.is_global();
+        let active = self.active_workspace(space);
+        let history = self.workspace_history.get_mut(&space)?;
+        let stack = if forward { &mut history.forward } else { &mut history.back };
+        while let Some(&id) = stack.last() {
+            let valid = self.workspaces.get(id).is_some_and(|ws| global || ws.space == space);
+            if valid && Some(id) != active {
+                return Some(id);
+            }
+            stack.pop();
+        }
+        None
+    }
+
+    /// Activates `target`, the result of `workspace_history_target`, moving one
+    /// step back (or forward) through the space's history without starting a
+    /// new branch.
+    pub fn step_workspace_history(
+        &mut self,
+        space: SpaceId,
+        target: VirtualWorkspaceId,
+        forward: bool,
+        previous: Option<VirtualWorkspaceId>,
+    ) -> bool {
+        if !self.activate_workspace(space, target, previous) {
+            return false;
+        }
+        let history = self.workspace_history.entry(space).or_default();
+        let (from, to) = if forward {
+            (&mut history.forward, &mut history.back)
+        } else {
+            (&mut history.back, &mut history.forward)
+        };
+        if from.last() == Some(&target) {
+            from.pop();
+        }
+        if let Some(previous) = previous.filter(|&id| id != target) {
+            WorkspaceHistory::push(to, previous);
+        }
+        true
+    }
+
+    /// Switches back to `target`, the top of the back stack, the way
+    /// `workspace_auto_back_and_forth` toggles: the current workspace takes
+    /// its place so pressing again returns.
+    pub fn toggle_workspace_history(
+        &mut self,
+        space: SpaceId,
+        target: VirtualWorkspaceId,
+        previous: Option<VirtualWorkspaceId>,
+    ) -> bool {
+        if let Some(history) = self.workspace_history.get_mut(&space)
+            && history.back.last() == Some(&target)
+        {
+            history.back.pop();
+        }
+        self.set_active_workspace_from(space, target, previous)
+    }
 
+    fn activate_workspace(
+        &mut self,
+        space: SpaceId,
+        workspace_id: VirtualWorkspaceId,
+        previous: Option<VirtualWorkspaceId>,
+    ) -> bool {
+        trace_misc("set_active_workspace", || {
             let result = if self.workspaces.contains_key(workspace_id)
                 && self.workspaces.get(workspace_id).map(|w| w.space) == Some(space)
             {
-                self.active_workspace_per_space.insert(space, (active, workspace_id));
+                self.active_workspace_per_space.insert(space, (previous, workspace_id));
                 true
             } else {
                 error!(
@@ -397,11 +1081,7 @@ impl VirtualWorkspaceManager {
         space: SpaceId,
         skip_empty: Option<bool>,
     ) -> Vec<VirtualWorkspaceId> {
-        let ids = match self.workspaces_by_space.get(&space) {
-            Some(v) => v,
-            None => return Vec::new(),
-        };
-
+        let ids = self.ordered_ids(space);
         let require_non_empty = skip_empty == Some(true);
 
         ids.iter()
@@ -426,7 +1106,7 @@ impl VirtualWorkspaceManager {
         let base_ids: Vec<VirtualWorkspaceId> = if skip_empty == Some(true) {
             self.filtered_workspace_ids(space, Some(true))
         } else {
-            self.workspaces_by_space.get(&space).cloned().unwrap_or_default()
+            self.ordered_ids(space)
         };
 
         if base_ids.is_empty() {
@@ -504,6 +1184,11 @@ impl VirtualWorkspaceManager {
                     }
                 });
 
+            if existing_mapping.map(|(_, old)| old) != Some(workspace_id) {
+                // Moving a window retags it with just its new workspace.
+                self.window_tags.retain(|(_, wid), _| *wid != window_id);
+            }
+
             if let Some((existing_space, old_workspace_id)) = existing_mapping {
                 if existing_space != space {
                     if let Some(old_workspace) = self.workspaces.get_mut(old_workspace_id) {
@@ -542,8 +1227,26 @@ impl VirtualWorkspaceManager {
         self.window_to_workspace.get(&(space, window_id)).copied()
     }
 
-    pub fn set_last_rule_decision(&mut self, space: SpaceId, window_id: WindowId, value: bool) {
-        self.last_rule_decision.insert((space, window_id), value);
+    /// Finds the space and workspace a window belongs to.
+    pub fn locate_window(&self, window_id: WindowId) -> Option<(SpaceId, VirtualWorkspaceId)> {
+        self.window_to_workspace
+            .iter()
+            .find(|((_, wid), _)| *wid == window_id)
+            .map(|(&(space, _), &ws_id)| (space, ws_id))
+    }
+
+    pub fn set_last_rule_decision(&mut self, space: SpaceId, window_id: WindowId, value: bool) {
+        self.last_rule_decision.entry((space, window_id)).or_default().floating = value;
+    }
+
+    fn set_last_rule_workspace(
+        &mut self,
+        space: SpaceId,
+        window_id: WindowId,
+        workspace_id: VirtualWorkspaceId,
+    ) {
+        self.last_rule_decision.entry((space, window_id)).or_default().workspace =
+            Some(workspace_id);
     }
 
     pub fn remove_window(&mut self, window_id: WindowId) {
@@ -561,7 +1264,9 @@ impl VirtualWorkspaceManager {
                 self.window_rule_floating.remove(&(space, wid));
                 self.last_rule_decision.remove(&(space, wid));
             }
+            self.window_tags.remove(&(space, wid));
         }
+        self.restored_placements.remove(&window_id);
     }
 
     pub fn remove_windows_for_app(&mut self, pid: pid_t) {
@@ -585,11 +1290,55 @@ impl VirtualWorkspaceManager {
                 self.window_rule_floating.remove(&(space, window_id));
                 self.last_rule_decision.remove(&(space, window_id));
             }
+            self.window_tags.remove(&(space, window_id));
+        }
+        self.restored_placements.retain(|window_id, _| window_id.pid != pid);
+        self.launched_apps.remove(&pid);
+    }
+
+    /// Moves everything known about `old` over to `new`, for re-associating a
+    /// restored window with the live window it turned out to be.
+    pub fn replace_window(&mut self, old: WindowId, new: WindowId) {
+        fn rekey<V>(map: &mut HashMap<(SpaceId, WindowId), V>, old: WindowId, new: WindowId) {
+            let spaces: Vec<SpaceId> =
+                map.keys().filter(|(_, wid)| *wid == old).map(|(space, _)| *space).collect();
+            for space in spaces {
+                if let Some(value) = map.remove(&(space, old)) {
+                    map.insert((space, new), value);
+                }
+            }
+        }
+
+        rekey(&mut self.window_to_workspace, old, new);
+        rekey(&mut self.window_rule_floating, old, new);
+        rekey(&mut self.last_rule_decision, old, new);
+        rekey(&mut self.window_tags, old, new);
+        for workspace in self.workspaces.values_mut() {
+            if workspace.windows.remove(&old) {
+                workspace.windows.insert(new);
+            }
+            if workspace.last_focused == Some(old) {
+                workspace.last_focused = Some(new);
+            }
+        }
+        for positions in self.floating_positions.values_mut() {
+            if let Some(frame) = positions.remove_position(old) {
+                positions.store_position(new, frame);
+            }
         }
     }
 
-    /// Gets all windows in the active virtual workspace for a given native space.
+    /// Gets all windows shown on a native space: those in the active virtual
+    /// workspace plus any tagged with, or viewed alongside, it.
     pub fn windows_in_active_workspace(&self, space: SpaceId) -> Vec<WindowId> {
+        if self.uses_tags(space) {
+            let mut windows: Vec<WindowId> = self
+                .windows_on_space(space)
+                .filter(|wid| self.is_window_in_active_workspace(space, *wid))
+                .collect();
+            windows.sort_unstable_by_key(|wid| (wid.pid, wid.idx));
+            return windows;
+        }
         if let Some(workspace_id) = self.active_workspace(space) {
             if let Some(workspace) = self.workspaces.get(workspace_id) {
                 return workspace.windows().collect();
@@ -601,13 +1350,149 @@ impl VirtualWorkspaceManager {
     pub fn is_window_in_active_workspace(&self, space: SpaceId, window_id: WindowId) -> bool {
         if let Some(active_workspace_id) = self.active_workspace(space) {
             if let Some(window_workspace_id) = self.window_to_workspace.get(&(space, window_id)) {
-                return *window_workspace_id == active_workspace_id;
+                if *window_workspace_id == active_workspace_id {
+                    return true;
+                }
+                let view = self.view_workspaces(space);
+                return view.contains(window_workspace_id)
+                    || self
+                        .window_tags
+                        .get(&(space, window_id))
+                        .is_some_and(|tags| tags.iter().any(|tag| view.contains(tag)));
+            }
+        }
+        true
+    }
+
+    pub fn windows_on_space(&self, space: SpaceId) -> impl Iterator<Item = WindowId> + '_ {
+        self.window_to_workspace.keys().filter(move |(sp, _)| *sp == space).map(|(_, wid)| *wid)
+    }
+
+    fn uses_tags(&self, space: SpaceId) -> bool {
+        self.viewed_workspaces.get(&space).is_some_and(|ids| !ids.is_empty())
+            || self.window_tags.keys().any(|(sp, _)| *sp == space)
+    }
+
+    /// The workspaces a window is tagged with, starting with the one it
+    /// belongs to.
+    pub fn window_tags(&self, space: SpaceId, window_id: WindowId) -> Vec<VirtualWorkspaceId> {
+        let mut tags: Vec<_> = self.workspace_for_window(space, window_id).into_iter().collect();
+        tags.extend(self.window_tags.get(&(space, window_id)).into_iter().flatten().copied());
+        tags
+    }
+
+    /// Adds or removes a tag on a window. The last tag cannot be removed; when
+    /// the workspace the window belongs to is untagged, its next tag takes
+    /// over. Returns whether anything changed.
+    pub fn toggle_window_tag(
+        &mut self,
+        space: SpaceId,
+        window_id: WindowId,
+        workspace_id: VirtualWorkspaceId,
+    ) -> bool {
+        if self.workspaces.get(workspace_id).map(|ws| ws.space) != Some(space) {
+            return false;
+        }
+        let Some(home) = self.workspace_for_window(space, window_id) else {
+            return false;
+        };
+        let key = (space, window_id);
+
+        if home != workspace_id {
+            let tags = self.window_tags.entry(key).or_default();
+            if let Some(pos) = tags.iter().position(|&id| id == workspace_id) {
+                tags.remove(pos);
+            } else {
+                tags.push(workspace_id);
             }
+            if tags.is_empty() {
+                self.window_tags.remove(&key);
+            }
+            return true;
+        }
+
+        let Some(tags) = self.window_tags.get_mut(&key).filter(|tags| !tags.is_empty()) else {
+            return false;
+        };
+        let new_home = tags.remove(0);
+        if tags.is_empty() {
+            self.window_tags.remove(&key);
+        }
+        if let Some(ws) = self.workspaces.get_mut(home) {
+            ws.remove_window(window_id);
+        }
+        if let Some(ws) = self.workspaces.get_mut(new_home) {
+            ws.add_window(window_id);
+        }
+        self.window_to_workspace.insert(key, new_home);
+        true
+    }
+
+    /// The workspaces shown on a space: the active one first, then any viewed
+    /// alongside it.
+    pub fn view_workspaces(&self, space: SpaceId) -> Vec<VirtualWorkspaceId> {
+        let active = self.active_workspace(space);
+        let mut view: Vec<_> = active.into_iter().collect();
+        view.extend(
+            self.viewed_workspaces
+                .get(&space)
+                .into_iter()
+                .flatten()
+                .copied()
+                .filter(|&id| Some(id) != active)
+                .filter(|&id| self.workspaces.get(id).is_some_and(|ws| ws.space == space)),
+        );
+        view
+    }
+
+    /// Adds a workspace to, or removes it from, the set shown alongside the
+    /// active workspace. The active workspace itself cannot be toggled off.
+    pub fn toggle_view(&mut self, space: SpaceId, workspace_id: VirtualWorkspaceId) -> bool {
+        if self.active_workspace(space) == Some(workspace_id)
+            || self.workspaces.get(workspace_id).map(|ws| ws.space) != Some(space)
+        {
+            return false;
+        }
+        let viewed = self.viewed_workspaces.entry(space).or_default();
+        if let Some(pos) = viewed.iter().position(|&id| id == workspace_id) {
+            viewed.remove(pos);
+        } else {
+            viewed.push(workspace_id);
         }
         true
     }
 
+    /// Windows the layout of `workspace_id` should tile: those tagged with it
+    /// and, while it is active, those of the workspaces viewed alongside it.
+    pub fn layout_windows(&self, space: SpaceId, workspace_id: VirtualWorkspaceId) -> Vec<WindowId> {
+        let view = if self.active_workspace(space) == Some(workspace_id) {
+            self.view_workspaces(space)
+        } else {
+            vec![workspace_id]
+        };
+        let mut windows: Vec<WindowId> = self
+            .windows_on_space(space)
+            .filter(|&wid| self.window_tags(space, wid).iter().any(|tag| view.contains(tag)))
+            .collect();
+        windows.sort_unstable_by_key(|wid| (wid.pid, wid.idx));
+        windows
+    }
+
+    /// Whether any window carries extra tags or any space views more than one
+    /// workspace, in which case layouts need `layout_windows` kept in sync.
+    pub fn has_tags_or_views(&self) -> bool {
+        !self.window_tags.is_empty() || self.viewed_workspaces.values().any(|ids| !ids.is_empty())
+    }
+
     pub fn windows_in_inactive_workspaces(&self, space: SpaceId) -> Vec<WindowId> {
+        if self.uses_tags(space) {
+            let mut windows: Vec<WindowId> = self
+                .windows_on_space(space)
+                .filter(|wid| !self.is_window_in_active_workspace(space, *wid))
+                .collect();
+            windows.sort_unstable_by_key(|wid| (wid.pid, wid.idx));
+            return windows;
+        }
         let active_workspace_id = self.active_workspace(space);
 
         self.workspaces
@@ -863,6 +1748,164 @@ impl VirtualWorkspaceManager {
         }
     }
 
+    /// Deletes a workspace, moving its windows (and their floating positions)
+    /// to a fallback workspace on the same space. The fallback is the active
+    /// workspace, or the previously active / neighbouring one if the deleted
+    /// workspace was active. Returns the fallback workspace.
+    pub fn delete_workspace(
+        &mut self,
+        space: SpaceId,
+        workspace_id: VirtualWorkspaceId,
+    ) -> Result<VirtualWorkspaceId, WorkspaceError> {
+        if self.workspaces.get(workspace_id).map(|w| w.space) != Some(space) {
+            return Err(WorkspaceError::InvalidWorkspaceId(workspace_id));
+        }
+        let ids = self.workspaces_by_space.get(&space).cloned().unwrap_or_default();
+        let Some(pos) = ids.iter().position(|&id| id == workspace_id) else {
+            return Err(WorkspaceError::InvalidWorkspaceId(workspace_id));
+        };
+        if ids.len() <= 1 {
+            return Err(WorkspaceError::InconsistentState(format!(
+                "Cannot delete the only workspace on space {:?}",
+                space
+            )));
+        }
+
+        let (last, active) = self
+            .active_workspace_per_space
+            .get(&space)
+            .copied()
+            .unwrap_or((None, workspace_id));
+        let fallback = if active != workspace_id {
+            active
+        } else if let Some(last) = last.filter(|&id| id != workspace_id && ids.contains(&id)) {
+            last
+        } else if pos > 0 {
+            ids[pos - 1]
+        } else {
+            ids[pos + 1]
+        };
+
+        let Some(deleted) = self.workspaces.remove(workspace_id) else {
+            return Err(WorkspaceError::InvalidWorkspaceId(workspace_id));
+        };
+        if let Some(target) = self.workspaces.get_mut(fallback) {
+            for window_id in deleted.windows() {
+                target.add_window(window_id);
+                self.window_to_workspace.insert((space, window_id), fallback);
+            }
+        }
+        if let Some(positions) = self.floating_positions.remove(&(space, workspace_id)) {
+            let target = self.floating_positions.entry((space, fallback)).or_default();
+            for window_id in positions.windows() {
+                if let Some(position) = positions.get_position(window_id) {
+                    target.store_if_absent(window_id, position);
+                }
+            }
+        }
+        if let Some(ids) = self.workspaces_by_space.get_mut(&space) {
+            ids.retain(|&id| id != workspace_id);
+        }
+        self.global_order.retain(|&id| id != workspace_id);
+        self.forget_workspace_tags(workspace_id);
+        for window_id in deleted.windows() {
+            if let Some(tags) = self.window_tags.get_mut(&(space, window_id)) {
+                tags.retain(|&id| id != fallback);
+                if tags.is_empty() {
+                    self.window_tags.remove(&(space, window_id));
+                }
+            }
+        }
+
+        let last = last.filter(|&id| id != workspace_id && id != fallback);
+        let active = if active == workspace_id { fallback } else { active };
+        self.active_workspace_per_space.insert(space, (last, active));
+
+        Ok(fallback)
+    }
+
+    /// Moves a workspace to a new position in the space's workspace order.
+    /// Indices past the end move the workspace to the last position.
+    pub fn move_workspace_to_index(
+        &mut self,
+        space: SpaceId,
+        workspace_id: VirtualWorkspaceId,
+        index: usize,
+    ) -> bool {
+        let ids = if self.is_global() {
+            &mut self.global_order
+        } else {
+            let Some(ids) = self.workspaces_by_space.get_mut(&space) else {
+                return false;
+            };
+            ids
+        };
+        let Some(pos) = ids.iter().position(|&id| id == workspace_id) else {
+            return false;
+        };
+        let id = ids.remove(pos);
+        let index = index.min(ids.len());
+        ids.insert(index, id);
+        pos != index
+    }
+
+    /// Exchanges the windows, focus memory and floating positions of two
+    /// workspaces on the same space. Names and positions stay where they are.
+    pub fn swap_workspaces(
+        &mut self,
+        space: SpaceId,
+        a: VirtualWorkspaceId,
+        b: VirtualWorkspaceId,
+    ) -> bool {
+        if a == b
+            || self.workspaces.get(a).map(|w| w.space) != Some(space)
+            || self.workspaces.get(b).map(|w| w.space) != Some(space)
+        {
+            return false;
+        }
+        let Some([ws_a, ws_b]) = self.workspaces.get_disjoint_mut([a, b]) else {
+            return false;
+        };
+        std::mem::swap(&mut ws_a.windows, &mut ws_b.windows);
+        std::mem::swap(&mut ws_a.last_focused, &mut ws_b.last_focused);
+
+        for (target, ws) in [(a, &*ws_a), (b, &*ws_b)] {
+            for window_id in ws.windows() {
+                self.window_to_workspace.insert((space, window_id), target);
+            }
+        }
+
+        let positions_a = self.floating_positions.remove(&(space, a));
+        let positions_b = self.floating_positions.remove(&(space, b));
+        if let Some(positions) = positions_a {
+            self.floating_positions.insert((space, b), positions);
+        }
+        if let Some(positions) = positions_b {
+            self.floating_positions.insert((space, a), positions);
+        }
+        for tags in self.window_tags.values_mut() {
+            for tag in tags.iter_mut() {
+                if *tag == a {
+                    *tag = b;
+                } else if *tag == b {
+                    *tag = a;
+                }
+            }
+        }
+        true
+    }
+
+    /// Drops a workspace from every window's extra tags and every view.
+    fn forget_workspace_tags(&mut self, workspace_id: VirtualWorkspaceId) {
+        self.window_tags.retain(|_, tags| {
+            tags.retain(|&id| id != workspace_id);
+            !tags.is_empty()
+        });
+        for viewed in self.viewed_workspaces.values_mut() {
+            viewed.retain(|&id| id != workspace_id);
+        }
+    }
+
     pub fn workspace_windows(
         &self,
         space: SpaceId,
@@ -892,6 +1935,46 @@ impl VirtualWorkspaceManager {
         }
     }
 
+    /// Routes a window whose app rule targets a workspace pinned to another
+    /// display onto that display's space.
+    fn assign_to_pinned_workspace(
+        &mut self,
+        window_id: WindowId,
+        space: SpaceId,
+        workspace_name: &str,
+        rule: &AppWorkspaceRule,
+        prev_rule_decision: bool,
+        actions: Option<AppRuleActions>,
+    ) -> Option<AppRuleAssignment> {
+        if self.workspace_allowed_on_space(workspace_name, space) {
+            return None;
+        }
+        let target_space = self.space_for_pinned_workspace(workspace_name)?;
+        let (workspace_id, _) = self
+            .list_workspaces(target_space)
+            .into_iter()
+            .find(|(_, name)| name == workspace_name)?;
+        if !self.assign_window_to_workspace(target_space, window_id, workspace_id) {
+            return None;
+        }
+        self.set_last_rule_workspace(target_space, window_id, workspace_id);
+        if rule.floating {
+            self.window_rule_floating.insert((target_space, window_id), true);
+        } else {
+            self.window_rule_floating.remove(&(target_space, window_id));
+        }
+        Some(AppRuleAssignment {
+            workspace_id,
+            floating: rule.floating,
+            prev_rule_decision,
+            actions,
+        })
+    }
+
+    pub fn workspace_space(&self, workspace_id: VirtualWorkspaceId) -> Option<SpaceId> {
+        self.workspaces.get(workspace_id).map(|ws| ws.space)
+    }
+
     pub fn assign_window_with_app_info(
         &mut self,
         window_id: WindowId,
@@ -902,8 +1985,11 @@ impl VirtualWorkspaceManager {
         ax_role: Option<&str>,
         ax_subrole: Option<&str>,
     ) -> Result<AppRuleResult, WorkspaceError> {
-        let prev_rule_decision =
-            self.last_rule_decision.get(&(space, window_id)).copied().unwrap_or(false);
+        let prev_rule_decision = self
+            .last_rule_decision
+            .get(&(space, window_id))
+            .is_some_and(|decision| decision.floating);
+        let first_decision = !self.last_rule_decision.contains_key(&(space, window_id));
 
         self.ensure_space_initialized(space);
         if self.workspaces_by_space.get(&space).map(|v| v.is_empty()).unwrap_or(true) {
@@ -921,57 +2007,33 @@ impl VirtualWorkspaceManager {
                 self.window_rule_floating.remove(&(space, window_id));
                 return Ok(AppRuleResult::Unmanaged);
             }
+            let actions = if first_decision { AppRuleActions::from_rule(&rule) } else { None };
+
+            if existing_assignment.is_none()
+                && let Some(WorkspaceSelector::Name(name)) = &rule.workspace
+                && let Some(assignment) = self.assign_to_pinned_workspace(
+                    window_id,
+                    space,
+                    name,
+                    &rule,
+                    prev_rule_decision,
+                    actions.clone(),
+                )
+            {
+                return Ok(AppRuleResult::Managed(assignment));
+            }
 
-            let target_workspace_id = if let Some(ref ws_sel) = rule.workspace {
-                let maybe_idx: Option<usize> = match ws_sel {
-                    WorkspaceSelector::Index(i) => Some(*i),
-                    WorkspaceSelector::Name(name) => {
-                        let workspaces = self.list_workspaces(space);
-                        match workspaces.iter().position(|(_, n)| n == name) {
-                            Some(idx) => Some(idx),
-                            None => {
-                                tracing::warn!(
-                                    "App rule references workspace name '{}' which could not be resolved for space {:?}; falling back to default workspace",
-                                    name,
-                                    space
-                                );
-                                None
-                            }
-                        }
-                    }
-                };
-
-                if let Some(workspace_idx) = maybe_idx {
-                    let len = self.workspaces_by_space.get(&space).map(|v| v.len()).unwrap_or(0);
-                    if workspace_idx >= len {
-                        tracing::warn!(
-                            "App rule references non-existent workspace index {}, falling back to active workspace",
-                            workspace_idx
-                        );
-                        self.get_default_workspace(space)?
-                    } else {
-                        let workspaces = self.list_workspaces(space);
-                        if let Some((workspace_id, _)) = workspaces.get(workspace_idx) {
-                            *workspace_id
-                        } else {
-                            tracing::warn!(
-                                "App rule references invalid workspace index {}, falling back to active workspace",
-                                workspace_idx
-                            );
-                            self.get_default_workspace(space)?
-                        }
-                    }
-                } else if let Some(existing_ws) = existing_assignment {
-                    existing_ws
-                } else {
-                    self.get_default_workspace(space)?
-                }
-            } else {
-                if let Some(existing_ws) = existing_assignment {
-                    existing_ws
-                } else {
-                    self.get_default_workspace(space)?
-                }
+            let resolved = match rule.workspace {
+                Some(ref ws_sel) => self.resolve_rule_workspace(space, ws_sel),
+                None => None,
+            };
+            // A workspace index past the last workspace falls back to the
+            // default workspace rather than the window's current one.
+            let index_rule = matches!(rule.workspace, Some(WorkspaceSelector::Index(_)));
+            let target_workspace_id = match (resolved, existing_assignment) {
+                (Some(workspace_id), _) => workspace_id,
+                (None, Some(workspace_id)) if !index_rule => workspace_id,
+                _ => self.get_default_workspace(space)?,
             };
 
             if let Some(existing_ws) = existing_assignment {
@@ -984,10 +2046,14 @@ impl VirtualWorkspaceManager {
                     workspace_id: existing_ws,
                     floating: rule.floating,
                     prev_rule_decision,
+                    actions,
                 }));
             }
 
             if self.assign_window_to_workspace(space, window_id, target_workspace_id) {
+                if resolved.is_some() {
+                    self.set_last_rule_workspace(space, window_id, target_workspace_id);
+                }
                 if rule.floating {
                     self.window_rule_floating.insert((space, window_id), true);
                 } else {
@@ -997,6 +2063,7 @@ impl VirtualWorkspaceManager {
                     workspace_id: target_workspace_id,
                     floating: rule.floating,
                     prev_rule_decision,
+                    actions,
                 }));
             } else {
                 error!("Failed to assign window to workspace from app rule");
@@ -1009,6 +2076,20 @@ impl VirtualWorkspaceManager {
                 workspace_id: existing_ws,
                 floating: false,
                 prev_rule_decision,
+                actions: None,
+            }));
+        }
+
+        if let Some(bundle_id) = app_bundle_id
+            && let Some(workspace_id) =
+                self.restore_remembered_placement(window_id, space, bundle_id, window_title)
+        {
+            self.window_rule_floating.remove(&(space, window_id));
+            return Ok(AppRuleResult::Managed(AppRuleAssignment {
+                workspace_id,
+                floating: false,
+                prev_rule_decision,
+                actions: None,
             }));
         }
 
@@ -1019,6 +2100,7 @@ impl VirtualWorkspaceManager {
                 workspace_id: default_workspace_id,
                 floating: false,
                 prev_rule_decision,
+                actions: None,
             }))
         } else {
             error!("Failed to assign window to default workspace");
@@ -1026,6 +2108,124 @@ impl VirtualWorkspaceManager {
         }
     }
 
+    pub fn set_placement_memory(&mut self, memory: PlacementMemory) {
+        self.placement_memory = memory;
+    }
+
+    pub fn placement_memory_mut(&mut self) -> &mut PlacementMemory { &mut self.placement_memory }
+
+    /// Lets the first windows of a just launched app return to where the app's
+    /// windows last lived.
+    pub fn mark_app_launched(&mut self, pid: pid_t) { self.launched_apps.insert(pid, 0); }
+
+    /// Records where a window lives in the placement memory, for the app's
+    /// windows to return there after a relaunch. Returns whether the memory
+    /// changed.
+    pub fn remember_placement(
+        &mut self,
+        window_id: WindowId,
+        bundle_id: &str,
+        title: &str,
+        floating: bool,
+        frame: CGRect,
+    ) -> bool {
+        let Some((space, workspace_id)) = self.locate_window(window_id) else {
+            return false;
+        };
+        let Some(workspace) = self.workspaces.get(workspace_id) else {
+            return false;
+        };
+        let placement = RememberedPlacement {
+            title_pattern: title_pattern(title),
+            workspace: workspace.name.clone(),
+            floating,
+            frame: floating.then_some(frame),
+            display_uuid: self.space_displays.get(&space).cloned(),
+        };
+        self.placement_memory.remember(bundle_id, placement)
+    }
+
+    /// Assigns one of the first windows of a newly launched app to the
+    /// workspace a window with its title last lived in, preferring that
+    /// workspace on the display it was on. The app's windows stop being
+    /// restored at the first one without a remembered placement, or once each
+    /// remembered placement had its chance.
+    fn restore_remembered_placement(
+        &mut self,
+        window_id: WindowId,
+        space: SpaceId,
+        bundle_id: &str,
+        title: Option<&str>,
+    ) -> Option<VirtualWorkspaceId> {
+        let restored = *self.launched_apps.get(&window_id.pid)?;
+        let remembered = self.placement_memory.placement_count(bundle_id);
+        let placement = self.placement_memory.lookup(bundle_id, title).cloned();
+        if placement.is_none() || restored + 1 >= remembered {
+            self.launched_apps.remove(&window_id.pid);
+        } else {
+            self.launched_apps.insert(window_id.pid, restored + 1);
+        }
+        let placement = placement?;
+        let remembered_space = placement.display_uuid.as_ref().and_then(|uuid| {
+            self.space_displays.iter().find(|(_, display)| *display == uuid).map(|(s, _)| *s)
+        });
+        let (target_space, workspace_id) = remembered_space
+            .into_iter()
+            .chain(std::iter::once(space))
+            .find_map(|candidate| {
+                let workspace_id = self
+                    .list_workspaces(candidate)
+                    .into_iter()
+                    .find(|(_, name)| *name == placement.workspace)?
+                    .0;
+                Some((candidate, workspace_id))
+            })?;
+        if !self.assign_window_to_workspace(target_space, window_id, workspace_id) {
+            return None;
+        }
+        self.restored_placements.insert(window_id, placement);
+        Some(workspace_id)
+    }
+
+    /// Takes the remembered placement restored for a new window, if any.
+    pub fn take_restored_placement(&mut self, window_id: WindowId) -> Option<RememberedPlacement> {
+        self.restored_placements.remove(&window_id)
+    }
+
+    /// The workspace on `space` named by an app rule's `workspace`, if it
+    /// resolves there.
+    fn resolve_rule_workspace(
+        &mut self,
+        space: SpaceId,
+        selector: &WorkspaceSelector,
+    ) -> Option<VirtualWorkspaceId> {
+        let workspaces = self.list_workspaces(space);
+        match selector {
+            WorkspaceSelector::Index(idx) => {
+                let workspace_id = workspaces.get(*idx).map(|(id, _)| *id);
+                if workspace_id.is_none() {
+                    tracing::warn!(
+                        "App rule references non-existent workspace index {}, falling back to active workspace",
+                        idx
+                    );
+                }
+                workspace_id
+            }
+            WorkspaceSelector::Name(name) => {
+                let workspace_id =
+                    workspaces.iter().find(|(_, n)| n == name).map(|(id, _)| *id);
+                if workspace_id.is_none() {
+                    tracing::warn!(
+                        "App rule references workspace name '{}' which could not be resolved for space {:?}; falling back to default workspace",
+                        name,
+                        space
+                    );
+                }
+                workspace_id
+            }
+        }
+    }
+
     fn get_default_workspace(
         &mut self,
         space: SpaceId,
@@ -1065,166 +2265,178 @@ impl VirtualWorkspaceManager {
         ax_role: Option<&str>,
         ax_subrole: Option<&str>,
     ) -> Option<&AppWorkspaceRule> {
-        let mut matches: Vec<(usize, &AppWorkspaceRule, usize)> = Vec::new();
-
-        for (idx, rule) in self.app_rules.iter().enumerate() {
-            if let Some(ref rule_app_id) = rule.app_id {
-                match app_bundle_id {
-                    Some(bundle_id) if rule_app_id.eq_ignore_ascii_case(bundle_id) => {}
-                    _ => continue,
-                }
-            }
-
-            if let Some(ref rule_name) = rule.app_name {
-                match app_name {
-                    Some(name) => {
-                        let name_l = name.to_lowercase();
-                        let rule_name_l = rule_name.to_lowercase();
-                        if !(name_l.contains(&rule_name_l) || rule_name_l.contains(&name_l)) {
-                            continue;
-                        }
-                    }
-                    None => continue,
-                }
-            }
+        let (_, winner) =
+            self.rank_app_rules(app_bundle_id, app_name, window_title, ax_role, ax_subrole);
+        winner.map(|(idx, _)| &self.app_rules[idx])
+    }
 
-            if let Some(ref rule_re) = rule.title_regex {
-                if rule_re.is_empty() {
-                    continue;
-                }
-                match window_title {
-                    Some(title) => match self.app_rule_regex_cache.get(idx) {
-                        Some(Some(re)) => {
-                            if !re.is_match(title) {
-                                continue;
-                            }
-                        }
-                        _ => continue,
-                    },
-                    None => continue,
-                }
-            }
+    /// Moves an already assigned window to the workspace its matching app rule
+    /// now names, for re-applying rules after they change. A window moved away
+    /// from where a rule last placed it stays where the user put it. Returns the
+    /// workspace the window was moved to.
+    pub fn reapply_rule_workspace(
+        &mut self,
+        window_id: WindowId,
+        space: SpaceId,
+        query: &AppRuleQuery,
+    ) -> Option<VirtualWorkspaceId> {
+        let current = self.window_to_workspace.get(&(space, window_id)).copied()?;
+        if self
+            .last_rule_decision
+            .get(&(space, window_id))
+            .and_then(|decision| decision.workspace)
+            .is_some_and(|placed| placed != current)
+        {
+            return None;
+        }
 
-            // Case-insensitive substring matching for title_substring
-            if let Some(ref title_sub) = rule.title_substring {
-                if title_sub.is_empty() {
-                    continue;
-                }
-                match window_title {
-                    Some(title) => {
-                        let title_l = title.to_lowercase();
-                        let sub_l = title_sub.to_lowercase();
-                        if !title_l.contains(&sub_l) {
-                            continue;
-                        }
-                    }
-                    None => continue,
-                }
-            }
+        let rule = self
+            .find_matching_app_rule(
+                query.app_id.as_deref(),
+                query.app_name.as_deref(),
+                query.title.as_deref(),
+                query.ax_role.as_deref(),
+                query.ax_subrole.as_deref(),
+            )
+            .filter(|rule| rule.manage)?
+            .clone();
+        let target = self.resolve_rule_workspace(space, rule.workspace.as_ref()?)?;
+        self.set_last_rule_workspace(space, window_id, target);
+        if target == current || !self.assign_window_to_workspace(space, window_id, target) {
+            return None;
+        }
+        Some(target)
+    }
+
+    /// Runs app-rule matching for a window description without assigning
+    /// anything, reporting every matching rule and why the winner won.
+    pub fn explain_app_rules(&self, query: &AppRuleQuery) -> AppRuleExplanation {
+        let (matches, winner) = self.rank_app_rules(
+            query.app_id.as_deref(),
+            query.app_name.as_deref(),
+            query.title.as_deref(),
+            query.ax_role.as_deref(),
+            query.ax_subrole.as_deref(),
+        );
 
-            if let Some(ref rule_ax_role) = rule.ax_role {
-                if rule_ax_role.is_empty() {
-                    continue;
-                }
-                match ax_role {
-                    Some(r) => {
-                        if r != rule_ax_role.as_str() {
-                            continue;
-                        }
-                    }
-                    None => continue,
+        let reason = match &winner {
+            None => "no rule matched".to_string(),
+            Some((idx, RuleChoice::OnlyMatch)) => format!("rule {} is the only match", idx),
+            Some((idx, choice)) => {
+                let specificity = matches.iter().find(|(i, _)| i == idx).map_or(0, |m| m.1);
+                let (pool, why) = match choice {
+                    RuleChoice::SharedAppId(app_id) => (
+                        matches
+                            .iter()
+                            .filter(|(i, _)| self.app_rules[*i].app_id.as_deref() == Some(*app_id))
+                            .collect::<Vec<_>>(),
+                        format!("matching rules share app_id '{}', so only they compete; ", app_id),
+                    ),
+                    _ => (matches.iter().collect(), String::new()),
+                };
+                let tied: Vec<String> = pool
+                    .iter()
+                    .filter(|(i, score)| i != idx && *score == specificity)
+                    .map(|(i, _)| i.to_string())
+                    .collect();
+                let mut reason = format!(
+                    "{}rule {} has the highest specificity ({} conditions)",
+                    why, idx, specificity
+                );
+                if !tied.is_empty() {
+                    reason.push_str(&format!(
+                        " and is defined before tied rule(s) {}",
+                        tied.join(", ")
+                    ));
                 }
+                reason
             }
+        };
 
-            if let Some(ref rule_ax_sub) = rule.ax_subrole {
-                if rule_ax_sub.is_empty() {
-                    continue;
-                }
-                match ax_subrole {
-                    Some(sr) => {
-                        if sr != rule_ax_sub.as_str() {
-                            continue;
-                        }
-                    }
-                    None => continue,
-                }
-            }
+        AppRuleExplanation {
+            query: query.clone(),
+            matches: matches
+                .into_iter()
+                .map(|(index, specificity)| AppRuleMatch {
+                    index,
+                    specificity,
+                    rule: self.app_rules[index].clone(),
+                })
+                .collect(),
+            winner: winner.map(|(idx, _)| idx),
+            reason,
+        }
+    }
 
-            let mut score = 0usize;
-            if rule.app_id.as_ref().map_or(false, |s| !s.is_empty()) {
-                score += 1;
-            }
-            if rule.app_name.as_ref().map_or(false, |s| !s.is_empty()) {
-                score += 1;
-            }
-            if rule.title_regex.as_ref().map_or(false, |s| !s.is_empty()) {
-                score += 1;
-            }
-            if rule.title_substring.as_ref().map_or(false, |s| !s.is_empty()) {
-                score += 1;
-            }
-            if rule.ax_role.as_ref().map_or(false, |s| !s.is_empty()) {
-                score += 1;
-            }
-            if rule.ax_subrole.as_ref().map_or(false, |s| !s.is_empty()) {
-                score += 1;
-            }
-
-            matches.push((idx, rule, score));
-        }
+    /// Every rule matching the window as `(index, specificity)`, plus the
+    /// winning rule's index and why it won.
+    ///
+    /// When several matching rules share a non-empty `app_id`, only that group
+    /// competes (the group holding the earliest rule if there are several);
+    /// otherwise every match does. The most specific rule wins and earlier
+    /// rules break ties.
+    fn rank_app_rules(
+        &self,
+        app_bundle_id: Option<&str>,
+        app_name: Option<&str>,
+        window_title: Option<&str>,
+        ax_role: Option<&str>,
+        ax_subrole: Option<&str>,
+    ) -> (Vec<(usize, usize)>, Option<(usize, RuleChoice<'_>)>) {
+        let matches: Vec<(usize, usize)> = self
+            .app_rules
+            .iter()
+            .enumerate()
+            .filter(|(idx, rule)| {
+                let title_regex = self.app_rule_regex_cache.get(*idx).and_then(Option::as_ref);
+                app_rule_matches(
+                    rule,
+                    title_regex,
+                    app_bundle_id,
+                    app_name,
+                    window_title,
+                    ax_role,
+                    ax_subrole,
+                )
+            })
+            .map(|(idx, rule)| (idx, app_rule_specificity(rule)))
+            .collect();
 
         if matches.is_empty() {
-            return None;
+            return (matches, None);
         }
 
         if matches.len() == 1 {
-            return Some(matches[0].1);
-        }
-
-        let mut groups: HashMap<&str, Vec<&(usize, &AppWorkspaceRule, usize)>> = HashMap::default();
-        for entry in &matches {
-            if let Some(ref app_id) = entry.1.app_id {
-                if !app_id.is_empty() {
-                    groups.entry(app_id.as_str()).or_default().push(entry);
-                }
-            }
+            let winner = (matches[0].0, RuleChoice::OnlyMatch);
+            return (matches, Some(winner));
         }
 
-        if !groups.is_empty() {
-            let mut candidate_group_key: Option<&str> = None;
-            let mut candidate_group_first_idx: Option<usize> = None;
-
-            for (key, vec_entries) in groups.iter() {
-                if vec_entries.len() > 1 {
-                    let first_idx = vec_entries.iter().map(|e| e.0).min().unwrap_or(usize::MAX);
-                    if candidate_group_key.is_none()
-                        || first_idx < candidate_group_first_idx.unwrap()
-                    {
-                        candidate_group_key = Some(*key);
-                        candidate_group_first_idx = Some(first_idx);
-                    }
-                }
-            }
-
-            if let Some(key) = candidate_group_key {
-                if let Some(vec_entries) = groups.get(key) {
-                    let best = vec_entries.iter().copied().max_by(|a, b| match a.2.cmp(&b.2) {
-                        std::cmp::Ordering::Equal => b.0.cmp(&a.0), // prefer earlier-defined rule on tie
-                        ord => ord,
-                    });
-                    if let Some(best_entry) = best {
-                        return Some(best_entry.1);
-                    }
-                }
+        let mut groups: HashMap<&str, Vec<usize>> = HashMap::default();
+        for &(idx, _) in &matches {
+            if let Some(app_id) = self.app_rules[idx].app_id.as_deref()
+                && !app_id.is_empty()
+            {
+                groups.entry(app_id).or_default().push(idx);
             }
         }
 
-        let best_overall = matches.iter().max_by(|a, b| match a.2.cmp(&b.2) {
-            std::cmp::Ordering::Equal => b.0.cmp(&a.0), // prefer earlier-defined rule on tie
-            ord => ord,
-        });
+        let shared_group = groups
+            .iter()
+            .filter(|(_, members)| members.len() > 1)
+            .min_by_key(|(_, members)| members.iter().copied().min().unwrap_or(usize::MAX))
+            .map(|(app_id, _)| *app_id);
+
+        let winner = match shared_group {
+            Some(app_id) => most_specific(
+                matches
+                    .iter()
+                    .filter(|(idx, _)| self.app_rules[*idx].app_id.as_deref() == Some(app_id)),
+            )
+            .map(|idx| (idx, RuleChoice::SharedAppId(app_id))),
+            None => most_specific(matches.iter()).map(|idx| (idx, RuleChoice::MostSpecific)),
+        };
 
-        best_overall.map(|(_, rule, _)| *rule)
+        (matches, winner)
     }
 
     pub fn get_stats(&self) -> WorkspaceStats {
@@ -1288,6 +2500,134 @@ pub struct WorkspaceStats {
     pub workspace_window_counts: HashMap<VirtualWorkspaceId, usize>,
 }
 
+/// Number of conditions a rule specifies; more specific rules win ties
+/// between matches.
+fn app_rule_specificity(rule: &AppWorkspaceRule) -> usize {
+    [
+        &rule.app_id,
+        &rule.app_name,
+        &rule.title_regex,
+        &rule.title_substring,
+        &rule.ax_role,
+        &rule.ax_subrole,
+    ]
+    .into_iter()
+    .filter(|field| field.as_deref().is_some_and(|s| !s.is_empty()))
+    .count()
+}
+
+/// The index of the most specific `(index, specificity)` entry, preferring the
+/// earlier-defined rule on a tie.
+fn most_specific<'a>(entries: impl Iterator<Item = &'a (usize, usize)>) -> Option<usize> {
+    entries
+        .max_by(|a, b| match a.1.cmp(&b.1) {
+            std::cmp::Ordering::Equal => b.0.cmp(&a.0),
+            ord => ord,
+        })
+        .map(|entry| entry.0)
+}
+
+/// Compiles an app rule `title_regex` the way rules match titles: case-insensitively.
+pub fn compile_title_regex(pattern: &str) -> Result<regex::Regex, regex::Error> {
+    regex::RegexBuilder::new(pattern).case_insensitive(true).build()
+}
+
+/// Whether a window satisfies every criterion of `rule`. `title_regex` is the
+/// compiled form of `rule.title_regex`.
+pub fn app_rule_matches(
+    rule: &AppWorkspaceRule,
+    title_regex: Option<&regex::Regex>,
+    app_bundle_id: Option<&str>,
+    app_name: Option<&str>,
+    window_title: Option<&str>,
+    ax_role: Option<&str>,
+    ax_subrole: Option<&str>,
+) -> bool {
+    if let Some(ref rule_app_id) = rule.app_id {
+        match app_bundle_id {
+            Some(bundle_id) if rule_app_id.eq_ignore_ascii_case(bundle_id) => {}
+            _ => return false,
+        }
+    }
+
+    if let Some(ref rule_name) = rule.app_name {
+        match app_name {
+            Some(name) => {
+                let name_l = name.to_lowercase();
+                let rule_name_l = rule_name.to_lowercase();
+                if !(name_l.contains(&rule_name_l) || rule_name_l.contains(&name_l)) {
+                    return false;
+                }
+            }
+            None => return false,
+        }
+    }
+
+    if let Some(ref rule_re) = rule.title_regex {
+        if rule_re.is_empty() {
+            return false;
+        }
+        match window_title {
+            Some(title) => match title_regex {
+                Some(re) => {
+                    if !re.is_match(title) {
+                        return false;
+                    }
+                }
+                None => return false,
+            },
+            None => return false,
+        }
+    }
+
+    // Case-insensitive substring matching for title_substring
+    if let Some(ref title_sub) = rule.title_substring {
+        if title_sub.is_empty() {
+            return false;
+        }
+        match window_title {
+            Some(title) => {
+                let title_l = title.to_lowercase();
+                let sub_l = title_sub.to_lowercase();
+                if !title_l.contains(&sub_l) {
+                    return false;
+                }
+            }
+            None => return false,
+        }
+    }
+
+    if let Some(ref rule_ax_role) = rule.ax_role {
+        if rule_ax_role.is_empty() {
+            return false;
+        }
+        match ax_role {
+            Some(r) => {
+                if r != rule_ax_role.as_str() {
+                    return false;
+                }
+            }
+            None => return false,
+        }
+    }
+
+    if let Some(ref rule_ax_sub) = rule.ax_subrole {
+        if rule_ax_sub.is_empty() {
+            return false;
+        }
+        match ax_subrole {
+            Some(sr) => {
+                if sr != rule_ax_sub.as_str() {
+                    return false;
+                }
+            }
+            None => return false,
+        }
+    }
+
+    true
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1450,6 +2790,603 @@ mod tests {
         assert_eq!(manager.prev_workspace(space, ws3_id, None), Some(ws2_id));
     }
 
+    #[test]
+    fn delete_workspace_moves_windows_to_fallback() {
+        let mut manager = VirtualWorkspaceManager::new();
+        let space = SpaceId::new(1);
+        let ws1_id = manager.create_workspace(space, Some("WS1".to_string())).unwrap();
+        let ws2_id = manager.create_workspace(space, Some("WS2".to_string())).unwrap();
+        let window1 = WindowId::new(1, 1);
+        let window2 = WindowId::new(1, 2);
+        let frame = CGRect::new(CGPoint::new(10.0, 10.0), CGSize::new(100.0, 100.0));
+
+        manager.set_active_workspace(space, ws1_id);
+        manager.assign_window_to_workspace(space, window1, ws1_id);
+        manager.assign_window_to_workspace(space, window2, ws2_id);
+        manager.store_floating_position(space, ws2_id, window2, frame);
+
+        assert_eq!(manager.delete_workspace(space, ws2_id), Ok(ws1_id));
+        assert!(manager.workspace_info(space, ws2_id).is_none());
+        assert!(!manager.list_workspaces(space).iter().any(|(id, _)| *id == ws2_id));
+        assert_eq!(manager.workspace_for_window(space, window2), Some(ws1_id));
+        assert!(manager.workspace_info(space, ws1_id).unwrap().contains_window(window2));
+        assert_eq!(manager.get_floating_position(space, ws1_id, window2), Some(frame));
+        assert_eq!(manager.active_workspace(space), Some(ws1_id));
+
+        // Deleting the active workspace falls back to the previously active one.
+        let ws3_id = manager.create_workspace(space, Some("WS3".to_string())).unwrap();
+        manager.set_active_workspace(space, ws3_id);
+        assert_eq!(manager.delete_workspace(space, ws3_id), Ok(ws1_id));
+        assert_eq!(manager.active_workspace(space), Some(ws1_id));
+        assert_eq!(manager.last_workspace(space), None);
+    }
+
+    #[test]
+    fn delete_last_remaining_workspace_fails() {
+        let mut settings = VirtualWorkspaceSettings::default();
+        settings.default_workspace_count = 1;
+        let mut manager = VirtualWorkspaceManager::new_with_config(&settings);
+        let space = SpaceId::new(1);
+        let only = manager.list_workspaces(space)[0].0;
+
+        assert!(manager.delete_workspace(space, only).is_err());
+        assert_eq!(manager.active_workspace(space), Some(only));
+    }
+
+    #[test]
+    fn move_workspace_to_index_reorders() {
+        let mut manager = VirtualWorkspaceManager::new();
+        let space = SpaceId::new(1);
+        let ws1_id = manager.create_workspace(space, Some("WS1".to_string())).unwrap();
+        let ws2_id = manager.create_workspace(space, Some("WS2".to_string())).unwrap();
+
+        assert!(manager.move_workspace_to_index(space, ws2_id, 0));
+        let ids: Vec<_> = manager.list_workspaces(space).into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids[0], ws2_id);
+
+        assert!(manager.move_workspace_to_index(space, ws2_id, usize::MAX));
+        let ids: Vec<_> = manager.list_workspaces(space).into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids.last(), Some(&ws2_id));
+        assert_eq!(ids[ids.len() - 2], ws1_id);
+        assert!(!manager.move_workspace_to_index(space, ws2_id, ids.len() - 1));
+    }
+
+    #[test]
+    fn swap_workspaces_exchanges_contents() {
+        let mut manager = VirtualWorkspaceManager::new();
+        let space = SpaceId::new(1);
+        let ws1_id = manager.create_workspace(space, Some("WS1".to_string())).unwrap();
+        let ws2_id = manager.create_workspace(space, Some("WS2".to_string())).unwrap();
+        let window1 = WindowId::new(1, 1);
+        let window2 = WindowId::new(1, 2);
+        let frame = CGRect::new(CGPoint::new(10.0, 10.0), CGSize::new(100.0, 100.0));
+
+        manager.assign_window_to_workspace(space, window1, ws1_id);
+        manager.assign_window_to_workspace(space, window2, ws2_id);
+        manager.store_floating_position(space, ws1_id, window1, frame);
+
+        assert!(manager.swap_workspaces(space, ws1_id, ws2_id));
+        assert_eq!(manager.workspace_for_window(space, window1), Some(ws2_id));
+        assert_eq!(manager.workspace_for_window(space, window2), Some(ws1_id));
+        assert_eq!(manager.workspace_info(space, ws1_id).unwrap().name, "WS1");
+        assert_eq!(manager.get_floating_position(space, ws2_id, window1), Some(frame));
+        assert_eq!(manager.get_floating_position(space, ws1_id, window1), None);
+        assert!(!manager.swap_workspaces(space, ws1_id, ws1_id));
+    }
+
+    fn dynamic_manager(count: usize) -> VirtualWorkspaceManager {
+        let mut settings = VirtualWorkspaceSettings::default();
+        settings.dynamic = true;
+        settings.default_workspace_count = count;
+        settings.workspace_names = Vec::new();
+        VirtualWorkspaceManager::new_with_config(&settings)
+    }
+
+    fn workspace_ids(
+        manager: &mut VirtualWorkspaceManager,
+        space: SpaceId,
+    ) -> Vec<VirtualWorkspaceId> {
+        manager.list_workspaces(space).into_iter().map(|(id, _)| id).collect()
+    }
+
+    #[test]
+    fn dynamic_workspaces_keep_one_trailing_empty_workspace() {
+        let mut manager = dynamic_manager(1);
+        let space = SpaceId::new(1);
+
+        // The initial workspace is active, so an empty trailing one is added.
+        let ids = workspace_ids(&mut manager, space);
+        assert_eq!(ids.len(), 2);
+        let first = ids[0];
+        let trailing = ids[1];
+
+        // Moving a window to the trailing workspace creates another one.
+        assert!(manager.assign_window_to_workspace(space, WindowId::new(1, 1), trailing));
+        assert!(manager.reconcile_dynamic_workspaces(space).is_empty());
+        let ids = workspace_ids(&mut manager, space);
+        assert_eq!(ids.len(), 3);
+        assert_eq!(&ids[..2], &[first, trailing]);
+
+        // Switching to the new trailing workspace creates another one as well.
+        manager.set_active_workspace(space, ids[2]);
+        manager.reconcile_dynamic_workspaces(space);
+        let ids = workspace_ids(&mut manager, space);
+        assert_eq!(ids.len(), 4);
+        assert!(manager.workspace_windows(space, ids[3]).is_empty());
+    }
+
+    #[test]
+    fn dynamic_workspaces_remove_empty_inactive_workspaces() {
+        let mut manager = dynamic_manager(1);
+        let space = SpaceId::new(1);
+        let ids = workspace_ids(&mut manager, space);
+        let window = WindowId::new(1, 1);
+
+        manager.assign_window_to_workspace(space, window, ids[1]);
+        manager.set_active_workspace(space, ids[1]);
+        manager.reconcile_dynamic_workspaces(space);
+        let before = workspace_ids(&mut manager, space);
+        assert_eq!(before.len(), 3);
+
+        // Emptying the inactive middle workspace removes it and compacts indices.
+        manager.set_active_workspace(space, ids[0]);
+        manager.remove_window(window);
+        assert_eq!(manager.reconcile_dynamic_workspaces(space), vec![ids[1]]);
+        let after = workspace_ids(&mut manager, space);
+        assert_eq!(after, vec![ids[0], before[2]]);
+        assert_eq!(manager.last_workspace(space), None);
+    }
+
+    #[test]
+    fn dynamic_workspaces_respect_bounds() {
+        let mut manager = dynamic_manager(3);
+        let space = SpaceId::new(1);
+
+        // Workspaces below default_workspace_count are never removed.
+        let ids = workspace_ids(&mut manager, space);
+        assert_eq!(ids.len(), 3);
+        assert!(manager.reconcile_dynamic_workspaces(space).is_empty());
+
+        for (i, id) in ids.iter().enumerate() {
+            manager.assign_window_to_workspace(space, WindowId::new(1, i as u32 + 1), *id);
+        }
+        for i in 0..64 {
+            let last = *workspace_ids(&mut manager, space).last().unwrap();
+            manager.assign_window_to_workspace(space, WindowId::new(2, i + 1), last);
+            manager.reconcile_dynamic_workspaces(space);
+        }
+        assert_eq!(workspace_ids(&mut manager, space).len(), manager.max_workspaces);
+    }
+
+    #[test]
+    fn static_workspaces_ignore_reconcile() {
+        let mut manager = VirtualWorkspaceManager::new();
+        let space = SpaceId::new(1);
+        let before = workspace_ids(&mut manager, space);
+        assert!(manager.reconcile_dynamic_workspaces(space).is_empty());
+        assert_eq!(workspace_ids(&mut manager, space), before);
+    }
+
+    fn pinned_manager() -> VirtualWorkspaceManager {
+        let mut settings = VirtualWorkspaceSettings::default();
+        settings.default_workspace_count = 3;
+        settings.workspace_names = vec!["mail".into(), "code".into(), "web".into()];
+        settings.workspace_displays.insert("mail".into(), "display-a".into());
+        settings.workspace_displays.insert("code".into(), "main".into());
+        VirtualWorkspaceManager::new_with_config(&settings)
+    }
+
+    fn workspace_names(manager: &mut VirtualWorkspaceManager, space: SpaceId) -> Vec<String> {
+        manager.list_workspaces(space).into_iter().map(|(_, name)| name).collect()
+    }
+
+    #[test]
+    fn pinned_workspaces_only_exist_on_their_display() {
+        let mut manager = pinned_manager();
+        let space_a = SpaceId::new(1);
+        let space_b = SpaceId::new(2);
+        manager.set_main_display(Some("display-b".into()));
+        manager.set_space_display(space_a, Some("display-a".into()));
+        manager.set_space_display(space_b, Some("display-b".into()));
+
+        assert_eq!(workspace_names(&mut manager, space_a), vec!["mail", "web"]);
+        assert_eq!(workspace_names(&mut manager, space_b), vec!["code", "web"]);
+        assert_eq!(manager.space_for_pinned_workspace("mail"), Some(space_a));
+        assert_eq!(manager.space_for_pinned_workspace("code"), Some(space_b));
+        assert_eq!(manager.space_for_pinned_workspace("web"), None);
+    }
+
+    #[test]
+    fn pins_apply_when_display_becomes_known() {
+        let mut manager = pinned_manager();
+        let space = SpaceId::new(1);
+
+        // Without a display every pinned workspace is withheld.
+        assert_eq!(workspace_names(&mut manager, space), vec!["web"]);
+
+        let removed = manager.set_space_display(space, Some("display-a".into()));
+        assert!(removed.is_empty());
+        assert_eq!(workspace_names(&mut manager, space), vec!["web", "mail"]);
+
+        // Moving the space to another display drops the now misplaced empty workspace.
+        let mail = manager.list_workspaces(space)[1].0;
+        let removed = manager.set_space_display(space, Some("display-c".into()));
+        assert_eq!(removed, vec![mail]);
+        assert_eq!(workspace_names(&mut manager, space), vec!["web"]);
+    }
+
+    #[test]
+    fn app_rules_route_windows_to_pinned_display() {
+        let mut manager = pinned_manager();
+        manager.app_rules = vec![AppWorkspaceRule {
+            app_id: Some("com.apple.mail".into()),
+            workspace: Some(WorkspaceSelector::Name("mail".into())),
+            floating: false,
+            manage: true,
+            app_name: None,
+            title_regex: None,
+            title_substring: None,
+            ax_role: None,
+            ax_subrole: None,
+            ..Default::default()
+        }];
+        manager.rebuild_app_rule_regex_cache();
+        let space_a = SpaceId::new(1);
+        let space_b = SpaceId::new(2);
+        manager.set_space_display(space_a, Some("display-a".into()));
+        manager.set_space_display(space_b, Some("display-b".into()));
+        let mail = manager.list_workspaces(space_a)[0].0;
+
+        let window = WindowId::new(1, 1);
+        let assignment =
+            assign(&mut manager, window, space_b, Some("com.apple.mail"), None, None, None, None);
+        assert_eq!(assignment.workspace_id, mail);
+        assert_eq!(manager.workspace_space(mail), Some(space_a));
+        assert_eq!(manager.workspace_for_window(space_a, window), Some(mail));
+        assert_eq!(manager.workspace_for_window(space_b, window), None);
+    }
+
+    #[test]
+    fn app_rule_actions_only_apply_to_first_decision() {
+        let mut manager = VirtualWorkspaceManager::new_with_rules(vec![AppWorkspaceRule {
+            app_id: Some("com.apple.QuickTimePlayerX".into()),
+            floating: true,
+            center: true,
+            focus: false,
+            ..Default::default()
+        }]);
+        let space = SpaceId::new(1);
+        let window = WindowId::new(1, 1);
+        let app = Some("com.apple.QuickTimePlayerX");
+
+        let actions = assign(&mut manager, window, space, app, None, None, None, None)
+            .actions
+            .expect("first decision carries the rule's actions");
+        assert!(actions.center && !actions.focus);
+
+        let screen = CGRect::new(CGPoint::new(0.0, 0.0), CGSize::new(1000.0, 800.0));
+        let frame = actions.floating_frame(screen, CGSize::new(200.0, 100.0)).unwrap();
+        assert_eq!(frame.origin, CGPoint::new(400.0, 350.0));
+
+        manager.set_last_rule_decision(space, window, true);
+        let again = assign(&mut manager, window, space, app, None, None, None, None);
+        assert!(again.actions.is_none());
+    }
+
+    #[test]
+    fn explain_app_rules_reports_matches_and_winner() {
+        let rule = |app_id: Option<&str>, title_substring: Option<&str>| AppWorkspaceRule {
+            app_id: app_id.map(Into::into),
+            workspace: None,
+            floating: true,
+            manage: true,
+            app_name: None,
+            title_regex: None,
+            title_substring: title_substring.map(Into::into),
+            ax_role: None,
+            ax_subrole: None,
+            ..Default::default()
+        };
+        let manager = VirtualWorkspaceManager::new_with_rules(vec![
+            rule(Some("com.example.x"), None),
+            rule(None, Some("Dialog")),
+            rule(Some("com.example.x"), Some("Dialog")),
+        ]);
+
+        let explanation = manager.explain_app_rules(&AppRuleQuery {
+            app_id: Some("com.example.x".into()),
+            title: Some("Dialog".into()),
+            ..Default::default()
+        });
+        let matched: Vec<_> = explanation.matches.iter().map(|m| m.index).collect();
+        assert_eq!(matched, vec![0, 1, 2]);
+        assert_eq!(explanation.winner, Some(2));
+        assert!(explanation.reason.contains("share app_id"));
+
+        let explanation = manager.explain_app_rules(&AppRuleQuery {
+            app_id: Some("com.example.y".into()),
+            title: Some("Dialog".into()),
+            ..Default::default()
+        });
+        assert_eq!(explanation.winner, Some(1));
+        assert_eq!(explanation.reason, "rule 1 is the only match");
+
+        let explanation = manager.explain_app_rules(&AppRuleQuery::default());
+        assert_eq!(explanation.winner, None);
+        assert!(explanation.matches.is_empty());
+    }
+
+    #[test]
+    fn remembered_placement_restores_relaunched_windows() {
+        let mut manager = VirtualWorkspaceManager::new();
+        let space = SpaceId::new(1);
+        manager.set_space_display(space, Some("display-a".into()));
+        let workspaces = manager.list_workspaces(space);
+        let frame = CGRect::new(CGPoint::new(10.0, 20.0), CGSize::new(300.0, 200.0));
+        let app = Some("com.example.x");
+
+        let window = WindowId::new(1, 1);
+        assign(&mut manager, window, space, app, None, Some("Notes"), None, None);
+        assert!(manager.assign_window_to_workspace(space, window, workspaces[2].0));
+        assert!(manager.remember_placement(window, "com.example.x", "Notes", true, frame));
+        manager.remove_windows_for_app(1);
+
+        // Windows of apps that were not just launched stay where they open.
+        let running = WindowId::new(4, 1);
+        let assignment =
+            assign(&mut manager, running, space, app, None, Some("Notes"), None, None);
+        assert_ne!(assignment.workspace_id, workspaces[2].0);
+
+        let relaunched = WindowId::new(2, 1);
+        manager.mark_app_launched(relaunched.pid);
+        let assignment =
+            assign(&mut manager, relaunched, space, app, None, Some("Notes"), None, None);
+        assert_eq!(assignment.workspace_id, workspaces[2].0);
+        let placement = manager.take_restored_placement(relaunched).unwrap();
+        assert!(placement.floating);
+        assert_eq!(placement.frame, Some(frame));
+        assert_eq!(placement.display_uuid.as_deref(), Some("display-a"));
+
+        // Only the app's first windows are restored.
+        let later = WindowId::new(2, 2);
+        let assignment = assign(&mut manager, later, space, app, None, Some("Notes"), None, None);
+        assert_ne!(assignment.workspace_id, workspaces[2].0);
+
+        let other = WindowId::new(3, 1);
+        let other_app = Some("com.example.y");
+        let assignment = assign(&mut manager, other, space, other_app, None, None, None, None);
+        assert_ne!(assignment.workspace_id, workspaces[2].0);
+        assert!(manager.take_restored_placement(other).is_none());
+    }
+
+    #[test]
+    fn reapply_rule_workspace_respects_manual_moves() {
+        let rule = |workspace: usize| AppWorkspaceRule {
+            app_id: Some("com.example.x".into()),
+            workspace: Some(WorkspaceSelector::Index(workspace)),
+            floating: false,
+            manage: true,
+            app_name: None,
+            title_regex: None,
+            title_substring: None,
+            ax_role: None,
+            ax_subrole: None,
+            ..Default::default()
+        };
+        let mut manager = VirtualWorkspaceManager::new_with_rules(vec![rule(1)]);
+        let space = SpaceId::new(1);
+        let window = WindowId::new(1, 1);
+        let query = AppRuleQuery {
+            app_id: Some("com.example.x".into()),
+            ..Default::default()
+        };
+        let workspaces: Vec<_> =
+            manager.list_workspaces(space).iter().map(|(id, _)| *id).collect();
+
+        let app = Some("com.example.x");
+        let assignment = assign(&mut manager, window, space, app, None, None, None, None);
+        assert_eq!(assignment.workspace_id, workspaces[1]);
+        assert_eq!(manager.reapply_rule_workspace(window, space, &query), None);
+
+        manager.app_rules = vec![rule(2)];
+        manager.rebuild_app_rule_regex_cache();
+        assert_eq!(manager.reapply_rule_workspace(window, space, &query), Some(workspaces[2]));
+        assert_eq!(manager.workspace_for_window(space, window), Some(workspaces[2]));
+
+        // Once moved by hand, the window stays put.
+        assert!(manager.assign_window_to_workspace(space, window, workspaces[0]));
+        manager.app_rules = vec![rule(1)];
+        manager.rebuild_app_rule_regex_cache();
+        assert_eq!(manager.reapply_rule_workspace(window, space, &query), None);
+        assert_eq!(manager.workspace_for_window(space, window), Some(workspaces[0]));
+    }
+
+    fn global_manager() -> VirtualWorkspaceManager {
+        let mut settings = VirtualWorkspaceSettings::default();
+        settings.default_workspace_count = 4;
+        settings.workspace_model = WorkspaceModel::Global;
+        VirtualWorkspaceManager::new_with_config(&settings)
+    }
+
+    #[test]
+    fn global_workspaces_are_shared_between_spaces() {
+        let mut manager = global_manager();
+        let space1 = SpaceId::new(1);
+        let space2 = SpaceId::new(2);
+
+        let order: Vec<_> =
+            manager.workspace_order(space1).into_iter().map(|(id, _)| id).collect();
+        assert_eq!(order.len(), 4);
+        assert_eq!(manager.active_workspace(space1), Some(order[0]));
+
+        // A second display picks up the first workspace that is not visible anywhere.
+        assert_eq!(
+            manager.workspace_order(space2).into_iter().map(|(id, _)| id).collect::<Vec<_>>(),
+            order
+        );
+        assert_eq!(manager.active_workspace(space2), Some(order[1]));
+        assert_eq!(manager.workspace_space(order[1]), Some(space2));
+        assert_eq!(manager.next_workspace(space2, order[1], None), Some(order[2]));
+    }
+
+    #[test]
+    fn transfer_workspace_moves_windows_and_replaces_active() {
+        let mut manager = global_manager();
+        let space1 = SpaceId::new(1);
+        let space2 = SpaceId::new(2);
+        let order: Vec<_> =
+            manager.workspace_order(space1).into_iter().map(|(id, _)| id).collect();
+        let window = WindowId::new(1, 1);
+        manager.assign_window_to_workspace(space1, window, order[0]);
+        manager.set_active_workspace(space1, order[2]);
+        manager.set_active_workspace(space1, order[0]);
+
+        assert!(manager.transfer_workspace(order[0], space2));
+        assert_eq!(manager.workspace_space(order[0]), Some(space2));
+        assert_eq!(manager.workspace_for_window(space2, window), Some(order[0]));
+        assert_eq!(manager.workspace_for_window(space1, window), None);
+        // The previously active workspace takes over on the old space.
+        assert_eq!(manager.active_workspace(space1), Some(order[2]));
+    }
+
+    #[test]
+    fn workspace_moved_between_displays_rescales_floating_positions() {
+        let mut manager = VirtualWorkspaceManager::new();
+        let space1 = SpaceId::new(1);
+        let space2 = SpaceId::new(2);
+        manager.list_workspaces(space1);
+        manager.list_workspaces(space2);
+        let ws = manager.active_workspace(space1).unwrap();
+        let window = WindowId::new(1, 1);
+        manager.assign_window_to_workspace(space1, window, ws);
+        let from = CGRect::new(CGPoint::new(0.0, 0.0), CGSize::new(1000.0, 800.0));
+        let to = CGRect::new(CGPoint::new(1000.0, 0.0), CGSize::new(2000.0, 1600.0));
+        manager.store_floating_position(
+            space1,
+            ws,
+            window,
+            CGRect::new(CGPoint::new(100.0, 200.0), CGSize::new(300.0, 400.0)),
+        );
+
+        assert!(manager.transfer_workspace(ws, space2));
+        manager.rescale_floating_positions(space2, ws, from, to);
+
+        assert_eq!(manager.workspace_for_window(space2, window), Some(ws));
+        assert_eq!(
+            manager.get_floating_position(space2, ws, window),
+            Some(CGRect::new(CGPoint::new(1200.0, 400.0), CGSize::new(600.0, 800.0)))
+        );
+        assert!(manager.active_workspace(space1).is_some_and(|id| id != ws));
+    }
+
+    #[test]
+    fn workspace_history_steps_back_and_forward() {
+        let mut manager = VirtualWorkspaceManager::new();
+        let space = SpaceId::new(1);
+        let ids: Vec<_> = manager.list_workspaces(space).into_iter().map(|(id, _)| id).collect();
+        manager.set_active_workspace(space, ids[0]);
+        manager.set_active_workspace(space, ids[1]);
+        manager.set_active_workspace(space, ids[2]);
+
+        let step = |manager: &mut VirtualWorkspaceManager, forward: bool| {
+            let target = manager.workspace_history_target(space, forward)?;
+            let previous = manager.active_workspace(space);
+            assert!(manager.step_workspace_history(space, target, forward, previous));
+            Some(target)
+        };
+        assert_eq!(step(&mut manager, false), Some(ids[1]));
+        assert_eq!(step(&mut manager, false), Some(ids[0]));
+        assert_eq!(step(&mut manager, true), Some(ids[1]));
+        assert_eq!(manager.active_workspace(space), Some(ids[1]));
+
+        // A normal switch starts a new branch and drops the forward entries.
+        manager.set_active_workspace(space, ids[3]);
+        assert_eq!(manager.workspace_history_target(space, true), None);
+        assert_eq!(step(&mut manager, false), Some(ids[1]));
+    }
+
+    #[test]
+    fn workspace_history_is_bounded_and_skips_deleted_workspaces() {
+        let mut manager = VirtualWorkspaceManager::new();
+        let space = SpaceId::new(1);
+        let ids: Vec<_> = manager.list_workspaces(space).into_iter().map(|(id, _)| id).collect();
+        for _ in 0..WORKSPACE_HISTORY_LIMIT {
+            manager.set_active_workspace(space, ids[0]);
+            manager.set_active_workspace(space, ids[1]);
+        }
+        assert_eq!(manager.workspace_history[&space].back.len(), WORKSPACE_HISTORY_LIMIT);
+
+        manager.set_active_workspace(space, ids[2]);
+        manager.delete_workspace(space, ids[1]).unwrap();
+        assert_eq!(manager.workspace_history_target(space, false), Some(ids[0]));
+    }
+
+    #[test]
+    fn tagged_windows_show_on_every_tagged_workspace() {
+        let mut manager = VirtualWorkspaceManager::new();
+        let space = SpaceId::new(1);
+        let ids: Vec<_> = manager.list_workspaces(space).into_iter().map(|(id, _)| id).collect();
+        let window = WindowId::new(1, 1);
+        manager.assign_window_to_workspace(space, window, ids[0]);
+        manager.set_active_workspace(space, ids[1]);
+        assert!(!manager.is_window_in_active_workspace(space, window));
+
+        assert!(manager.toggle_window_tag(space, window, ids[1]));
+        assert_eq!(manager.window_tags(space, window), vec![ids[0], ids[1]]);
+        assert!(manager.is_window_in_active_workspace(space, window));
+        assert_eq!(manager.layout_windows(space, ids[0]), vec![window]);
+        assert_eq!(manager.layout_windows(space, ids[1]), vec![window]);
+
+        // Untagging the home workspace hands the window to its remaining tag,
+        // and the last tag cannot be removed.
+        assert!(manager.toggle_window_tag(space, window, ids[0]));
+        assert_eq!(manager.workspace_for_window(space, window), Some(ids[1]));
+        assert!(!manager.toggle_window_tag(space, window, ids[1]));
+        assert!(!manager.has_tags_or_views());
+    }
+
+    #[test]
+    fn viewed_workspaces_join_the_active_layout() {
+        let mut manager = VirtualWorkspaceManager::new();
+        let space = SpaceId::new(1);
+        let ids: Vec<_> = manager.list_workspaces(space).into_iter().map(|(id, _)| id).collect();
+        let first = WindowId::new(1, 1);
+        let second = WindowId::new(2, 1);
+        manager.assign_window_to_workspace(space, first, ids[0]);
+        manager.assign_window_to_workspace(space, second, ids[1]);
+        manager.set_active_workspace(space, ids[0]);
+
+        assert!(!manager.toggle_view(space, ids[0]));
+        assert!(manager.toggle_view(space, ids[1]));
+        assert_eq!(manager.view_workspaces(space), vec![ids[0], ids[1]]);
+        assert_eq!(manager.layout_windows(space, ids[0]), vec![first, second]);
+        assert_eq!(manager.layout_windows(space, ids[1]), vec![second]);
+        assert_eq!(manager.windows_in_active_workspace(space), vec![first, second]);
+        assert!(manager.windows_in_inactive_workspaces(space).is_empty());
+
+        assert!(manager.toggle_view(space, ids[1]));
+        assert_eq!(manager.windows_in_inactive_workspaces(space), vec![second]);
+    }
+
+    #[test]
+    fn orphaned_global_workspaces_are_adopted() {
+        let mut manager = global_manager();
+        let space1 = SpaceId::new(1);
+        let space2 = SpaceId::new(2);
+        let order: Vec<_> =
+            manager.workspace_order(space1).into_iter().map(|(id, _)| id).collect();
+        manager.workspace_order(space2);
+        let window = WindowId::new(1, 1);
+        manager.assign_window_to_workspace(space2, window, order[1]);
+
+        assert!(manager.adopt_orphaned_workspaces(&[space1]));
+        assert_eq!(manager.workspace_space(order[1]), Some(space1));
+        assert_eq!(manager.workspace_for_window(space1, window), Some(order[1]));
+        assert_eq!(manager.active_workspace(space1), Some(order[0]));
+        assert_eq!(manager.active_workspace(space2), None);
+        assert!(!manager.adopt_orphaned_workspaces(&[space1]));
+    }
+
     #[test]
     fn app_rules() {
         let space1 = SpaceId::new(1);
@@ -1478,6 +3415,7 @@ mod tests {
                 title_substring: None,
                 ax_role: None,
                 ax_subrole: None,
+                ..Default::default()
             },
             // Match by app_name -> workspace 1
             AppWorkspaceRule {
@@ -1490,6 +3428,7 @@ mod tests {
                 title_substring: None,
                 ax_role: None,
                 ax_subrole: None,
+                ..Default::default()
             },
             // Title substring -> workspace 0
             AppWorkspaceRule {
@@ -1502,6 +3441,7 @@ mod tests {
                 title_substring: Some("Preferences".into()),
                 ax_role: None,
                 ax_subrole: None,
+                ..Default::default()
             },
             // Title regex -> workspace 2
             AppWorkspaceRule {
@@ -1514,6 +3454,7 @@ mod tests {
                 title_substring: None,
                 ax_role: None,
                 ax_subrole: None,
+                ..Default::default()
             },
             // AX role + subrole floating
             AppWorkspaceRule {
@@ -1526,6 +3467,7 @@ mod tests {
                 title_substring: None,
                 ax_role: Some("AXWindow".into()),
                 ax_subrole: Some("AXDialog".into()),
+                ..Default::default()
             },
             // Workspace by name
             AppWorkspaceRule {
@@ -1538,6 +3480,7 @@ mod tests {
                 title_substring: None,
                 ax_role: None,
                 ax_subrole: None,
+                ..Default::default()
             },
             // Specificity tie breaking generic vs substring (generic workspace 0, specific workspace 2)
             AppWorkspaceRule {
@@ -1550,6 +3493,7 @@ mod tests {
                 title_substring: None,
                 ax_role: None,
                 ax_subrole: None,
+                ..Default::default()
             },
             AppWorkspaceRule {
                 app_id: Some("com.example.tie".into()),
@@ -1561,6 +3505,7 @@ mod tests {
                 title_substring: Some("Editor".into()),
                 ax_role: None,
                 ax_subrole: None,
+                ..Default::default()
             },
             // Reapplication: Bitwarden title becomes floating
             AppWorkspaceRule {
@@ -1573,6 +3518,7 @@ mod tests {
                 title_substring: Some("Bitwarden".into()),
                 ax_role: None,
                 ax_subrole: None,
+                ..Default::default()
             },
             AppWorkspaceRule {
                 app_id: Some("app.zen-browser.zen".into()),
@@ -1584,6 +3530,7 @@ mod tests {
                 title_substring: None,
                 ax_role: None,
                 ax_subrole: None,
+                ..Default::default()
             },
             // Workspace override when specific rule matches different workspace + floating
             AppWorkspaceRule {
@@ -1596,6 +3543,7 @@ mod tests {
                 title_substring: None,
                 ax_role: None,
                 ax_subrole: None,
+                ..Default::default()
             },
             AppWorkspaceRule {
                 app_id: Some("app.zen-browser.zen".into()),
@@ -1607,6 +3555,7 @@ mod tests {
                 title_substring: Some("bitwarden".into()),
                 ax_role: None,
                 ax_subrole: None,
+                ..Default::default()
             },
         ];
 
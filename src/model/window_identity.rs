@@ -0,0 +1,211 @@
+//! Re-associates the windows of a restored layout with live windows.
+//!
+//! A [`WindowId`] is only valid for the lifetime of the process owning the
+//! window, so a layout saved before a restart refers to windows that may have
+//! come back under different ids. Each saved window carries a
+//! [`WindowIdentity`], and live windows are matched against those.
+
+use objc2_core_foundation::CGRect;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+use crate::actor::app::WindowId;
+use crate::common::collections::BTreeMap;
+use crate::model::placement_memory::title_pattern;
+use crate::sys::geometry::CGRectDef;
+use crate::sys::window_server::WindowServerId;
+
+/// Lowest score at which a live window is taken to be a restored one.
+const MIN_MATCH_SCORE: u32 = 3;
+
+/// What is known about a window besides its id.
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WindowIdentity {
+    pub bundle_id: Option<String>,
+    pub title: String,
+    pub window_server_id: Option<WindowServerId>,
+    #[serde_as(as = "CGRectDef")]
+    pub frame: CGRect,
+}
+
+impl WindowIdentity {
+    /// How likely it is that `self` and `other` are the same window, or `None`
+    /// if they cannot be.
+    fn match_score(&self, other: &WindowIdentity) -> Option<u32> {
+        if let (Some(a), Some(b)) = (&self.bundle_id, &other.bundle_id)
+            && a != b
+        {
+            return None;
+        }
+        // Window server ids are reused across reboots, so they only count
+        // together with some other evidence.
+        let same_server_id =
+            self.window_server_id.is_some() && self.window_server_id == other.window_server_id;
+        let title = title_similarity(&self.title, &other.title);
+        let distance = (self.frame.mid().x - other.frame.mid().x).abs()
+            + (self.frame.mid().y - other.frame.mid().y).abs()
+            + (self.frame.size.width - other.frame.size.width).abs()
+            + (self.frame.size.height - other.frame.size.height).abs();
+        let frame = if distance < 8.0 {
+            2
+        } else if distance < 100.0 {
+            1
+        } else {
+            0
+        };
+        let server_id = if same_server_id && title + frame > 0 { 3 } else { 0 };
+        Some(title + frame + server_id)
+    }
+}
+
+/// How alike two window titles are, from 3 (identical) down to 0.
+pub fn title_similarity(a: &str, b: &str) -> u32 {
+    if a == b {
+        3
+    } else if title_pattern(a) == title_pattern(b) {
+        2
+    } else if word_overlap(a, b) >= 0.5 {
+        1
+    } else {
+        0
+    }
+}
+
+/// Share of the words in the shorter title that also appear in the other.
+fn word_overlap(a: &str, b: &str) -> f64 {
+    let words_a: Vec<&str> = a.split_whitespace().collect();
+    let words_b: Vec<&str> = b.split_whitespace().collect();
+    let (shorter, longer) =
+        if words_a.len() <= words_b.len() { (words_a, words_b) } else { (words_b, words_a) };
+    if shorter.is_empty() {
+        return 0.0;
+    }
+    let shared = shorter.iter().filter(|word| longer.contains(word)).count();
+    shared as f64 / shorter.len() as f64
+}
+
+/// Restored windows not yet re-associated with a live window.
+#[derive(Debug, Default)]
+pub struct WindowMatcher {
+    unmatched: BTreeMap<WindowId, WindowIdentity>,
+}
+
+impl WindowMatcher {
+    pub fn new(identities: BTreeMap<WindowId, WindowIdentity>) -> Self {
+        WindowMatcher { unmatched: identities }
+    }
+
+    pub fn is_empty(&self) -> bool { self.unmatched.is_empty() }
+
+    pub fn unmatched(&self) -> &BTreeMap<WindowId, WindowIdentity> { &self.unmatched }
+
+    /// Stops tracking the restored window with id `wid` if the live window
+    /// that now has this id is not it, as after a reboot or a reused pid.
+    /// Returns whether it did.
+    pub fn forget_reused_id(&mut self, wid: WindowId, live: &WindowIdentity) -> bool {
+        let reused = self.unmatched.get(&wid).is_some_and(|restored| {
+            restored.match_score(live).is_none_or(|score| score < MIN_MATCH_SCORE)
+        });
+        if reused {
+            self.unmatched.remove(&wid);
+        }
+        reused
+    }
+
+    /// Finds the restored window that the live window `wid` most likely is,
+    /// and stops tracking it. Returns `wid` itself when it kept its id.
+    pub fn take_match(&mut self, wid: WindowId, live: &WindowIdentity) -> Option<WindowId> {
+        let same_id = self
+            .unmatched
+            .get(&wid)
+            .and_then(|restored| restored.match_score(live))
+            .filter(|&score| score >= MIN_MATCH_SCORE);
+        let best = match same_id {
+            Some(_) => Some(wid),
+            None => self
+                .unmatched
+                .iter()
+                .filter_map(|(&old, restored)| Some((old, restored.match_score(live)?)))
+                .filter(|&(_, score)| score >= MIN_MATCH_SCORE)
+                .max_by(|(a_id, a), (b_id, b)| a.cmp(b).then(b_id.cmp(a_id)))
+                .map(|(old, _)| old),
+        }?;
+        self.unmatched.remove(&best);
+        Some(best)
+    }
+
+    /// Stops tracking every remaining restored window, returning their ids.
+    pub fn take_unmatched(&mut self) -> Vec<WindowId> {
+        std::mem::take(&mut self.unmatched).into_keys().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use objc2_core_foundation::{CGPoint, CGSize};
+
+    use super::*;
+
+    fn identity(bundle_id: &str, title: &str, wsid: u32, x: f64) -> WindowIdentity {
+        WindowIdentity {
+            bundle_id: Some(bundle_id.into()),
+            title: title.into(),
+            window_server_id: Some(WindowServerId(wsid)),
+            frame: CGRect::new(CGPoint::new(x, 0.0), CGSize::new(800.0, 600.0)),
+        }
+    }
+
+    #[test]
+    fn matches_restored_windows_by_identity() {
+        let editor = WindowId::new(10, 100);
+        let terminal = WindowId::new(11, 200);
+        let notes = WindowId::new(12, 300);
+        let mut matcher = WindowMatcher::new(BTreeMap::from([
+            (editor, identity("com.example.editor", "main.rs - project", 100, 0.0)),
+            (terminal, identity("com.example.term", "zsh", 200, 800.0)),
+            (notes, identity("com.example.notes", "Notes", 300, 0.0)),
+        ]));
+
+        // A window that kept its id is matched to itself.
+        let live = identity("com.example.notes", "Notes", 300, 0.0);
+        assert_eq!(matcher.take_match(notes, &live), Some(notes));
+
+        // After a relaunch, the title and frame identify the window.
+        let live = identity("com.example.editor", "lib.rs - project", 555, 0.0);
+        assert_eq!(matcher.take_match(WindowId::new(20, 555), &live), Some(editor));
+
+        // A reused window server id alone is not enough, nor is another app.
+        let live = identity("com.example.term", "htop", 200, 2000.0);
+        assert_eq!(matcher.take_match(WindowId::new(21, 200), &live), None);
+        let live = identity("com.example.other", "zsh", 200, 800.0);
+        assert_eq!(matcher.take_match(WindowId::new(22, 200), &live), None);
+
+        assert_eq!(matcher.take_unmatched(), vec![terminal]);
+        assert!(matcher.is_empty());
+    }
+
+    #[test]
+    fn reused_ids_do_not_take_over_restored_windows() {
+        let editor = WindowId::new(10, 100);
+        let mut matcher = WindowMatcher::new(BTreeMap::from([(
+            editor,
+            identity("com.example.editor", "main.rs - project", 100, 0.0),
+        )]));
+
+        // After a reboot another app's window, or an unrelated window of the
+        // same app, can get the id the editor had.
+        let live = identity("com.example.editor", "Preferences", 400, 2000.0);
+        assert_eq!(matcher.take_match(editor, &live), None);
+        assert!(matcher.forget_reused_id(editor, &live));
+        assert!(matcher.is_empty());
+
+        let mut matcher = WindowMatcher::new(BTreeMap::from([(
+            editor,
+            identity("com.example.editor", "main.rs - project", 100, 0.0),
+        )]));
+        let live = identity("com.example.editor", "main.rs - project", 100, 0.0);
+        assert!(!matcher.forget_reused_id(editor, &live));
+        assert_eq!(matcher.take_match(editor, &live), Some(editor));
+    }
+}